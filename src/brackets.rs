@@ -0,0 +1,265 @@
+use std::borrow::Cow;
+
+use ropey::Rope;
+
+use crate::language::Language;
+
+/// The brackets of a text, ignoring those inside its strings and comments.
+#[derive(Default)]
+pub struct BracketPairs {
+  /// The offsets of every matched pair, in the order they close.
+  pub pairs: Vec<(usize, usize)>,
+  /// The offsets of the opening brackets without a match, in order.
+  unclosed: Vec<usize>,
+}
+
+impl BracketPairs {
+  /// Finds the brackets of `text` a line at a time, so the rope is never
+  /// copied whole.
+  pub fn scan(text: &Rope, language: &Language) -> Self {
+    let mut scanner = Scanner {
+      language,
+      context: Context::Code,
+      open: Vec::new(),
+      brackets: Self::default(),
+    };
+    let mut offset = 0;
+    for line in text.lines() {
+      let len = line.len_bytes();
+      scanner.scan_line(&Cow::from(line), offset);
+      offset += len;
+    }
+
+    let mut brackets = scanner.brackets;
+    brackets
+      .unclosed
+      .extend(scanner.open.into_iter().map(|(ix, _)| ix));
+    brackets.unclosed.sort_unstable();
+    brackets
+  }
+
+  /// The pair to highlight for a cursor at `offset`: the bracket just after
+  /// or just before it, otherwise the innermost pair around it.
+  pub fn pair_for_cursor(&self, offset: usize) -> Option<(usize, usize)> {
+    // Brackets are all ASCII, so the one before the cursor starts a byte before.
+    let touching = |ix: usize| {
+      self
+        .pairs
+        .iter()
+        .find(|&&(open, close)| open == ix || close == ix)
+        .copied()
+    };
+    touching(offset)
+      .or_else(|| touching(offset.checked_sub(1)?))
+      .or_else(|| self.enclosing_pair(offset))
+  }
+
+  /// The innermost pair with its opener before `offset` and its closer at or
+  /// after it.
+  pub fn enclosing_pair(&self, offset: usize) -> Option<(usize, usize)> {
+    // Pairs close innermost first.
+    self
+      .pairs
+      .iter()
+      .find(|&&(open, close)| open < offset && offset <= close)
+      .copied()
+  }
+
+  /// The innermost opening bracket before `offset` that isn't closed before
+  /// it, whether it has a match or not.
+  pub fn enclosing_open(&self, offset: usize) -> Option<usize> {
+    let open = self.enclosing_pair(offset).map(|(open, _)| open);
+    // Openers left unclosed inside a pair that ends before `offset` don't
+    // enclose it.
+    let unclosed = self.unclosed.iter().rev().copied().find(|&unclosed| {
+      unclosed < offset
+        && !self
+          .pairs
+          .iter()
+          .any(|&(open, close)| open < unclosed && unclosed < close && close < offset)
+    });
+    open.max(unclosed)
+  }
+}
+
+/// What a line starts inside of, as strings and block comments can span
+/// lines.
+#[derive(Clone, Copy)]
+enum Context {
+  Code,
+  BlockComment { end: &'static str },
+  String { quote: char, escaped: bool },
+}
+
+struct Scanner<'a> {
+  language: &'a Language,
+  context: Context,
+  /// The opening brackets not closed yet, with their offsets.
+  open: Vec<(usize, char)>,
+  brackets: BracketPairs,
+}
+
+impl Scanner<'_> {
+  /// Scans `line`, including its line break, which starts at `offset`.
+  fn scan_line(&mut self, line: &str, offset: usize) {
+    let language = self.language;
+    let mut ix = 0;
+    while let Some(ch) = line[ix..].chars().next() {
+      let rest = &line[ix..];
+      match self.context {
+        Context::BlockComment { end } => {
+          let Some(len) = rest.find(end) else {
+            return;
+          };
+          ix += len + end.len();
+          self.context = Context::Code;
+        }
+        Context::String { quote, mut escaped } => {
+          let closed = rest.char_indices().find_map(|(len, ch)| {
+            let closed = ch == quote && !escaped;
+            escaped = ch == '\\' && !escaped;
+            closed.then_some(len + ch.len_utf8())
+          });
+          let Some(len) = closed else {
+            self.context = Context::String { quote, escaped };
+            return;
+          };
+          ix += len;
+          self.context = Context::Code;
+        }
+        Context::Code => {
+          if let Some((start, end)) = language.block_comment
+            && rest.starts_with(start)
+          {
+            ix += start.len();
+            self.context = Context::BlockComment { end };
+          } else if let Some(token) = language.line_comment
+            && rest.starts_with(token)
+          {
+            return;
+          } else if language.string_quotes.contains(&ch) {
+            ix += ch.len_utf8();
+            self.context = Context::String {
+              quote: ch,
+              escaped: false,
+            };
+          } else if let Some(quote) = language.char_quote
+            && ch == quote
+            && let Some(len) = char_literal_len(rest, quote)
+          {
+            ix += len;
+          } else {
+            self.bracket(ch, offset + ix);
+            ix += ch.len_utf8();
+          }
+        }
+      }
+    }
+  }
+
+  fn bracket(&mut self, ch: char, offset: usize) {
+    if self.language.closing_bracket(ch).is_some() {
+      self.open.push((offset, ch));
+    } else if let Some(opening) = self.language.opening_bracket(ch)
+      // An unmatched closer is skipped, along with the openers left unclosed
+      // inside the pair it does close.
+      && let Some(depth) = self.open.iter().rposition(|&(_, ch)| ch == opening)
+    {
+      self.brackets.pairs.push((self.open[depth].0, offset));
+      self
+        .brackets
+        .unclosed
+        .extend(self.open.drain(depth..).skip(1).map(|(ix, _)| ix));
+    }
+  }
+}
+
+/// The length of the character literal at the start of `rest`, if the quote
+/// there opens one rather than a lifetime or label.
+fn char_literal_len(rest: &str, quote: char) -> Option<usize> {
+  let mut chars = rest.char_indices().skip(1);
+  let (_, first) = chars.next()?;
+  if first == quote || first == '\n' {
+    return None;
+  }
+  let (len, ch) = if first == '\\' {
+    // Skip the escaped character, then allow for escapes like `\x7f` and
+    // `\u{1F600}`.
+    chars.next()?;
+    chars.take(8).find(|&(_, ch)| ch == quote || ch == '\n')?
+  } else {
+    chars.next()?
+  };
+  (ch == quote).then_some(len + ch.len_utf8())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use super::*;
+
+  fn rust() -> &'static Language {
+    Language::for_path(Path::new("main.rs")).unwrap()
+  }
+
+  fn bracket_pairs(text: &str) -> BracketPairs {
+    BracketPairs::scan(&Rope::from_str(text), rust())
+  }
+
+  #[test]
+  fn pairs_close_innermost_first() {
+    assert_eq!(
+      bracket_pairs("f(a[0], { b })").pairs,
+      [(3, 5), (8, 12), (1, 13)]
+    );
+  }
+
+  #[test]
+  fn strings_and_comments_are_skipped() {
+    assert_eq!(bracket_pairs("(\")\" /* ( */ // )\n)").pairs, [(0, 18)]);
+
+    // Strings and block comments go on across lines, line comments don't.
+    let text = "(\"\n)\\\"\n\" /*\n)*/ //\n)";
+    assert_eq!(bracket_pairs(text).pairs, [(0, 19)]);
+  }
+
+  #[test]
+  fn char_literals_are_skipped() {
+    let text = r"f('(', '\'', '\u{28}', ')')";
+    assert_eq!(bracket_pairs(text).pairs, [(1, 26)]);
+  }
+
+  #[test]
+  fn lifetimes_are_not_char_literals() {
+    let text = "fn f<'a>(x: &'a str) -> &'a str { x }";
+    assert_eq!(bracket_pairs(text).pairs, [(8, 19), (32, 36)]);
+  }
+
+  #[test]
+  fn cursor_pair() {
+    let brackets = bracket_pairs("a(b[c]de)");
+    // On the bracket after the cursor, then the one before it.
+    assert_eq!(brackets.pair_for_cursor(3), Some((3, 5)));
+    assert_eq!(brackets.pair_for_cursor(6), Some((3, 5)));
+    // Otherwise the innermost pair around it.
+    assert_eq!(brackets.pair_for_cursor(7), Some((1, 8)));
+    assert_eq!(brackets.pair_for_cursor(0), None);
+  }
+
+  #[test]
+  fn enclosing_brackets() {
+    let brackets = bracket_pairs("{ a(b) [c }");
+    assert_eq!(brackets.enclosing_pair(4), Some((3, 5)));
+    assert_eq!(brackets.enclosing_pair(6), Some((0, 10)));
+    assert_eq!(brackets.enclosing_pair(0), None);
+
+    // An opener without a match still encloses what follows it.
+    assert_eq!(brackets.enclosing_open(9), Some(7));
+    assert_eq!(brackets.enclosing_open(6), Some(0));
+    // But not once the pair around it has closed.
+    let brackets = bracket_pairs("{ ( }\n");
+    assert_eq!(brackets.enclosing_open(4), Some(2));
+    assert_eq!(brackets.enclosing_open(6), None);
+  }
+}
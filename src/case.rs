@@ -0,0 +1,143 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Case {
+  Upper,
+  Lower,
+  Title,
+  Snake,
+  Camel,
+  Kebab,
+  Pascal,
+}
+
+/// Converts `text` to `case`. Identifier cases are applied line by line,
+/// keeping each line's surrounding whitespace.
+pub fn convert(text: &str, case: Case) -> String {
+  match case {
+    Case::Upper => text.to_uppercase(),
+    Case::Lower => text.to_lowercase(),
+    Case::Title => title_case(text),
+    Case::Snake | Case::Camel | Case::Kebab | Case::Pascal => text
+      .split('\n')
+      .map(|line| {
+        let core = line.trim();
+        let start = line.len() - line.trim_start().len();
+        format!(
+          "{}{}{}",
+          &line[..start],
+          join_words(&words(core), case),
+          &line[start + core.len()..]
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n"),
+  }
+}
+
+fn title_case(text: &str) -> String {
+  let mut result = String::with_capacity(text.len());
+  let mut at_word_start = true;
+  for ch in text.chars() {
+    if at_word_start {
+      result.extend(ch.to_uppercase());
+    } else {
+      result.extend(ch.to_lowercase());
+    }
+    at_word_start = ch.is_whitespace();
+  }
+  result
+}
+
+/// Splits an identifier or phrase into lowercase words at separators and
+/// case changes, keeping acronyms together (`HTTPServer` is `http`, `server`).
+fn words(text: &str) -> Vec<String> {
+  let chars = text.chars().collect::<Vec<_>>();
+  let mut words = Vec::new();
+  let mut word = String::new();
+
+  for (ix, &ch) in chars.iter().enumerate() {
+    if !ch.is_alphanumeric() {
+      if !word.is_empty() {
+        words.push(std::mem::take(&mut word));
+      }
+      continue;
+    }
+
+    if ch.is_uppercase() && !word.is_empty() {
+      let prev = chars[ix - 1];
+      let next_is_lower = chars.get(ix + 1).is_some_and(|next| next.is_lowercase());
+      if !prev.is_uppercase() || next_is_lower {
+        words.push(std::mem::take(&mut word));
+      }
+    }
+    word.extend(ch.to_lowercase());
+  }
+  if !word.is_empty() {
+    words.push(word);
+  }
+  words
+}
+
+fn join_words(words: &[String], case: Case) -> String {
+  let capitalize = |word: &String| {
+    let mut chars = word.chars();
+    chars
+      .next()
+      .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+      .unwrap_or_default()
+  };
+
+  match case {
+    Case::Snake => words.join("_"),
+    Case::Kebab => words.join("-"),
+    Case::Pascal => words.iter().map(capitalize).collect(),
+    Case::Camel => words
+      .iter()
+      .enumerate()
+      .map(|(ix, word)| {
+        if ix == 0 {
+          word.clone()
+        } else {
+          capitalize(word)
+        }
+      })
+      .collect(),
+    Case::Upper | Case::Lower | Case::Title => words.join(" "),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn letter_cases() {
+    assert_eq!(convert("Hello wörld", Case::Upper), "HELLO WÖRLD");
+    assert_eq!(convert("Hello WÖRLD", Case::Lower), "hello wörld");
+    assert_eq!(
+      convert("hello wORLD\nagain", Case::Title),
+      "Hello World\nAgain"
+    );
+  }
+
+  #[test]
+  fn identifier_cases() {
+    assert_eq!(convert("fooBar baz", Case::Snake), "foo_bar_baz");
+    assert_eq!(convert("foo_bar-baz", Case::Camel), "fooBarBaz");
+    assert_eq!(convert("FooBar2Baz", Case::Kebab), "foo-bar2-baz");
+    assert_eq!(convert("some-name", Case::Pascal), "SomeName");
+  }
+
+  #[test]
+  fn acronyms_stay_together() {
+    assert_eq!(convert("HTTPServer", Case::Snake), "http_server");
+    assert_eq!(convert("parseURL", Case::Kebab), "parse-url");
+    assert_eq!(convert("XML_http_request", Case::Pascal), "XmlHttpRequest");
+  }
+
+  #[test]
+  fn lines_keep_their_whitespace() {
+    let text = "  fooBar\n\tbaz_qux  \n";
+    assert_eq!(convert(text, Case::Snake), "  foo_bar\n\tbaz_qux  \n");
+    assert_eq!(convert(text, Case::Camel), "  fooBar\n\tbazQux  \n");
+  }
+}
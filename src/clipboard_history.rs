@@ -0,0 +1,143 @@
+use std::{
+  collections::VecDeque,
+  hash::{DefaultHasher, Hash, Hasher},
+};
+
+use gpui::{Global, SharedString, actions};
+
+actions!(clipboard_history, [PasteFromHistory]);
+
+const MAX_ENTRIES: usize = 50;
+/// The most text kept in the history, across its entries. Older entries
+/// are dropped to stay within it, and larger texts aren't kept at all.
+const MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// Text copied or cut in one go.
+#[derive(Clone, Debug)]
+pub struct ClipboardEntry {
+  pub text: SharedString,
+  /// The text's hash, so recording an entry doesn't compare its text with
+  /// every other.
+  hash: u64,
+}
+
+impl ClipboardEntry {
+  pub fn new(text: impl Into<SharedString>) -> Self {
+    let text = text.into();
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    Self {
+      hash: hasher.finish(),
+      text,
+    }
+  }
+
+  /// A one-line summary for the history picker.
+  pub fn preview(&self) -> String {
+    let mut preview = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((end, _)) = preview.char_indices().nth(80) {
+      preview.truncate(end);
+      preview.push('…');
+    }
+    preview
+  }
+}
+
+/// The text copied and cut in the app, most recent first.
+#[derive(Default)]
+pub struct ClipboardHistory {
+  entries: VecDeque<ClipboardEntry>,
+  /// The length of all the entries' text.
+  bytes: usize,
+}
+
+impl Global for ClipboardHistory {}
+
+impl ClipboardHistory {
+  /// Records an entry, moving it to the front if it's already present.
+  pub fn push(&mut self, entry: ClipboardEntry) {
+    if entry.text.len() > MAX_BYTES {
+      return;
+    }
+    if let Some(ix) = self
+      .entries
+      .iter()
+      .position(|existing| existing.hash == entry.hash && existing.text == entry.text)
+      && let Some(existing) = self.entries.remove(ix)
+    {
+      self.bytes -= existing.text.len();
+    }
+    self.bytes += entry.text.len();
+    self.entries.push_front(entry);
+    while self.entries.len() > MAX_ENTRIES || self.bytes > MAX_BYTES {
+      let Some(oldest) = self.entries.pop_back() else {
+        break;
+      };
+      self.bytes -= oldest.text.len();
+    }
+  }
+
+  pub fn entries(&self) -> &VecDeque<ClipboardEntry> {
+    &self.entries
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn texts(history: &ClipboardHistory) -> Vec<&str> {
+    history
+      .entries()
+      .iter()
+      .map(|entry| entry.text.as_ref())
+      .collect()
+  }
+
+  #[test]
+  fn recopied_text_moves_to_the_front() {
+    let mut history = ClipboardHistory::default();
+    for text in ["a", "b", "c", "a"] {
+      history.push(ClipboardEntry::new(text));
+    }
+    assert_eq!(texts(&history), ["a", "c", "b"]);
+    assert_eq!(history.bytes, 3);
+  }
+
+  #[test]
+  fn oldest_entries_are_dropped() {
+    let mut history = ClipboardHistory::default();
+    for ix in 0..MAX_ENTRIES + 5 {
+      history.push(ClipboardEntry::new(ix.to_string()));
+    }
+    assert_eq!(history.entries().len(), MAX_ENTRIES);
+    assert_eq!(history.entries()[0].text, (MAX_ENTRIES + 4).to_string());
+    assert_eq!(history.entries()[MAX_ENTRIES - 1].text, "5");
+  }
+
+  #[test]
+  fn entries_stay_within_the_byte_budget() {
+    let mut history = ClipboardHistory::default();
+    let large = |ch: &str| ch.repeat(MAX_BYTES / 3);
+    for ch in ["a", "b", "c", "d"] {
+      history.push(ClipboardEntry::new(large(ch)));
+    }
+    assert_eq!(history.entries().len(), 3);
+    assert!(history.bytes <= MAX_BYTES);
+    assert!(history.entries()[0].text.starts_with('d'));
+
+    // Text larger than the whole budget isn't kept.
+    history.push(ClipboardEntry::new("x".repeat(MAX_BYTES + 1)));
+    assert_eq!(history.entries().len(), 3);
+    assert!(history.entries()[0].text.starts_with('d'));
+  }
+
+  #[test]
+  fn preview_is_one_shortened_line() {
+    let entry = ClipboardEntry::new(format!("fn main() {{\n  {}\n}}", "x".repeat(100)));
+    let preview = entry.preview();
+    assert!(preview.starts_with("fn main() { xx"));
+    assert_eq!(preview.chars().count(), 81);
+    assert!(preview.ends_with('…'));
+  }
+}
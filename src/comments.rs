@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use crate::text_input::leading_whitespace;
+
+/// Toggles line comments on a run of lines: comments them out at their
+/// minimum indentation, or uncomments them when every non-blank one is
+/// already commented.
+pub struct LineComments<'a> {
+  token: &'a str,
+  commented: bool,
+  indent: usize,
+}
+
+impl<'a> LineComments<'a> {
+  /// Decides how to toggle `lines`, or `None` if they're all blank.
+  pub fn new(lines: &str, token: &'a str) -> Option<Self> {
+    let non_blank = lines
+      .split('\n')
+      .filter(|line| !line.trim().is_empty())
+      .collect::<Vec<_>>();
+    let indent = non_blank
+      .iter()
+      .map(|line| leading_whitespace(line).len())
+      .min()?;
+    let commented = non_blank
+      .iter()
+      .all(|line| line[leading_whitespace(line).len()..].starts_with(token));
+    Some(Self {
+      token,
+      commented,
+      indent,
+    })
+  }
+
+  /// `line` with its comment added or removed. Blank lines are left alone.
+  pub fn toggle(&self, line: &str) -> String {
+    let token = self.token;
+    if line.trim().is_empty() {
+      line.to_string()
+    } else if self.commented {
+      let indent = leading_whitespace(line);
+      let rest = &line[indent.len()..];
+      let rest = rest.strip_prefix(token).unwrap_or(rest);
+      format!("{indent}{}", rest.strip_prefix(' ').unwrap_or(rest))
+    } else {
+      let indent = self.indent.min(leading_whitespace(line).len());
+      format!("{}{token} {}", &line[..indent], &line[indent..])
+    }
+  }
+}
+
+/// The edit toggling a block comment around `selected`: the range of it to
+/// replace, the replacement and the range within the replacement to select.
+/// A selection already wrapped in `open` and `close`, ignoring whitespace
+/// around it, is unwrapped.
+pub fn toggle_block_comment(
+  selected: &str,
+  open: &str,
+  close: &str,
+) -> (Range<usize>, String, Range<usize>) {
+  let trimmed = selected.trim();
+  if let Some(inner) = trimmed
+    .strip_prefix(open)
+    .and_then(|rest| rest.strip_suffix(close))
+  {
+    let inner = inner.strip_prefix(' ').unwrap_or(inner);
+    let inner = inner.strip_suffix(' ').unwrap_or(inner);
+    let start = selected.len() - selected.trim_start().len();
+    (
+      start..start + trimmed.len(),
+      inner.to_string(),
+      0..inner.len(),
+    )
+  } else {
+    let inner_start = open.len() + 1;
+    (
+      0..selected.len(),
+      format!("{open} {selected} {close}"),
+      inner_start..inner_start + selected.len(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn toggle_lines(text: &str) -> Option<String> {
+    let comments = LineComments::new(text, "//")?;
+    Some(
+      text
+        .split('\n')
+        .map(|line| comments.toggle(line))
+        .collect::<Vec<_>>()
+        .join("\n"),
+    )
+  }
+
+  #[test]
+  fn lines_are_commented_at_their_minimum_indentation() {
+    assert_eq!(
+      toggle_lines("  if x {\n    y();\n\n  }").unwrap(),
+      "  // if x {\n  //   y();\n\n  // }"
+    );
+  }
+
+  #[test]
+  fn lines_are_uncommented_when_all_are_commented() {
+    assert_eq!(
+      toggle_lines("  // a\n\n    //b\n  //  c").unwrap(),
+      "  a\n\n    b\n   c"
+    );
+    // A single uncommented line comments them all.
+    assert_eq!(toggle_lines("// a\nb").unwrap(), "// // a\n// b");
+  }
+
+  #[test]
+  fn blank_lines_alone_are_left_as_they_are() {
+    assert!(toggle_lines("\n  \n").is_none());
+  }
+
+  #[test]
+  fn block_comments() {
+    assert_eq!(
+      toggle_block_comment("a + b", "/*", "*/"),
+      (0..5, "/* a + b */".to_string(), 3..8)
+    );
+    // Whitespace around a wrapped selection stays outside the edit.
+    assert_eq!(
+      toggle_block_comment("  /* a + b */\n", "/*", "*/"),
+      (2..13, "a + b".to_string(), 0..5)
+    );
+    assert_eq!(
+      toggle_block_comment("<!--x-->", "<!--", "-->"),
+      (0..8, "x".to_string(), 0..1)
+    );
+  }
+}
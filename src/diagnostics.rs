@@ -0,0 +1,254 @@
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+  Error,
+  Warning,
+  Note,
+}
+
+impl Severity {
+  /// The severity named at the start of a compiler message, like the
+  /// `error` of `error[E0308]: mismatched types`, with the rest of it.
+  fn parse_prefix(message: &str) -> Option<(Self, &str)> {
+    let (severity, rest) = [
+      ("error", Self::Error),
+      ("warning", Self::Warning),
+      ("note", Self::Note),
+    ]
+    .into_iter()
+    .find_map(|(name, severity)| Some((severity, message.strip_prefix(name)?)))?;
+    let rest = match rest.strip_prefix('[') {
+      Some(rest) => &rest[rest.find(']')? + 1..],
+      None => rest,
+    };
+    Some((severity, rest.strip_prefix(':')?.trim_start()))
+  }
+}
+
+/// A problem reported by a tool at a position in a buffer.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+  /// The zero-based line.
+  pub line: usize,
+  /// The zero-based column, in graphemes.
+  pub column: usize,
+  pub severity: Severity,
+  pub message: String,
+}
+
+/// A diagnostic found in the output of a task.
+#[derive(Clone, Debug)]
+pub struct Problem {
+  pub path: PathBuf,
+  pub diagnostic: Diagnostic,
+}
+
+/// Finds compiler-style problems in output read line by line. Both
+/// `path:line:col: message` and rustc's message followed by a
+/// `--> path:line:col` line are understood. Paths are relative to the
+/// directory the task ran in and only those of existing files match.
+pub struct ProblemMatcher {
+  root: PathBuf,
+  /// The last message seen, for a following `-->` line to locate.
+  message: Option<(Severity, String)>,
+}
+
+impl ProblemMatcher {
+  pub fn new(root: PathBuf) -> Self {
+    Self {
+      root,
+      message: None,
+    }
+  }
+
+  pub fn match_line(&mut self, line: &str) -> Option<Problem> {
+    let trimmed = line.trim();
+    if let Some(location) = trimmed.strip_prefix("--> ") {
+      let (path, line, column, _) = self.parse_location(location)?;
+      let (severity, message) = self.message.clone()?;
+      return Some(self.problem(path, line, column, severity, message));
+    }
+    if let Some((severity, message)) = Severity::parse_prefix(trimmed) {
+      self.message = Some((severity, message.to_string()));
+      return None;
+    }
+
+    let (path, line, column, message) = self.parse_location(trimmed)?;
+    let message = message.trim_start_matches(':').trim();
+    let (severity, message) = Severity::parse_prefix(message).unwrap_or((Severity::Error, message));
+    Some(self.problem(path, line, column, severity, message.to_string()))
+  }
+
+  fn problem(
+    &self,
+    path: PathBuf,
+    line: usize,
+    column: usize,
+    severity: Severity,
+    message: String,
+  ) -> Problem {
+    Problem {
+      path,
+      diagnostic: Diagnostic {
+        line: line.saturating_sub(1),
+        column: column.saturating_sub(1),
+        severity,
+        message,
+      },
+    }
+  }
+
+  /// Splits `path:line[:column]` off the start of `text`, returning the
+  /// canonical path, the one-based line and column and what follows.
+  fn parse_location<'a>(&self, text: &'a str) -> Option<(PathBuf, usize, usize, &'a str)> {
+    // Paths can themselves hold colons, like Windows drive letters, so each
+    // colon followed by a number is tried in turn.
+    for (ix, _) in text.match_indices(':') {
+      let path = &text[..ix];
+      let Some((line, rest)) = parse_number(&text[ix + 1..]) else {
+        continue;
+      };
+      if path.is_empty() || path.contains(char::is_whitespace) {
+        return None;
+      }
+      let (column, rest) = rest
+        .strip_prefix(':')
+        .and_then(parse_number)
+        .unwrap_or((1, rest));
+      let path = self.resolve(Path::new(path))?;
+      return Some((path, line, column, rest));
+    }
+    None
+  }
+
+  fn resolve(&self, path: &Path) -> Option<PathBuf> {
+    let path = self.root.join(path);
+    path.is_file().then(|| path.canonicalize().unwrap_or(path))
+  }
+}
+
+/// Splits a decimal number off the start of `text`.
+fn parse_number(text: &str) -> Option<(usize, &str)> {
+  let len = text
+    .find(|c: char| !c.is_ascii_digit())
+    .unwrap_or(text.len());
+  Some((text[..len].parse().ok()?, &text[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use super::*;
+
+  /// A directory holding the given empty files, unique to the test.
+  fn root(name: &str, files: &[&str]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("zeta-{name}-{}", std::process::id()));
+    for file in files {
+      let path = root.join(file);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, "").unwrap();
+    }
+    root
+  }
+
+  fn location(problem: &Problem) -> (PathBuf, usize, usize) {
+    (
+      problem.path.clone(),
+      problem.diagnostic.line,
+      problem.diagnostic.column,
+    )
+  }
+
+  #[test]
+  fn path_line_column_message() {
+    let root = root("problems-inline", &["src/main.rs"]);
+    let main = root.join("src/main.rs").canonicalize().unwrap();
+    let mut matcher = ProblemMatcher::new(root.clone());
+
+    let problem = matcher
+      .match_line("src/main.rs:12:5: error: expected `;`")
+      .unwrap();
+    assert_eq!(location(&problem), (main.clone(), 11, 4));
+    assert_eq!(problem.diagnostic.severity, Severity::Error);
+    assert_eq!(problem.diagnostic.message, "expected `;`");
+
+    let problem = matcher
+      .match_line("src/main.rs:3: warning: unused")
+      .unwrap();
+    assert_eq!(location(&problem), (main.clone(), 2, 0));
+    assert_eq!(problem.diagnostic.severity, Severity::Warning);
+
+    // Without a severity, the message is taken to be an error.
+    let problem = matcher.match_line("src/main.rs:1:1: oops").unwrap();
+    assert_eq!(problem.diagnostic.severity, Severity::Error);
+    assert_eq!(problem.diagnostic.message, "oops");
+
+    fs::remove_dir_all(&root).ok();
+  }
+
+  #[test]
+  fn rustc_message_then_location() {
+    let root = root("problems-rustc", &["src/lib.rs"]);
+    let lib = root.join("src/lib.rs").canonicalize().unwrap();
+    let mut matcher = ProblemMatcher::new(root.clone());
+
+    assert!(
+      matcher
+        .match_line("error[E0308]: mismatched types")
+        .is_none()
+    );
+    let problem = matcher.match_line("   --> src/lib.rs:4:18").unwrap();
+    assert_eq!(location(&problem), (lib.clone(), 3, 17));
+    assert_eq!(problem.diagnostic.severity, Severity::Error);
+    assert_eq!(problem.diagnostic.message, "mismatched types");
+
+    assert!(
+      matcher
+        .match_line("warning: unused variable: `x`")
+        .is_none()
+    );
+    let problem = matcher.match_line("  --> src/lib.rs:9:7").unwrap();
+    assert_eq!(location(&problem), (lib, 8, 6));
+    assert_eq!(problem.diagnostic.severity, Severity::Warning);
+    assert_eq!(problem.diagnostic.message, "unused variable: `x`");
+
+    fs::remove_dir_all(&root).ok();
+  }
+
+  // Backslashes are part of the file name on Unix, which lets the drive
+  // letter's colon be tested without a Windows path to resolve.
+  #[cfg(unix)]
+  #[test]
+  fn windows_drive_paths() {
+    let root = root("problems-windows", &["C:\\src\\main.rs"]);
+    let main = root.join("C:\\src\\main.rs").canonicalize().unwrap();
+    let mut matcher = ProblemMatcher::new(root.clone());
+
+    let problem = matcher
+      .match_line("C:\\src\\main.rs:7:2: error: unexpected token")
+      .unwrap();
+    assert_eq!(location(&problem), (main, 6, 1));
+    assert_eq!(problem.diagnostic.message, "unexpected token");
+
+    fs::remove_dir_all(&root).ok();
+  }
+
+  #[test]
+  fn non_matches() {
+    let root = root("problems-none", &["src/main.rs"]);
+    let mut matcher = ProblemMatcher::new(root.clone());
+
+    // Missing files, ordinary output, times and paths with spaces.
+    assert!(matcher.match_line("src/missing.rs:1:1: error: x").is_none());
+    assert!(matcher.match_line("   Compiling zeta v0.1.0").is_none());
+    assert!(matcher.match_line("Finished at 12:30:01").is_none());
+    assert!(matcher.match_line("see src/main.rs:1:1").is_none());
+    assert!(matcher.match_line("src/main.rs: no line").is_none());
+    // A location line with no message before it.
+    assert!(matcher.match_line("--> src/main.rs:1:1").is_none());
+
+    fs::remove_dir_all(&root).ok();
+  }
+}
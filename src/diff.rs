@@ -0,0 +1,131 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+  Equal,
+  Deleted,
+  Inserted,
+}
+
+/// Above this many line pairs the changed region is shown as entirely
+/// replaced rather than paying for the quadratic comparison.
+const MAX_CELLS: usize = 4_000_000;
+
+/// A line diff from `old` to `new`. Lines keep no line breaks.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<(DiffKind, &'a str)> {
+  let old = old.lines().collect::<Vec<_>>();
+  let new = new.lines().collect::<Vec<_>>();
+  let prefix = old
+    .iter()
+    .zip(&new)
+    .take_while(|(old, new)| old == new)
+    .count();
+  let suffix = old[prefix..]
+    .iter()
+    .rev()
+    .zip(new[prefix..].iter().rev())
+    .take_while(|(old, new)| old == new)
+    .count();
+  let old_middle = &old[prefix..old.len() - suffix];
+  let new_middle = &new[prefix..new.len() - suffix];
+
+  let mut result = old[..prefix]
+    .iter()
+    .map(|&line| (DiffKind::Equal, line))
+    .collect::<Vec<_>>();
+  if old_middle.len() * new_middle.len() > MAX_CELLS {
+    result.extend(old_middle.iter().map(|&line| (DiffKind::Deleted, line)));
+    result.extend(new_middle.iter().map(|&line| (DiffKind::Inserted, line)));
+  } else {
+    result.extend(lcs_diff(old_middle, new_middle));
+  }
+  result.extend(
+    old[old.len() - suffix..]
+      .iter()
+      .map(|&line| (DiffKind::Equal, line)),
+  );
+  result
+}
+
+/// Diffs by the longest common subsequence of lines.
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(DiffKind, &'a str)> {
+  // lengths[i][j] is the LCS length of old[i..] and new[j..].
+  let width = new.len() + 1;
+  let mut lengths = vec![0usize; (old.len() + 1) * width];
+  for i in (0..old.len()).rev() {
+    for j in (0..new.len()).rev() {
+      lengths[i * width + j] = if old[i] == new[j] {
+        lengths[(i + 1) * width + j + 1] + 1
+      } else {
+        lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+      };
+    }
+  }
+
+  let (mut i, mut j) = (0, 0);
+  let mut result = Vec::new();
+  while i < old.len() || j < new.len() {
+    if i < old.len() && j < new.len() && old[i] == new[j] {
+      result.push((DiffKind::Equal, old[i]));
+      i += 1;
+      j += 1;
+    } else if i < old.len()
+      && (j == new.len() || lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+    {
+      result.push((DiffKind::Deleted, old[i]));
+      i += 1;
+    } else {
+      result.push((DiffKind::Inserted, new[j]));
+      j += 1;
+    }
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::DiffKind::*;
+  use super::*;
+
+  #[test]
+  fn unchanged_lines() {
+    assert_eq!(diff_lines("a\nb", "a\nb"), [(Equal, "a"), (Equal, "b")]);
+    // Line breaks aren't compared.
+    assert_eq!(diff_lines("a\r\nb\n", "a\nb"), [(Equal, "a"), (Equal, "b")]);
+  }
+
+  #[test]
+  fn insertions() {
+    assert_eq!(
+      diff_lines("a\nc", "a\nb\nc"),
+      [(Equal, "a"), (Inserted, "b"), (Equal, "c")]
+    );
+    assert_eq!(diff_lines("", "a\nb"), [(Inserted, "a"), (Inserted, "b")]);
+  }
+
+  #[test]
+  fn deletions() {
+    assert_eq!(
+      diff_lines("a\nb\nc", "a\nc"),
+      [(Equal, "a"), (Deleted, "b"), (Equal, "c")]
+    );
+    assert_eq!(diff_lines("a\nb", ""), [(Deleted, "a"), (Deleted, "b")]);
+  }
+
+  #[test]
+  fn replacements() {
+    assert_eq!(
+      diff_lines("a\nb\nc", "a\nx\nc"),
+      [(Equal, "a"), (Deleted, "b"), (Inserted, "x"), (Equal, "c")]
+    );
+    // Lines common to both sides are kept between the changes.
+    assert_eq!(
+      diff_lines("a\nb\nc\nd", "b\nx\nd"),
+      [
+        (Deleted, "a"),
+        (Equal, "b"),
+        (Deleted, "c"),
+        (Inserted, "x"),
+        (Equal, "d"),
+      ]
+    );
+  }
+}
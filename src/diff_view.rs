@@ -0,0 +1,134 @@
+use std::fs;
+
+use gpui::{
+  App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, MouseButton,
+  SharedString, Window, div, prelude::*, px, rgb,
+};
+
+use crate::{
+  diff::{DiffKind, diff_lines},
+  text_input::{Cancel, TextInput},
+  ui::button,
+};
+
+/// Compares the editor's unsaved content with the file on disk, offering to
+/// reload the file or keep the editor's content.
+pub struct DiffView {
+  editor: Entity<TextInput>,
+  lines: Result<Vec<(DiffKind, SharedString)>, SharedString>,
+  focus_handle: FocusHandle,
+}
+
+impl EventEmitter<DismissEvent> for DiffView {}
+
+impl DiffView {
+  pub fn new(editor: Entity<TextInput>, cx: &mut Context<Self>) -> Self {
+    let editor_state = editor.read(cx);
+    let lines = editor_state
+      .path
+      .as_ref()
+      .ok_or_else(|| "The buffer has no file".into())
+      .and_then(|path| {
+        fs::read(path)
+          .and_then(|bytes| editor_state.encoding.decode(bytes))
+          .map_err(|error| error.to_string().into())
+      })
+      .map(|disk| {
+        diff_lines(&editor_state.content.to_string(), &disk)
+          .into_iter()
+          .map(|(kind, line)| (kind, SharedString::from(line.to_string())))
+          .collect()
+      });
+    Self {
+      editor,
+      lines,
+      focus_handle: cx.focus_handle(),
+    }
+  }
+
+  fn reload(&mut self, cx: &mut Context<Self>) {
+    self
+      .editor
+      .update(cx, |editor, cx| editor.reload(cx))
+      .detach();
+    cx.emit(DismissEvent);
+  }
+
+  fn keep_mine(&mut self, cx: &mut Context<Self>) {
+    self
+      .editor
+      .update(cx, |editor, cx| editor.keep_local_changes(cx));
+    cx.emit(DismissEvent);
+  }
+
+  fn cancel(&mut self, _: &Cancel, _: &mut Window, cx: &mut Context<Self>) {
+    cx.emit(DismissEvent);
+  }
+}
+
+impl Focusable for DiffView {
+  fn focus_handle(&self, _: &App) -> FocusHandle {
+    self.focus_handle.clone()
+  }
+}
+
+impl Render for DiffView {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    let body = match &self.lines {
+      Ok(lines) => div().children(lines.iter().map(|(kind, line)| {
+        let (sign, color) = match kind {
+          DiffKind::Equal => (" ", rgb(0xffffff)),
+          DiffKind::Deleted => ("-", rgb(0xffdddd)),
+          DiffKind::Inserted => ("+", rgb(0xddffdd)),
+        };
+        div()
+          .px(px(4.))
+          .bg(color)
+          .whitespace_nowrap()
+          .child(format!("{sign} {line}"))
+      })),
+      Err(error) => div().child(error.clone()),
+    };
+
+    div()
+      .key_context("DiffView")
+      .track_focus(&self.focus_handle)
+      .on_action(cx.listener(Self::cancel))
+      .w(px(700.))
+      .p(px(8.))
+      .flex()
+      .flex_col()
+      .gap(px(4.))
+      .bg(rgb(0xdddddd))
+      .border_1()
+      .border_color(rgb(0x999999))
+      .child(
+        div()
+          .flex()
+          .gap(px(8.))
+          .items_center()
+          .child(
+            div()
+              .flex_1()
+              .text_sm()
+              .child("Your changes (-) against the file on disk (+)"),
+          )
+          .child(button("Reload").on_mouse_down(
+            MouseButton::Left,
+            cx.listener(|this, _, _, cx| this.reload(cx)),
+          ))
+          .child(button("Keep Mine").on_mouse_down(
+            MouseButton::Left,
+            cx.listener(|this, _, _, cx| this.keep_mine(cx)),
+          )),
+      )
+      .child(
+        div()
+          .id("diff-lines")
+          .max_h(px(400.))
+          .overflow_y_scroll()
+          .text_sm()
+          .child(body),
+      )
+  }
+}
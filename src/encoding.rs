@@ -0,0 +1,269 @@
+use std::io;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+  #[default]
+  Utf8,
+  Utf8Bom,
+  Utf16Le,
+  Utf16LeBom,
+  Utf16Be,
+  Utf16BeBom,
+  Latin1,
+}
+
+impl Encoding {
+  pub const ALL: [Encoding; 7] = [
+    Encoding::Utf8,
+    Encoding::Utf8Bom,
+    Encoding::Utf16Le,
+    Encoding::Utf16LeBom,
+    Encoding::Utf16Be,
+    Encoding::Utf16BeBom,
+    Encoding::Latin1,
+  ];
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Utf8 => "UTF-8",
+      Self::Utf8Bom => "UTF-8 with BOM",
+      Self::Utf16Le => "UTF-16 LE",
+      Self::Utf16LeBom => "UTF-16 LE with BOM",
+      Self::Utf16Be => "UTF-16 BE",
+      Self::Utf16BeBom => "UTF-16 BE with BOM",
+      Self::Latin1 => "Latin-1",
+    }
+  }
+
+  /// Guesses the encoding of `bytes` from its byte order mark, then by the
+  /// NUL bytes that ASCII text leaves in UTF-16, then by whether it is valid
+  /// UTF-8, and otherwise as Latin-1. Data with NUL bytes that isn't UTF-16
+  /// or UTF-8 is taken as binary, which editing would mangle, so there's no
+  /// guess.
+  pub fn detect(bytes: &[u8]) -> Option<Self> {
+    if bytes.starts_with(UTF8_BOM) {
+      return Some(Self::Utf8Bom);
+    }
+    if bytes.starts_with(UTF16_LE_BOM) {
+      return Some(Self::Utf16LeBom);
+    }
+    if bytes.starts_with(UTF16_BE_BOM) {
+      return Some(Self::Utf16BeBom);
+    }
+    let nuls = |skip: usize| {
+      bytes
+        .iter()
+        .skip(skip)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count()
+    };
+    let (even_nuls, odd_nuls) = (nuls(0), nuls(1));
+    if bytes.len().is_multiple_of(2) && even_nuls + odd_nuls > 0 {
+      // ASCII leaves NULs in the high bytes, though other characters, like
+      // many CJK ones, can have one in either byte. Text decodes without
+      // unpaired surrogates or NUL characters.
+      let candidates = if odd_nuls >= even_nuls {
+        [Self::Utf16Le, Self::Utf16Be]
+      } else {
+        [Self::Utf16Be, Self::Utf16Le]
+      };
+      if let Some(encoding) = candidates.into_iter().find(|encoding| {
+        char::decode_utf16(encoding.utf16_units(bytes)).all(|ch| ch.is_ok_and(|ch| ch != '\0'))
+      }) {
+        return Some(encoding);
+      }
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+      return Some(Self::Utf8);
+    }
+    (even_nuls + odd_nuls == 0).then_some(Self::Latin1)
+  }
+
+  /// The UTF-16 code units in `bytes`, ignoring a trailing odd byte.
+  fn utf16_units(self, bytes: &[u8]) -> impl Iterator<Item = u16> {
+    let little_endian = matches!(self, Self::Utf16Le | Self::Utf16LeBom);
+    bytes.chunks_exact(2).map(move |pair| {
+      let pair = [pair[0], pair[1]];
+      if little_endian {
+        u16::from_le_bytes(pair)
+      } else {
+        u16::from_be_bytes(pair)
+      }
+    })
+  }
+
+  /// Decodes `bytes`, dropping the byte order mark. Fails instead of
+  /// replacing bytes that aren't valid in this encoding. UTF-8 reuses the
+  /// buffer.
+  pub fn decode(self, mut bytes: Vec<u8>) -> io::Result<String> {
+    match self {
+      Self::Utf8 | Self::Utf8Bom => {
+        if bytes.starts_with(UTF8_BOM) {
+          bytes.drain(..UTF8_BOM.len());
+        }
+        String::from_utf8(bytes).map_err(|error| {
+          invalid_data(format!(
+            "invalid {} at byte {}",
+            self.label(),
+            error.utf8_error().valid_up_to()
+          ))
+        })
+      }
+      Self::Utf16Le | Self::Utf16LeBom | Self::Utf16Be | Self::Utf16BeBom => {
+        let bom = match self {
+          Self::Utf16Le | Self::Utf16LeBom => UTF16_LE_BOM,
+          _ => UTF16_BE_BOM,
+        };
+        let bytes = bytes.strip_prefix(bom).unwrap_or(&bytes);
+        if !bytes.len().is_multiple_of(2) {
+          return Err(invalid_data(format!(
+            "{} text has an odd number of bytes",
+            self.label()
+          )));
+        }
+        char::decode_utf16(self.utf16_units(bytes))
+          .collect::<Result<String, _>>()
+          .map_err(|error| {
+            invalid_data(format!(
+              "unpaired surrogate {:#06x} in {} text",
+              error.unpaired_surrogate(),
+              self.label()
+            ))
+          })
+      }
+      Self::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+    }
+  }
+
+  /// Encodes the text made of `chunks`, writing a byte order mark for the
+  /// encodings that carry one. Fails on characters this encoding can't
+  /// represent.
+  pub fn encode<'a>(self, chunks: impl IntoIterator<Item = &'a str>) -> io::Result<Vec<u8>> {
+    let mut bytes = match self {
+      Self::Utf8Bom => UTF8_BOM.to_vec(),
+      Self::Utf16LeBom => UTF16_LE_BOM.to_vec(),
+      Self::Utf16BeBom => UTF16_BE_BOM.to_vec(),
+      Self::Utf8 | Self::Utf16Le | Self::Utf16Be | Self::Latin1 => Vec::new(),
+    };
+    for chunk in chunks {
+      match self {
+        Self::Utf8 | Self::Utf8Bom => bytes.extend_from_slice(chunk.as_bytes()),
+        Self::Utf16Le | Self::Utf16LeBom => {
+          bytes.extend(chunk.encode_utf16().flat_map(u16::to_le_bytes))
+        }
+        Self::Utf16Be | Self::Utf16BeBom => {
+          bytes.extend(chunk.encode_utf16().flat_map(u16::to_be_bytes))
+        }
+        Self::Latin1 => {
+          for ch in chunk.chars() {
+            bytes.push(
+              u8::try_from(ch).map_err(|_| {
+                invalid_data(format!("{ch:?} can't be encoded as {}", self.label()))
+              })?,
+            );
+          }
+        }
+      }
+    }
+    Ok(bytes)
+  }
+}
+
+fn invalid_data(message: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn utf16le(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+  }
+
+  fn utf16be(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+  }
+
+  #[test]
+  fn detect_byte_order_marks() {
+    assert_eq!(Encoding::detect(b"\xef\xbb\xbfa"), Some(Encoding::Utf8Bom));
+    assert_eq!(Encoding::detect(b"\xff\xfea\0"), Some(Encoding::Utf16LeBom));
+    assert_eq!(Encoding::detect(b"\xfe\xff\0a"), Some(Encoding::Utf16BeBom));
+  }
+
+  #[test]
+  fn detect_utf16_without_bom() {
+    assert_eq!(
+      Encoding::detect(&utf16le("plain text\n")),
+      Some(Encoding::Utf16Le)
+    );
+    assert_eq!(
+      Encoding::detect(&utf16be("plain text\n")),
+      Some(Encoding::Utf16Be)
+    );
+    // U+4E00 has a NUL in its low byte, where ASCII has none.
+    let mixed = "一二三 and some ASCII\n";
+    assert_eq!(Encoding::detect(&utf16le(mixed)), Some(Encoding::Utf16Le));
+    assert_eq!(Encoding::detect(&utf16be(mixed)), Some(Encoding::Utf16Be));
+  }
+
+  #[test]
+  fn detect_utf8_and_latin1() {
+    assert_eq!(
+      Encoding::detect("héllo 一".as_bytes()),
+      Some(Encoding::Utf8)
+    );
+    assert_eq!(Encoding::detect(b""), Some(Encoding::Utf8));
+    assert_eq!(Encoding::detect(b"caf\xe9"), Some(Encoding::Latin1));
+  }
+
+  #[test]
+  fn binary_data_is_not_guessed() {
+    assert_eq!(Encoding::detect(b"\x7fELF\x02\x01\x01\0\0\0\0\0\xff"), None);
+    assert_eq!(Encoding::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), None);
+  }
+
+  #[test]
+  fn round_trips() {
+    let text = "a\r\nü 一 😀";
+    for encoding in Encoding::ALL {
+      if encoding == Encoding::Latin1 {
+        continue;
+      }
+      let bytes = encoding.encode([text]).unwrap();
+      assert_eq!(
+        encoding.decode(bytes.clone()).unwrap(),
+        text,
+        "{encoding:?}"
+      );
+      assert_eq!(Encoding::detect(&bytes), Some(encoding), "{encoding:?}");
+    }
+    let bytes = Encoding::Latin1.encode(["a", "ü"]).unwrap();
+    assert_eq!(bytes, b"a\xfc");
+    assert_eq!(Encoding::Latin1.decode(bytes).unwrap(), "aü");
+  }
+
+  #[test]
+  fn byte_order_marks_are_written_only_for_their_encodings() {
+    assert_eq!(Encoding::Utf16Le.encode(["a"]).unwrap(), b"a\0");
+    assert_eq!(Encoding::Utf16LeBom.encode(["a"]).unwrap(), b"\xff\xfea\0");
+    assert_eq!(Encoding::Utf16Be.encode(["a"]).unwrap(), b"\0a");
+    assert_eq!(Encoding::Utf16BeBom.encode(["a"]).unwrap(), b"\xfe\xff\0a");
+    assert_eq!(Encoding::Utf8.encode(["a"]).unwrap(), b"a");
+    assert_eq!(Encoding::Utf8Bom.encode(["a"]).unwrap(), b"\xef\xbb\xbfa");
+  }
+
+  #[test]
+  fn invalid_text_fails_to_decode() {
+    assert!(Encoding::Utf8.decode(b"caf\xe9".to_vec()).is_err());
+    assert!(Encoding::Utf16Le.decode(b"a\0b".to_vec()).is_err());
+    assert!(Encoding::Utf16Le.decode(b"\0\xd8a\0".to_vec()).is_err());
+    assert!(Encoding::Latin1.encode(["一"]).is_err());
+  }
+}
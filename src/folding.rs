@@ -0,0 +1,282 @@
+use std::{borrow::Cow, ops::Range};
+
+use ropey::Rope;
+
+/// Lines that can be folded away under the first of them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FoldRegion {
+  /// The line left visible, followed by a placeholder when folded.
+  pub start_line: usize,
+  /// The last hidden line.
+  pub end_line: usize,
+}
+
+/// The foldable regions of `text`, sorted by their first line with at most
+/// one starting on each line. Lines more indented than the one before them
+/// fold under it. A pair of `brackets` spanning lines folds everything
+/// between its lines, taking precedence over indentation.
+pub fn fold_regions(text: &Rope, brackets: &[(usize, usize)], tab_size: usize) -> Vec<FoldRegion> {
+  let mut end_lines = vec![None; text.len_lines()];
+
+  // Each line still open on the stack has its indentation and the last
+  // line found more indented than it.
+  let mut open: Vec<(usize, usize, Option<usize>)> = Vec::new();
+  for (ix, line) in text.lines().enumerate() {
+    let line = Cow::from(line);
+    if line.trim().is_empty() {
+      continue;
+    }
+    let indent = indent_columns(&line, tab_size);
+    while let Some(&(start, start_indent, last)) = open.last() {
+      if start_indent < indent {
+        break;
+      }
+      open.pop();
+      end_lines[start] = last;
+    }
+    for (_, _, last) in &mut open {
+      *last = Some(ix);
+    }
+    open.push((ix, indent, None));
+  }
+  for (start, _, last) in open {
+    end_lines[start] = last;
+  }
+
+  for &(open, close) in brackets {
+    let (start, end) = (text.byte_to_line(open), text.byte_to_line(close));
+    // The closing bracket's line stays visible.
+    if end > start + 1 {
+      end_lines[start] = Some(end_lines[start].map_or(end - 1, |last: usize| last.max(end - 1)));
+    }
+  }
+
+  end_lines
+    .into_iter()
+    .enumerate()
+    .filter_map(|(start_line, end_line)| {
+      Some(FoldRegion {
+        start_line,
+        end_line: end_line?,
+      })
+    })
+    .collect()
+}
+
+/// Shifts the hidden text of a `fold` to account for `edited` being
+/// replaced by `new_len` bytes. Returns false once the edit touches the
+/// folded text or the line break after it, which unfolds it.
+pub fn adjust_fold(fold: &mut Range<usize>, edited: &Range<usize>, new_len: usize) -> bool {
+  if edited.start <= fold.end && edited.end > fold.start {
+    return false;
+  }
+  if fold.start >= edited.end {
+    fold.start = fold.start - edited.len() + new_len;
+    fold.end = fold.end - edited.len() + new_len;
+  }
+  true
+}
+
+/// How deeply each of `regions` is nested, 1 for those outside all others.
+pub fn region_depths(regions: &[FoldRegion]) -> Vec<usize> {
+  let mut enclosing: Vec<usize> = Vec::new();
+  regions
+    .iter()
+    .map(|region| {
+      while enclosing
+        .last()
+        .is_some_and(|&end_line| end_line < region.start_line)
+      {
+        enclosing.pop();
+      }
+      enclosing.push(region.end_line);
+      enclosing.len()
+    })
+    .collect()
+}
+
+/// The display width of the indentation of `line` in columns.
+pub fn indent_columns(line: &str, tab_size: usize) -> usize {
+  line
+    .chars()
+    .take_while(|ch| matches!(ch, ' ' | '\t'))
+    .fold(0, |column, ch| {
+      if ch == '\t' {
+        column + tab_size - column % tab_size
+      } else {
+        column + 1
+      }
+    })
+}
+
+/// Maps between buffer lines and the rows they are displayed on, once
+/// folded lines are left out.
+pub struct DisplayMap {
+  /// The hidden lines, sorted and disjoint.
+  hidden: Vec<Range<usize>>,
+  line_count: usize,
+}
+
+impl DisplayMap {
+  pub fn new(mut hidden: Vec<Range<usize>>, line_count: usize) -> Self {
+    hidden.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(hidden.len());
+    for range in hidden {
+      match merged.last_mut() {
+        Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+        _ => merged.push(range),
+      }
+    }
+    Self {
+      hidden: merged,
+      line_count,
+    }
+  }
+
+  pub fn row_count(&self) -> usize {
+    self.line_count - self.hidden.iter().map(|range| range.len()).sum::<usize>()
+  }
+
+  pub fn is_hidden(&self, line: usize) -> bool {
+    let ix = self.hidden.partition_point(|range| range.end <= line);
+    self
+      .hidden
+      .get(ix)
+      .is_some_and(|range| range.contains(&line))
+  }
+
+  /// The row showing `line`, or the row of the line a hidden line is
+  /// folded under. Lines hidden from the very top show on the first row.
+  pub fn row_for_line(&self, line: usize) -> usize {
+    let mut hidden_before = 0;
+    for range in &self.hidden {
+      if line < range.start {
+        break;
+      }
+      if line < range.end {
+        return (range.start - hidden_before).saturating_sub(1);
+      }
+      hidden_before += range.len();
+    }
+    line - hidden_before
+  }
+
+  pub fn line_for_row(&self, row: usize) -> usize {
+    let mut line = row;
+    for range in &self.hidden {
+      if range.start > line {
+        break;
+      }
+      line += range.len();
+    }
+    line
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn regions(text: &str, brackets: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    fold_regions(&Rope::from_str(text), brackets, 4)
+      .into_iter()
+      .map(|region| (region.start_line, region.end_line))
+      .collect()
+  }
+
+  #[test]
+  fn indented_lines_fold_under_the_line_before() {
+    assert_eq!(regions("fn a\n  b\n  c\nd\n", &[]), [(0, 2)]);
+    assert_eq!(regions("a\n  b\n    c\n  d\n", &[]), [(0, 3), (1, 2)]);
+    assert_eq!(regions("a\nb\n", &[]), []);
+  }
+
+  #[test]
+  fn blank_lines_dont_end_or_extend_regions() {
+    assert_eq!(regions("a\n  b\n\n  c\nd", &[]), [(0, 3)]);
+    assert_eq!(regions("a\n  b\n\n\nc", &[]), [(0, 1)]);
+  }
+
+  #[test]
+  fn tabs_count_as_columns_up_to_the_tab_stop() {
+    assert_eq!(regions("a\n\tb\n    c\nd", &[]), [(0, 2)]);
+    assert_eq!(regions("a\n  \tb\n    c\n      d", &[]), [(0, 3), (2, 3)]);
+  }
+
+  #[test]
+  fn bracket_pairs_take_precedence() {
+    // The closing bracket's line stays visible.
+    assert_eq!(regions("fn f() {\nx\ny\n}\n", &[(4, 5), (7, 13)]), [(0, 2)]);
+    // Brackets widen a region indentation alone would end early.
+    assert_eq!(
+      regions("foo(\nbar,\n  baz\n)", &[(3, 16)]),
+      [(0, 2), (1, 2)]
+    );
+    // A pair on neighbouring lines has nothing between them to fold.
+    assert_eq!(regions("f(\n)", &[(1, 3)]), []);
+  }
+
+  #[test]
+  fn folds_shift_with_edits_before_them() {
+    let adjust = |edited: Range<usize>, new_len: usize| {
+      let mut fold = 10..20;
+      adjust_fold(&mut fold, &edited, new_len).then_some(fold)
+    };
+    assert_eq!(adjust(2..4, 0), Some(8..18));
+    assert_eq!(adjust(10..10, 3), Some(13..23));
+    assert_eq!(adjust(21..25, 0), Some(10..20));
+    // Editing the folded text or the break after it unfolds it.
+    assert_eq!(adjust(12..13, 0), None);
+    assert_eq!(adjust(20..20, 1), None);
+    assert_eq!(adjust(5..11, 0), None);
+  }
+
+  #[test]
+  fn depths() {
+    let regions = [(0, 5), (1, 2), (3, 4), (6, 7)].map(|(start_line, end_line)| FoldRegion {
+      start_line,
+      end_line,
+    });
+    assert_eq!(region_depths(&regions), [1, 2, 2, 1]);
+  }
+
+  #[test]
+  fn display_map_merges_overlapping_ranges() {
+    let map = DisplayMap::new(vec![3..5, 1..2, 4..7, 7..8], 10);
+    assert_eq!(map.hidden, [1..2, 3..8]);
+    assert_eq!(map.row_count(), 4);
+    assert!(map.is_hidden(1));
+    assert!(!map.is_hidden(2));
+    assert!(map.is_hidden(7));
+    assert!(!map.is_hidden(8));
+  }
+
+  #[test]
+  fn display_map_rows() {
+    let map = DisplayMap::new(vec![1..2, 3..8], 10);
+    // Hidden lines show on the row of the line they're folded under.
+    let rows = (0..10)
+      .map(|line| map.row_for_line(line))
+      .collect::<Vec<_>>();
+    assert_eq!(rows, [0, 0, 1, 1, 1, 1, 1, 1, 2, 3]);
+    let lines = (0..map.row_count())
+      .map(|row| map.line_for_row(row))
+      .collect::<Vec<_>>();
+    assert_eq!(lines, [0, 2, 8, 9]);
+    for row in 0..map.row_count() {
+      assert_eq!(map.row_for_line(map.line_for_row(row)), row);
+    }
+  }
+
+  #[test]
+  fn display_map_hidden_from_the_top() {
+    let hidden = 0..2;
+    let map = DisplayMap::new(vec![hidden], 5);
+    assert_eq!(map.row_count(), 3);
+    assert_eq!(map.row_for_line(0), 0);
+    assert_eq!(map.row_for_line(1), 0);
+    assert_eq!(map.row_for_line(2), 0);
+    assert_eq!(map.row_for_line(4), 2);
+    assert_eq!(map.line_for_row(0), 2);
+  }
+}
@@ -0,0 +1,233 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// A line of a `.gitignore` file.
+struct Rule {
+  /// The directory holding the `.gitignore`, which anchored patterns are
+  /// relative to.
+  base: PathBuf,
+  pattern: Vec<char>,
+  negated: bool,
+  dir_only: bool,
+  /// Whether the pattern matches the whole path from `base` rather than
+  /// just the file name.
+  anchored: bool,
+}
+
+/// The `.gitignore` rules applying in a directory, from its own file and
+/// those of its ancestors up to the project root.
+#[derive(Default)]
+pub struct Gitignore {
+  rules: Vec<Rule>,
+}
+
+impl Gitignore {
+  /// Reads the `.gitignore` files from `root` down to `dir`.
+  pub fn for_dir(root: &Path, dir: &Path) -> Self {
+    let mut gitignore = Self::default();
+    let Ok(relative) = dir.strip_prefix(root) else {
+      return gitignore;
+    };
+    let mut base = root.to_path_buf();
+    gitignore.add_file(&base);
+    for component in relative.components() {
+      base.push(component);
+      gitignore.add_file(&base);
+    }
+    gitignore
+  }
+
+  fn add_file(&mut self, dir: &Path) {
+    let Ok(contents) = fs::read_to_string(dir.join(".gitignore")) else {
+      return;
+    };
+    for line in contents.lines() {
+      let line = line.trim_end();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let (negated, line) = match line.strip_prefix('!') {
+        Some(line) => (true, line),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+      };
+      let (dir_only, line) = match line.strip_suffix('/') {
+        Some(line) => (true, line),
+        None => (false, line),
+      };
+      let anchored = line.contains('/');
+      self.rules.push(Rule {
+        base: dir.to_path_buf(),
+        pattern: line.trim_start_matches('/').chars().collect(),
+        negated,
+        dir_only,
+        anchored,
+      });
+    }
+  }
+
+  /// Whether `path` is ignored. Later rules, including those of deeper
+  /// `.gitignore` files, take precedence.
+  pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in &self.rules {
+      if rule.dir_only && !is_dir {
+        continue;
+      }
+      let Ok(relative) = path.strip_prefix(&rule.base) else {
+        continue;
+      };
+      let subject = if rule.anchored {
+        relative.to_string_lossy().replace('\\', "/")
+      } else {
+        relative
+          .file_name()
+          .map(|name| name.to_string_lossy().into_owned())
+          .unwrap_or_default()
+      };
+      if glob_match(&rule.pattern, &subject.chars().collect::<Vec<_>>()) {
+        ignored = !rule.negated;
+      }
+    }
+    ignored
+  }
+}
+
+/// Matches `text` against a glob where `*` and `?` stay within a path
+/// component, `**` crosses them and `[...]` is a character class.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+  match pattern {
+    [] => text.is_empty(),
+    ['*', '*', '/', rest @ ..] => {
+      // `**/` also matches no directories at all.
+      glob_match(rest, text)
+        || (0..text.len()).any(|ix| text[ix] == '/' && glob_match(rest, &text[ix + 1..]))
+    }
+    ['*', '*', rest @ ..] => (0..=text.len()).any(|ix| glob_match(rest, &text[ix..])),
+    ['*', rest @ ..] => (0..=text.len())
+      .take_while(|&ix| ix == 0 || text[ix - 1] != '/')
+      .any(|ix| glob_match(rest, &text[ix..])),
+    ['?', rest @ ..] => {
+      matches!(text.first(), Some(&c) if c != '/') && glob_match(rest, &text[1..])
+    }
+    ['[', class @ ..] => {
+      let Some(end) = class
+        .iter()
+        .skip(1)
+        .position(|&c| c == ']')
+        .map(|ix| ix + 1)
+      else {
+        return text.first() == Some(&'[') && glob_match(class, &text[1..]);
+      };
+      let Some(&c) = text.first() else {
+        return false;
+      };
+      let (negated, set) = match class[..end].split_first() {
+        Some(('!' | '^', set)) => (true, set),
+        _ => (false, &class[..end]),
+      };
+      let mut in_set = false;
+      let mut ix = 0;
+      while ix < set.len() {
+        if ix + 2 < set.len() && set[ix + 1] == '-' {
+          in_set |= (set[ix]..=set[ix + 2]).contains(&c);
+          ix += 3;
+        } else {
+          in_set |= set[ix] == c;
+          ix += 1;
+        }
+      }
+      in_set != negated && c != '/' && glob_match(&class[end + 1..], &text[1..])
+    }
+    ['\\', literal, rest @ ..] | [literal, rest @ ..] => {
+      text.first() == Some(literal) && glob_match(rest, &text[1..])
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn matches(pattern: &str, text: &str) -> bool {
+    glob_match(
+      &pattern.chars().collect::<Vec<_>>(),
+      &text.chars().collect::<Vec<_>>(),
+    )
+  }
+
+  #[test]
+  fn literals_and_wildcards() {
+    assert!(matches("target", "target"));
+    assert!(!matches("target", "targets"));
+    assert!(matches("*.rs", "main.rs"));
+    assert!(matches("*.rs", ".rs"));
+    assert!(!matches("*.rs", "main.rsx"));
+    assert!(matches("a?c", "abc"));
+    assert!(!matches("a?c", "ac"));
+    assert!(matches(r"\*.txt", "*.txt"));
+    assert!(!matches(r"\*.txt", "a.txt"));
+  }
+
+  #[test]
+  fn wildcards_stay_within_components() {
+    assert!(!matches("*.rs", "src/main.rs"));
+    assert!(!matches("a?b", "a/b"));
+    assert!(matches("src/*.rs", "src/main.rs"));
+    assert!(!matches("src/*.rs", "src/bin/main.rs"));
+  }
+
+  #[test]
+  fn double_stars_cross_components() {
+    assert!(matches("**/build", "build"));
+    assert!(matches("**/build", "a/b/build"));
+    assert!(!matches("**/build", "a/rebuild"));
+    assert!(matches("logs/**", "logs/a/b.log"));
+    assert!(matches("a/**/b", "a/b"));
+    assert!(matches("a/**/b", "a/x/y/b"));
+    assert!(!matches("a/**/b", "a/x/yb"));
+  }
+
+  #[test]
+  fn character_classes() {
+    assert!(matches("[abc].txt", "b.txt"));
+    assert!(!matches("[abc].txt", "d.txt"));
+    assert!(matches("file[0-9]", "file7"));
+    assert!(!matches("file[0-9]", "filex"));
+    assert!(matches("[!a]", "b"));
+    assert!(!matches("[^a]", "a"));
+    assert!(matches("[]]", "]"));
+    assert!(!matches("a[/]b", "a/b"));
+    // An unclosed bracket is a literal.
+    assert!(matches("a[b", "a[b"));
+  }
+
+  #[test]
+  fn rules_from_nested_files() {
+    let root = std::env::temp_dir().join(format!("zeta-gitignore-{}", std::process::id()));
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(
+      root.join(".gitignore"),
+      "*.log\n!keep.log\n/out/\n# comment\n",
+    )
+    .unwrap();
+    fs::write(root.join("sub/.gitignore"), "keep.log\ndata/*.csv\n").unwrap();
+
+    let top = Gitignore::for_dir(&root, &root);
+    assert!(top.is_ignored(&root.join("a.log"), false));
+    assert!(!top.is_ignored(&root.join("keep.log"), false));
+    assert!(top.is_ignored(&root.join("out"), true));
+    assert!(!top.is_ignored(&root.join("out"), false));
+    assert!(!top.is_ignored(&root.join("# comment"), false));
+
+    let sub = Gitignore::for_dir(&root, &root.join("sub"));
+    assert!(sub.is_ignored(&root.join("sub/keep.log"), false));
+    // Anchored patterns apply below their own directory only.
+    assert!(!sub.is_ignored(&root.join("sub/out"), true));
+    assert!(sub.is_ignored(&root.join("sub/data/a.csv"), false));
+    assert!(!sub.is_ignored(&root.join("sub/a.csv"), false));
+
+    fs::remove_dir_all(&root).ok();
+  }
+}
@@ -0,0 +1,137 @@
+use std::ops::Range;
+
+use gpui::{
+  App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, Pixels, Point,
+  Subscription, Window, actions, div, prelude::*, px, rgb,
+};
+
+use crate::text_input::{Cancel, Newline, TextInput};
+
+actions!(go_to_line, [Toggle]);
+
+pub struct GoToLine {
+  input: Entity<TextInput>,
+  editor: Entity<TextInput>,
+  original_selection: Range<usize>,
+  original_selection_reversed: bool,
+  original_scroll: Point<Pixels>,
+  current_line: usize,
+  _subscription: Subscription,
+}
+
+impl EventEmitter<DismissEvent> for GoToLine {}
+
+impl GoToLine {
+  pub fn new(editor: Entity<TextInput>, cx: &mut Context<Self>) -> Self {
+    let input = cx.new(|cx| {
+      let mut input = TextInput::new("Line, line:column, +n or -n", cx);
+      input.single_line = true;
+      input
+    });
+    let subscription = cx.observe(&input, |this, _, cx| this.preview(cx));
+
+    let editor_state = editor.read(cx);
+    let (current_line, _) = editor_state.line_column_for_offset(editor_state.cursor_offset());
+    Self {
+      original_selection: editor_state.selected_range.clone(),
+      original_selection_reversed: editor_state.selection_reversed,
+      original_scroll: editor_state.scroll_handle.offset(),
+      current_line,
+      input,
+      editor,
+      _subscription: subscription,
+    }
+  }
+
+  /// Parses `line`, `line:column` or a relative `+n`/`-n` into an offset in
+  /// the editor. Lines and columns are one-based, columns count graphemes.
+  fn target(&self, cx: &App) -> Option<usize> {
+    let query = self.input.read(cx).content.to_string().trim().to_string();
+    if query.is_empty() {
+      return None;
+    }
+    let (line, column) = query.split_once(':').unwrap_or((&query, ""));
+
+    let line = if let Some(delta) = line.strip_prefix('+') {
+      self.current_line + delta.parse::<usize>().ok()?
+    } else if let Some(delta) = line.strip_prefix('-') {
+      self
+        .current_line
+        .saturating_sub(delta.parse::<usize>().ok()?)
+    } else {
+      line.parse::<usize>().ok()?.saturating_sub(1)
+    };
+    let column = if column.is_empty() {
+      0
+    } else {
+      column.parse::<usize>().ok()?.saturating_sub(1)
+    };
+
+    let editor = self.editor.read(cx);
+    Some(editor.offset_for_line_column(line.min(editor.line_count() - 1), column))
+  }
+
+  fn preview(&mut self, cx: &mut Context<Self>) {
+    match self.target(cx) {
+      Some(offset) => self.editor.update(cx, |editor, cx| {
+        editor.mark = None;
+        editor.move_to(offset, cx);
+      }),
+      None => self.restore(cx),
+    }
+  }
+
+  fn restore(&mut self, cx: &mut Context<Self>) {
+    let selection = self.original_selection.clone();
+    let reversed = self.original_selection_reversed;
+    let scroll = self.original_scroll;
+    self.editor.update(cx, |editor, cx| {
+      editor.selected_range = selection;
+      editor.selection_reversed = reversed;
+      editor.scroll_handle.set_offset(scroll);
+      cx.notify();
+    });
+  }
+
+  fn confirm(&mut self, _: &Newline, _: &mut Window, cx: &mut Context<Self>) {
+    if self.target(cx).is_none() {
+      self.restore(cx);
+    }
+    cx.emit(DismissEvent);
+  }
+
+  fn cancel(&mut self, _: &Cancel, _: &mut Window, cx: &mut Context<Self>) {
+    self.restore(cx);
+    cx.emit(DismissEvent);
+  }
+}
+
+impl Focusable for GoToLine {
+  fn focus_handle(&self, cx: &App) -> FocusHandle {
+    self.input.read(cx).focus_handle.clone()
+  }
+}
+
+impl Render for GoToLine {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    let line_count = self.editor.read(cx).line_count();
+    div()
+      .key_context("GoToLine")
+      .on_action(cx.listener(Self::confirm))
+      .on_action(cx.listener(Self::cancel))
+      .w(px(400.))
+      .p(px(8.))
+      .flex()
+      .flex_col()
+      .gap(px(4.))
+      .bg(rgb(0xdddddd))
+      .border_1()
+      .border_color(rgb(0x999999))
+      .child(div().h(px(30. + 4. * 2.)).child(self.input.clone()))
+      .child(div().text_sm().child(format!(
+        "Current line {} of {}",
+        self.current_line + 1,
+        line_count
+      )))
+  }
+}
@@ -0,0 +1,257 @@
+use std::{
+  ops::Range,
+  time::{Duration, Instant},
+};
+
+/// Consecutive insertions closer together than this are undone together.
+const GROUP_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+  pub start: usize,
+  pub old_text: String,
+  pub new_text: String,
+  pub selection: Range<usize>,
+  pub selection_reversed: bool,
+  /// Whether the entry is undone and redone together with the one before
+  /// it, as for edits made on behalf of another.
+  pub joined: bool,
+  /// Identifies the content the entry leaves, changing whenever the entry
+  /// does.
+  id: usize,
+  at: Instant,
+}
+
+impl HistoryEntry {
+  /// The range `new_text` occupies once the entry is applied.
+  pub fn new_range(&self) -> Range<usize> {
+    self.start..self.start + self.new_text.len()
+  }
+
+  pub fn old_range(&self) -> Range<usize> {
+    self.start..self.start + self.old_text.len()
+  }
+}
+
+#[derive(Default)]
+pub struct History {
+  undo_stack: Vec<HistoryEntry>,
+  redo_stack: Vec<HistoryEntry>,
+  last_id: usize,
+}
+
+impl History {
+  /// Identifies the current position in the history, which is the same
+  /// after undoing or redoing back to it and differs after any other edit.
+  pub fn state(&self) -> usize {
+    self.undo_stack.last().map_or(0, |entry| entry.id)
+  }
+
+  fn next_id(&mut self) -> usize {
+    self.last_id += 1;
+    self.last_id
+  }
+
+  pub fn record(
+    &mut self,
+    range: Range<usize>,
+    old_text: &str,
+    new_text: &str,
+    selection: Range<usize>,
+    selection_reversed: bool,
+  ) {
+    self.redo_stack.clear();
+    let now = Instant::now();
+
+    if let Some(last) = self.undo_stack.last_mut()
+      && !last.joined
+      && last.old_text.is_empty()
+      && old_text.is_empty()
+      && last.new_range().end == range.start
+      && !new_text.contains('\n')
+      && now.duration_since(last.at) < GROUP_INTERVAL
+    {
+      last.new_text.push_str(new_text);
+      last.at = now;
+      last.id = self.last_id + 1;
+      self.last_id = last.id;
+      return;
+    }
+
+    let id = self.next_id();
+    self.undo_stack.push(HistoryEntry {
+      start: range.start,
+      old_text: old_text.to_string(),
+      new_text: new_text.to_string(),
+      selection,
+      selection_reversed,
+      joined: false,
+      id,
+      at: now,
+    });
+  }
+
+  /// Replaces `range` of the text the last entry inserted with `new_text`,
+  /// so input still being composed by an input method is a single step.
+  /// Returns false, recording nothing, when the range isn't within it.
+  pub fn amend_last(&mut self, range: Range<usize>, new_text: &str) -> bool {
+    let id = self.last_id + 1;
+    let Some(last) = self.undo_stack.last_mut() else {
+      return false;
+    };
+    let inserted = last.new_range();
+    if last.joined || range.start < inserted.start || range.end > inserted.end {
+      return false;
+    }
+    last.new_text.replace_range(
+      range.start - inserted.start..range.end - inserted.start,
+      new_text,
+    );
+    last.at = Instant::now();
+    last.id = id;
+    self.last_id = id;
+    self.redo_stack.clear();
+    // A composition that was cancelled leaves nothing to undo.
+    if last.new_text == last.old_text {
+      self.undo_stack.pop();
+    }
+    true
+  }
+
+  /// Records an edit that's undone and redone together with the last one.
+  pub fn record_joined(&mut self, range: Range<usize>, old_text: &str, new_text: &str) {
+    let Some(last) = self.undo_stack.last() else {
+      return;
+    };
+    let (selection, selection_reversed) = (last.selection.clone(), last.selection_reversed);
+    self.redo_stack.clear();
+    let id = self.next_id();
+    self.undo_stack.push(HistoryEntry {
+      start: range.start,
+      old_text: old_text.to_string(),
+      new_text: new_text.to_string(),
+      selection,
+      selection_reversed,
+      joined: true,
+      id,
+      at: Instant::now(),
+    });
+  }
+
+  /// The entries of the last undo step, newest first.
+  pub fn undo(&mut self) -> Vec<HistoryEntry> {
+    let mut entries = Vec::new();
+    while let Some(entry) = self.undo_stack.pop() {
+      let joined = entry.joined;
+      self.redo_stack.push(entry.clone());
+      entries.push(entry);
+      if !joined {
+        break;
+      }
+    }
+    entries
+  }
+
+  /// The entries of the next redo step, oldest first.
+  pub fn redo(&mut self) -> Vec<HistoryEntry> {
+    let mut entries = Vec::new();
+    while let Some(entry) = self.redo_stack.pop() {
+      self.undo_stack.push(entry.clone());
+      entries.push(entry);
+      if !self.redo_stack.last().is_some_and(|next| next.joined) {
+        break;
+      }
+    }
+    entries
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn joined_entries_undo_and_redo_together() {
+    let mut history = History::default();
+    history.record(0..0, "", "a", 0..0, false);
+    history.record(5..6, "x", "", 6..6, false);
+    history.record_joined(9..10, "y", "");
+    history.record_joined(2..3, "z", "");
+
+    let undone = history.undo();
+    let starts = undone.iter().map(|entry| entry.start).collect::<Vec<_>>();
+    assert_eq!(starts, [2, 9, 5]);
+    assert!(undone.iter().all(|entry| entry.selection == (6..6)));
+
+    let redone = history.redo();
+    let starts = redone.iter().map(|entry| entry.start).collect::<Vec<_>>();
+    assert_eq!(starts, [5, 9, 2]);
+
+    assert_eq!(history.undo().len(), 3);
+    assert_eq!(history.undo().len(), 1);
+    assert!(history.undo().is_empty());
+  }
+
+  #[test]
+  fn typing_does_not_merge_into_joined_entries() {
+    let mut history = History::default();
+    history.record(0..0, "", "a", 0..0, false);
+    history.record_joined(4..4, "", "a");
+    history.record(1..1, "", "b", 1..1, false);
+    assert_eq!(history.undo().len(), 1);
+    assert_eq!(history.undo().len(), 2);
+  }
+
+  #[test]
+  fn state_returns_with_undo_and_redo() {
+    let mut history = History::default();
+    let empty = history.state();
+    history.record(0..0, "", "a", 0..0, false);
+    let saved = history.state();
+    assert_ne!(saved, empty);
+
+    history.record(1..1, "", "b", 1..1, false);
+    // Typing merged into the saved entry still changes the state.
+    assert_ne!(history.state(), saved);
+    history.undo();
+    assert_eq!(history.state(), empty);
+
+    history.record(0..0, "", "a", 0..0, false);
+    let saved = history.state();
+    history.record(1..2, "x", "y", 1..2, false);
+    history.undo();
+    assert_eq!(history.state(), saved);
+    history.redo();
+    assert_ne!(history.state(), saved);
+    history.undo();
+    history.undo();
+    assert_eq!(history.state(), empty);
+  }
+
+  #[test]
+  fn composition_amends_one_entry() {
+    let mut history = History::default();
+    history.record(0..0, "", "a", 0..0, false);
+    history.record(1..1, "", "k", 1..1, false);
+    assert!(history.amend_last(1..2, "ka"));
+    assert!(history.amend_last(1..3, "か"));
+    let undone = history.undo();
+    assert_eq!(undone.len(), 1);
+    assert_eq!(undone[0].new_text, "aか");
+    assert!(history.undo().is_empty());
+
+    // Outside the last entry's text, there's nothing to amend.
+    history.record(0..0, "", "a", 0..0, false);
+    assert!(!history.amend_last(1..2, "b"));
+  }
+
+  #[test]
+  fn cancelled_composition_leaves_no_entry() {
+    let mut history = History::default();
+    history.record(0..1, "x", "k", 0..1, false);
+    let state = history.state();
+    assert!(history.amend_last(0..1, "x"));
+    assert!(history.undo().is_empty());
+    assert_ne!(history.state(), state);
+  }
+}
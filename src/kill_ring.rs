@@ -0,0 +1,108 @@
+use std::ops::Range;
+
+const MAX_ENTRIES: usize = 60;
+
+#[derive(Default)]
+pub struct KillRing {
+  entries: Vec<String>,
+  yank_index: usize,
+  pub last_yank: Option<Range<usize>>,
+  pub last_kill_offset: Option<usize>,
+}
+
+impl KillRing {
+  /// Records killed text. Consecutive kills from the same offset are merged
+  /// into a single entry, like repeated `ctrl-k` in Emacs.
+  pub fn kill(&mut self, text: &str, offset: usize) {
+    if self.last_kill_offset == Some(offset)
+      && let Some(last) = self.entries.last_mut()
+    {
+      last.push_str(text);
+    } else {
+      self.push(text.to_string());
+    }
+    self.last_kill_offset = Some(offset);
+  }
+
+  pub fn push(&mut self, text: String) {
+    if self.entries.last() == Some(&text) {
+      return;
+    }
+    if self.entries.len() == MAX_ENTRIES {
+      self.entries.remove(0);
+    }
+    self.entries.push(text);
+    self.yank_index = self.entries.len() - 1;
+  }
+
+  pub fn current(&self) -> Option<&str> {
+    self.entries.last().map(String::as_str)
+  }
+
+  pub fn start_yank(&mut self) -> Option<&str> {
+    self.yank_index = self.entries.len().checked_sub(1)?;
+    self.entries.get(self.yank_index).map(String::as_str)
+  }
+
+  /// Rotates to the entry killed before the one yanked last.
+  pub fn rotate(&mut self) -> Option<&str> {
+    if self.entries.is_empty() {
+      return None;
+    }
+    self.yank_index = self
+      .yank_index
+      .checked_sub(1)
+      .unwrap_or(self.entries.len() - 1);
+    self.entries.get(self.yank_index).map(String::as_str)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn yank_pop_rotates_to_older_kills() {
+    let mut ring = KillRing::default();
+    assert_eq!(ring.start_yank(), None);
+    assert_eq!(ring.rotate(), None);
+
+    ring.push("one".into());
+    ring.push("two".into());
+    ring.push("three".into());
+    assert_eq!(ring.start_yank(), Some("three"));
+    assert_eq!(ring.rotate(), Some("two"));
+    assert_eq!(ring.rotate(), Some("one"));
+    // Past the oldest kill, it wraps around to the newest.
+    assert_eq!(ring.rotate(), Some("three"));
+    // A new yank starts over from the newest.
+    ring.rotate();
+    assert_eq!(ring.start_yank(), Some("three"));
+  }
+
+  #[test]
+  fn kills_from_the_same_offset_merge() {
+    let mut ring = KillRing::default();
+    ring.kill("foo", 4);
+    ring.kill("bar", 4);
+    assert_eq!(ring.current(), Some("foobar"));
+
+    ring.kill("baz", 0);
+    assert_eq!(ring.current(), Some("baz"));
+    assert_eq!(ring.start_yank(), Some("baz"));
+    assert_eq!(ring.rotate(), Some("foobar"));
+  }
+
+  #[test]
+  fn oldest_kills_are_dropped() {
+    let mut ring = KillRing::default();
+    // A repeat of the latest kill isn't recorded again.
+    ring.push("0".into());
+    ring.push("0".into());
+    for ix in 1..=MAX_ENTRIES {
+      ring.push(ix.to_string());
+    }
+    assert_eq!(ring.entries.len(), MAX_ENTRIES);
+    assert_eq!(ring.entries[0], "1");
+  }
+}
@@ -0,0 +1,280 @@
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct Language {
+  pub name: &'static str,
+  /// The file extensions the language is used for.
+  pub path_suffixes: &'static [&'static str],
+  pub brackets: &'static [(char, char)],
+  /// Line endings, besides an opening bracket, after which Enter indents.
+  pub block_openers: &'static [&'static str],
+  /// Pairs whose closer is inserted when typing the opener.
+  pub auto_close_pairs: &'static [(char, char)],
+  pub line_comment: Option<&'static str>,
+  pub block_comment: Option<(&'static str, &'static str)>,
+  pub string_quotes: &'static [char],
+  /// A quote around character literals that also starts lifetimes and
+  /// labels, so it only counts around a single character or escape.
+  pub char_quote: Option<char>,
+  /// Built-in snippets, as prefix and TextMate-style body.
+  pub snippets: &'static [(&'static str, &'static str)],
+}
+
+const DEFAULT_BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+const DEFAULT_PAIRS: &[(char, char)] =
+  &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+const BACKTICK_PAIRS: &[(char, char)] = &[
+  ('(', ')'),
+  ('[', ']'),
+  ('{', '}'),
+  ('"', '"'),
+  ('\'', '\''),
+  ('`', '`'),
+];
+
+pub static PLAIN_TEXT: Language = Language {
+  name: "Plain Text",
+  path_suffixes: &[],
+  brackets: DEFAULT_BRACKETS,
+  block_openers: &[],
+  auto_close_pairs: DEFAULT_PAIRS,
+  line_comment: None,
+  block_comment: None,
+  string_quotes: &[],
+  char_quote: None,
+  snippets: &[],
+};
+
+pub static LANGUAGES: &[Language] = &[
+  Language {
+    name: "Rust",
+    path_suffixes: &["rs"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &[],
+    auto_close_pairs: &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    string_quotes: &['"'],
+    char_quote: Some('\''),
+    snippets: &[
+      ("fn", "fn ${1:name}($2) {\n\t$0\n}"),
+      ("test", "#[test]\nfn ${1:name}() {\n\t$0\n}"),
+      ("match", "match ${1:value} {\n\t${2:pattern} => $0,\n}"),
+      ("impl", "impl ${1:Type} {\n\t$0\n}"),
+    ],
+  },
+  Language {
+    name: "Python",
+    path_suffixes: &["py", "pyi"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &[":"],
+    auto_close_pairs: DEFAULT_PAIRS,
+    line_comment: Some("#"),
+    block_comment: None,
+    string_quotes: &['"', '\''],
+    char_quote: None,
+    snippets: &[
+      ("def", "def ${1:name}($2):\n\t${0:pass}"),
+      (
+        "class",
+        "class ${1:Name}:\n\tdef __init__(self$2):\n\t\t${0:pass}",
+      ),
+      ("main", "if __name__ == \"__main__\":\n\t${0:main()}"),
+    ],
+  },
+  Language {
+    name: "JavaScript",
+    path_suffixes: &["js", "mjs", "cjs", "jsx"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &[],
+    auto_close_pairs: BACKTICK_PAIRS,
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    string_quotes: &['"', '\'', '`'],
+    char_quote: None,
+    snippets: &[
+      ("fn", "function ${1:name}($2) {\n\t$0\n}"),
+      ("log", "console.${1|log,warn,error|}($0);"),
+    ],
+  },
+  Language {
+    name: "TypeScript",
+    path_suffixes: &["ts", "mts", "cts", "tsx"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &[],
+    auto_close_pairs: BACKTICK_PAIRS,
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    string_quotes: &['"', '\'', '`'],
+    char_quote: None,
+    snippets: &[
+      ("fn", "function ${1:name}($2): ${3:void} {\n\t$0\n}"),
+      ("log", "console.${1|log,warn,error|}($0);"),
+    ],
+  },
+  Language {
+    name: "JSON",
+    path_suffixes: &["json"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &[],
+    auto_close_pairs: &[('[', ']'), ('{', '}'), ('"', '"')],
+    line_comment: None,
+    block_comment: None,
+    string_quotes: &['"'],
+    char_quote: None,
+    snippets: &[],
+  },
+  Language {
+    name: "TOML",
+    path_suffixes: &["toml"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &[],
+    auto_close_pairs: DEFAULT_PAIRS,
+    line_comment: Some("#"),
+    block_comment: None,
+    string_quotes: &['"', '\''],
+    char_quote: None,
+    snippets: &[],
+  },
+  Language {
+    name: "Shell",
+    path_suffixes: &["sh", "bash", "zsh"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &["then", "do", "else"],
+    auto_close_pairs: BACKTICK_PAIRS,
+    line_comment: Some("#"),
+    block_comment: None,
+    string_quotes: &['"', '\''],
+    char_quote: None,
+    snippets: &[
+      ("if", "if ${1:condition}; then\n\t$0\nfi"),
+      ("for", "for ${1:item} in ${2:items}; do\n\t$0\ndone"),
+    ],
+  },
+  Language {
+    name: "Lua",
+    path_suffixes: &["lua"],
+    brackets: DEFAULT_BRACKETS,
+    block_openers: &["then", "do", "else"],
+    auto_close_pairs: DEFAULT_PAIRS,
+    line_comment: Some("--"),
+    block_comment: Some(("--[[", "]]")),
+    string_quotes: &['"', '\''],
+    char_quote: None,
+    snippets: &[],
+  },
+  Language {
+    name: "SQL",
+    path_suffixes: &["sql"],
+    brackets: &[('(', ')')],
+    block_openers: &[],
+    auto_close_pairs: &[('(', ')'), ('"', '"'), ('\'', '\'')],
+    line_comment: Some("--"),
+    block_comment: Some(("/*", "*/")),
+    string_quotes: &['\'', '"'],
+    char_quote: None,
+    snippets: &[],
+  },
+];
+
+impl Language {
+  pub fn all() -> impl Iterator<Item = &'static Language> {
+    std::iter::once(&PLAIN_TEXT).chain(LANGUAGES)
+  }
+
+  /// The language for a file, by its extension.
+  pub fn for_path(path: &Path) -> Option<&'static Language> {
+    let extension = path.extension()?.to_str()?;
+    LANGUAGES.iter().find(|language| {
+      language
+        .path_suffixes
+        .iter()
+        .any(|suffix| suffix.eq_ignore_ascii_case(extension))
+    })
+  }
+
+  pub fn by_name(name: &str) -> Option<&'static Language> {
+    Self::all().find(|language| language.name.eq_ignore_ascii_case(name))
+  }
+
+  pub fn closing_bracket(&self, open: char) -> Option<char> {
+    self
+      .brackets
+      .iter()
+      .find_map(|&(start, end)| (start == open).then_some(end))
+  }
+
+  pub fn opening_bracket(&self, close: char) -> Option<char> {
+    self
+      .brackets
+      .iter()
+      .find_map(|&(start, end)| (end == close).then_some(start))
+  }
+
+  pub fn auto_close_pair(&self, open: char) -> Option<char> {
+    self
+      .auto_close_pairs
+      .iter()
+      .find_map(|&(start, end)| (start == open).then_some(end))
+  }
+
+  pub fn is_auto_closer(&self, close: char) -> bool {
+    self.auto_close_pairs.iter().any(|&(_, end)| end == close)
+  }
+
+  /// Whether typing `open` between `before` and `after` inserts its closer
+  /// too. It only does when nothing would end up glued to the closer, and
+  /// an apostrophe inside a word never turns into a quote pair.
+  pub fn should_auto_close(&self, open: char, before: Option<char>, after: Option<char>) -> bool {
+    let Some(close) = self.auto_close_pair(open) else {
+      return false;
+    };
+    let closes_cleanly = after
+      .is_none_or(|after| after.is_whitespace() || self.is_auto_closer(after) && after != open);
+    let inside_word = open == close && before.is_some_and(char::is_alphanumeric);
+    closes_cleanly && !inside_word
+  }
+
+  /// Whether a line ending in `text` opens a block whose body is indented.
+  pub fn opens_block(&self, text: &str) -> bool {
+    let text = text.trim_end();
+    if text
+      .chars()
+      .last()
+      .is_some_and(|ch| self.closing_bracket(ch).is_some())
+    {
+      return true;
+    }
+
+    self.block_openers.iter().any(|opener| {
+      text.strip_suffix(opener).is_some_and(|rest| {
+        !opener.starts_with(|ch: char| ch.is_alphanumeric())
+          || !rest.ends_with(|ch: char| ch.is_alphanumeric() || ch == '_')
+      })
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn auto_closing() {
+    let rust = Language::for_path(Path::new("main.rs")).unwrap();
+    assert!(rust.should_auto_close('(', None, None));
+    assert!(rust.should_auto_close('(', Some('f'), Some(' ')));
+    assert!(rust.should_auto_close('(', None, Some(']')));
+    // Not when the closer would be glued to the text after it.
+    assert!(!rust.should_auto_close('(', None, Some('x')));
+    assert!(!rust.should_auto_close('"', None, Some('"')));
+    // Characters the language doesn't pair.
+    assert!(!rust.should_auto_close('\'', None, None));
+    assert!(!rust.should_auto_close('x', None, None));
+
+    // An apostrophe in a word stays one.
+    assert!(!PLAIN_TEXT.should_auto_close('\'', Some('n'), None));
+    assert!(PLAIN_TEXT.should_auto_close('\'', Some(' '), None));
+    assert!(PLAIN_TEXT.should_auto_close('(', Some('n'), None));
+  }
+}
@@ -0,0 +1,96 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+  #[default]
+  Lf,
+  Crlf,
+}
+
+impl LineEnding {
+  pub const ALL: [LineEnding; 2] = [LineEnding::Lf, LineEnding::Crlf];
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Lf => "\n",
+      Self::Crlf => "\r\n",
+    }
+  }
+
+  pub fn label(self) -> &'static str {
+    match self {
+      Self::Lf => "LF",
+      Self::Crlf => "CRLF",
+    }
+  }
+
+  /// The predominant line ending of `text`, and whether both kinds occur.
+  /// Text without line breaks is LF.
+  pub fn detect(text: &str) -> (Self, bool) {
+    let breaks = text.matches('\n').count();
+    let crlf = text.matches("\r\n").count();
+    let lf = breaks - crlf;
+    let line_ending = if crlf > lf { Self::Crlf } else { Self::Lf };
+    (line_ending, crlf > 0 && lf > 0)
+  }
+
+  /// Rewrites every line break in `text` as `self`.
+  pub fn normalize(self, text: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    match self {
+      Self::Lf => text,
+      Self::Crlf => text.replace('\n', "\r\n"),
+    }
+  }
+}
+
+/// Splits `text` into lines, pairing each with the line break that ends it.
+/// The last line's break is empty.
+pub fn lines_with_breaks(text: &str) -> impl Iterator<Item = (&str, &'static str)> {
+  let mut lines = text.split('\n').peekable();
+  std::iter::from_fn(move || {
+    let line = lines.next()?;
+    if lines.peek().is_none() {
+      Some((line, ""))
+    } else if let Some(line) = line.strip_suffix('\r') {
+      Some((line, "\r\n"))
+    } else {
+      Some((line, "\n"))
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detection() {
+    assert_eq!(LineEnding::detect(""), (LineEnding::Lf, false));
+    assert_eq!(LineEnding::detect("one line"), (LineEnding::Lf, false));
+    assert_eq!(LineEnding::detect("a\nb\n"), (LineEnding::Lf, false));
+    assert_eq!(LineEnding::detect("a\r\nb\r\n"), (LineEnding::Crlf, false));
+    // Mixed text takes the more common ending, LF on a tie.
+    assert_eq!(
+      LineEnding::detect("a\r\nb\r\nc\n"),
+      (LineEnding::Crlf, true)
+    );
+    assert_eq!(LineEnding::detect("a\r\nb\n"), (LineEnding::Lf, true));
+    // A lone carriage return isn't a line break.
+    assert_eq!(LineEnding::detect("a\rb\n"), (LineEnding::Lf, false));
+  }
+
+  #[test]
+  fn normalization() {
+    let mixed = "a\r\nb\nc\r\n";
+    assert_eq!(LineEnding::Lf.normalize(mixed), "a\nb\nc\n");
+    assert_eq!(LineEnding::Crlf.normalize(mixed), "a\r\nb\r\nc\r\n");
+    assert_eq!(LineEnding::Crlf.normalize("a\rb"), "a\rb");
+  }
+
+  #[test]
+  fn lines_pair_with_their_breaks() {
+    let lines = lines_with_breaks("a\r\nb\nc").collect::<Vec<_>>();
+    assert_eq!(lines, [("a", "\r\n"), ("b", "\n"), ("c", "")]);
+    let lines = lines_with_breaks("a\n").collect::<Vec<_>>();
+    assert_eq!(lines, [("a", "\n"), ("", "")]);
+  }
+}
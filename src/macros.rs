@@ -0,0 +1,162 @@
+use std::{fs, io, path::PathBuf};
+
+use gpui::{Action, SharedString, actions};
+
+use crate::settings;
+
+actions!(
+  macros,
+  [
+    ToggleMacroRecording,
+    ReplayMacro,
+    ReplayMacroTimes,
+    SaveMacro,
+    RunSavedMacro
+  ]
+);
+
+/// The most times a macro can be replayed in one go.
+pub const MAX_REPLAY_TIMES: usize = 1000;
+
+/// Plays the saved macro with the given name, for binding macros to keys.
+#[derive(Clone, PartialEq, Action)]
+#[action(namespace = macros, no_json)]
+pub struct RunMacro {
+  pub name: SharedString,
+}
+
+/// One recorded editor input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MacroStep {
+  /// A `text_input` action, by its qualified name.
+  Action(SharedString),
+  /// Text typed into the editor.
+  Text(String),
+}
+
+/// Reads how many times to replay a macro, from 1 to `MAX_REPLAY_TIMES`.
+pub fn parse_times(input: &str) -> Result<usize, String> {
+  match input.parse::<usize>() {
+    Ok(times @ 1..=MAX_REPLAY_TIMES) => Ok(times),
+    _ => Err(format!(
+      "Enter a number from 1 to {MAX_REPLAY_TIMES}, not \"{input}\"."
+    )),
+  }
+}
+
+/// Where named macros are saved, one file each.
+fn macros_dir() -> Option<PathBuf> {
+  settings::config_dir().map(|dir| dir.join("macros"))
+}
+
+fn macro_path(name: &str) -> io::Result<PathBuf> {
+  if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidInput,
+      "Macro names can't be empty, start with a dot or contain slashes.",
+    ));
+  }
+  macros_dir()
+    .map(|dir| dir.join(name))
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME isn't set."))
+}
+
+/// The names of the saved macros, sorted.
+pub fn saved_names() -> Vec<String> {
+  let mut names = macros_dir()
+    .and_then(|dir| fs::read_dir(dir).ok())
+    .into_iter()
+    .flatten()
+    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+    .filter(|name| !name.starts_with('.'))
+    .collect::<Vec<_>>();
+  names.sort();
+  names
+}
+
+/// Writes `steps` as `action <name>` and `text <escaped text>` lines.
+pub fn save(name: &str, steps: &[MacroStep]) -> io::Result<()> {
+  let path = macro_path(name)?;
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+  let contents = steps
+    .iter()
+    .map(|step| match step {
+      MacroStep::Action(name) => format!("action {name}\n"),
+      MacroStep::Text(text) => format!("text {}\n", escape(text)),
+    })
+    .collect::<String>();
+  fs::write(path, contents)
+}
+
+pub fn load(name: &str) -> io::Result<Vec<MacroStep>> {
+  let contents = fs::read_to_string(macro_path(name)?)?;
+  Ok(
+    contents
+      .lines()
+      .filter_map(|line| match line.split_once(' ')? {
+        ("action", name) => Some(MacroStep::Action(name.to_string().into())),
+        ("text", text) => Some(MacroStep::Text(unescape(text))),
+        _ => None,
+      })
+      .collect(),
+  )
+}
+
+fn escape(text: &str) -> String {
+  text
+    .chars()
+    .map(|ch| match ch {
+      '\\' => "\\\\".into(),
+      '\n' => "\\n".into(),
+      '\r' => "\\r".into(),
+      '\t' => "\\t".into(),
+      ch => ch.to_string(),
+    })
+    .collect()
+}
+
+fn unescape(text: &str) -> String {
+  let mut unescaped = String::with_capacity(text.len());
+  let mut chars = text.chars();
+  while let Some(ch) = chars.next() {
+    if ch != '\\' {
+      unescaped.push(ch);
+      continue;
+    }
+    match chars.next() {
+      Some('n') => unescaped.push('\n'),
+      Some('r') => unescaped.push('\r'),
+      Some('t') => unescaped.push('\t'),
+      Some(ch) => unescaped.push(ch),
+      None => unescaped.push('\\'),
+    }
+  }
+  unescaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn replay_times() {
+    assert_eq!(parse_times("1"), Ok(1));
+    assert_eq!(parse_times("1000"), Ok(1000));
+    assert!(parse_times("0").is_err());
+    assert!(parse_times("1001").is_err());
+    assert!(parse_times("18446744073709551615").is_err());
+    assert!(parse_times("-3").is_err());
+    assert!(parse_times("three").is_err());
+  }
+
+  #[test]
+  fn escaped_text_round_trips() {
+    let text = "a\\tb\nc\r\n\td\\";
+    assert_eq!(escape(text), "a\\\\tb\\nc\\r\\n\\td\\\\");
+    assert!(!escape(text).contains('\n'));
+    assert_eq!(unescape(&escape(text)), text);
+    assert_eq!(unescape("trailing\\"), "trailing\\");
+  }
+}
@@ -1,31 +1,87 @@
+use std::{env, path::PathBuf};
+
 use gpui::{
   App, AppContext, Application, Bounds, KeyBinding, WindowBounds, WindowOptions, px, size,
 };
 
+mod brackets;
+mod case;
+mod clipboard_history;
+mod comments;
+mod diagnostics;
+mod diff;
+mod diff_view;
+mod encoding;
+mod folding;
+mod gitignore;
+mod go_to_line;
+mod history;
+mod kill_ring;
+mod language;
+mod line_ending;
+mod macros;
+mod minimap;
+mod pane;
+mod picker;
+mod project_panel;
+mod prompt;
+mod pty;
+mod recovery;
+mod session;
+mod settings;
+mod snippets;
+mod status_bar;
+mod task_panel;
+mod tasks;
+mod terminal;
+mod terminal_element;
+mod terminal_panel;
 mod text_element;
 mod text_input;
+mod ui;
+mod vt;
+mod watcher;
 mod zeta;
-use crate::{text_input::Quit, zeta::Zeta};
+use crate::{
+  clipboard_history::ClipboardHistory, session::Session, settings::Settings, snippets::Snippets,
+  text_input::Quit, zeta::Zeta,
+};
 
 fn main() {
   Application::new().run(|cx: &mut App| {
-    let bounds = Bounds::centered(None, size(px(800.0), px(600.0)), cx);
+    // The session is kept for the directory given as the argument, or the
+    // current one when a file or nothing is given.
+    let (workspace, file) = match env::args_os().nth(1).map(PathBuf::from) {
+      Some(path) if path.is_dir() => (path, None),
+      file => (env::current_dir().unwrap_or_default(), file),
+    };
+    let workspace = workspace.canonicalize().unwrap_or(workspace);
+    let session = Session::load(&workspace);
+    let window_bounds = session.window.unwrap_or_else(|| {
+      WindowBounds::Windowed(Bounds::centered(None, size(px(800.0), px(600.0)), cx))
+    });
 
+    cx.set_global(Settings::load());
+    cx.set_global(Snippets::load());
+    cx.set_global(ClipboardHistory::default());
     Zeta::register(cx);
 
     let window = cx
       .open_window(
         WindowOptions {
-          window_bounds: Some(WindowBounds::Windowed(bounds)),
+          window_bounds: Some(window_bounds),
           ..Default::default()
         },
-        |_, cx| cx.new(Zeta::new),
+        |window, cx| cx.new(|cx| Zeta::new(workspace, session, window, cx)),
       )
       .unwrap();
 
     window
       .update(cx, |view, window, cx| {
-        window.focus(&view.text_input.as_mut(cx).focus_handle);
+        view.activate_editor(window, cx);
+        if let Some(file) = file {
+          view.open_path(&file, None, window, cx).detach();
+        }
         cx.activate(true);
       })
       .unwrap();
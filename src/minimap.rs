@@ -0,0 +1,184 @@
+use gpui::{
+  App, Bounds, ContentMask, ElementId, Entity, GlobalElementId, Hsla, LayoutId, PaintQuad, Pixels,
+  Style, Window, fill, point, prelude::*, px, relative, rgba, size,
+};
+
+use crate::{
+  settings::Settings,
+  text_input::{LINE_HEIGHT, TextInput},
+};
+
+pub const MINIMAP_WIDTH: Pixels = px(80.);
+/// The height of each display row in the minimap.
+const ROW_HEIGHT: Pixels = px(2.);
+/// The width of each column in the minimap.
+const COLUMN_WIDTH: Pixels = px(1.);
+
+/// Where the minimap was last painted, for mouse handling.
+#[derive(Clone, Copy)]
+pub struct MinimapLayout {
+  pub bounds: Bounds<Pixels>,
+  /// The rectangle marking the rows in the editor's viewport.
+  pub slider: Bounds<Pixels>,
+  /// How far the slider's top can move, which maps linearly to the
+  /// editor's scroll range.
+  pub track: Pixels,
+}
+
+pub struct PrepaintState {
+  text: Vec<PaintQuad>,
+  layout: MinimapLayout,
+}
+
+/// A scaled-down overview of the whole buffer, drawn as one bar per run of
+/// non-whitespace text. When the buffer is taller than the minimap, the
+/// minimap scrolls along with the editor so both reach their ends together,
+/// and only the rows in view are drawn.
+pub struct MinimapElement {
+  pub input: Entity<TextInput>,
+}
+
+impl IntoElement for MinimapElement {
+  type Element = Self;
+
+  fn into_element(self) -> Self::Element {
+    self
+  }
+}
+
+impl Element for MinimapElement {
+  type RequestLayoutState = ();
+  type PrepaintState = PrepaintState;
+
+  fn id(&self) -> Option<ElementId> {
+    None
+  }
+
+  fn source_location(&self) -> Option<&'static core::panic::Location<'static>> {
+    None
+  }
+
+  fn request_layout(
+    &mut self,
+    _id: Option<&GlobalElementId>,
+    _inspector_id: Option<&gpui::InspectorElementId>,
+    window: &mut Window,
+    cx: &mut App,
+  ) -> (LayoutId, Self::RequestLayoutState) {
+    let mut style = Style::default();
+    style.size.width = relative(1.).into();
+    style.size.height = relative(1.).into();
+    (window.request_layout(style, [], cx), ())
+  }
+
+  fn prepaint(
+    &mut self,
+    _id: Option<&GlobalElementId>,
+    _inspector_id: Option<&gpui::InspectorElementId>,
+    bounds: Bounds<Pixels>,
+    _request_layout: &mut Self::RequestLayoutState,
+    window: &mut Window,
+    cx: &mut App,
+  ) -> Self::PrepaintState {
+    let input = self.input.read(cx);
+    let tab_size = cx.global::<Settings>().tab_size;
+    let display_map = input.display_map();
+    let row_count = display_map.row_count();
+
+    let viewport = input.scroll_handle.bounds().size.height;
+    let scroll_top = -input.scroll_handle.offset().y;
+    let max_scroll = input.scroll_handle.max_offset().height;
+    let fraction = if max_scroll > px(0.) {
+      (scroll_top / max_scroll).clamp(0., 1.)
+    } else {
+      0.
+    };
+
+    let content_height = ROW_HEIGHT * row_count as f32;
+    let minimap_scroll = (content_height - bounds.size.height).max(px(0.)) * fraction;
+    let slider_height = (ROW_HEIGHT * (viewport / LINE_HEIGHT)).min(bounds.size.height);
+    let slider = Bounds::new(
+      point(
+        bounds.left(),
+        bounds.top() + ROW_HEIGHT * (scroll_top / LINE_HEIGHT) - minimap_scroll,
+      ),
+      size(bounds.size.width, slider_height),
+    );
+    let track = content_height.min(bounds.size.height) - slider_height;
+
+    let first_row = (minimap_scroll / ROW_HEIGHT).floor() as usize;
+    let visible_rows = (bounds.size.height / ROW_HEIGHT).ceil() as usize + 1;
+    let max_columns = (bounds.size.width / COLUMN_WIDTH).floor() as usize;
+    let color = window.text_style().color.opacity(0.5);
+    let mut text = Vec::new();
+    for row in first_row..(first_row + visible_rows).min(row_count) {
+      let line = display_map.line_for_row(row);
+      let top = bounds.top() + ROW_HEIGHT * row as f32 - minimap_scroll;
+      let mut column = 0;
+      let mut run_start = None;
+      for ch in input.content.byte_slice(input.line_range(line)).chars() {
+        if column >= max_columns {
+          break;
+        }
+        let width = if ch == '\t' {
+          tab_size - column % tab_size
+        } else {
+          1
+        };
+        if ch.is_whitespace() {
+          if let Some(start) = run_start.take() {
+            text.push(bar(bounds, top, start, column, color));
+          }
+        } else if run_start.is_none() {
+          run_start = Some(column);
+        }
+        column += width;
+      }
+      if let Some(start) = run_start {
+        text.push(bar(bounds, top, start, column.min(max_columns), color));
+      }
+    }
+
+    PrepaintState {
+      text,
+      layout: MinimapLayout {
+        bounds,
+        slider,
+        track,
+      },
+    }
+  }
+
+  fn paint(
+    &mut self,
+    _id: Option<&GlobalElementId>,
+    _inspector_id: Option<&gpui::InspectorElementId>,
+    bounds: Bounds<Pixels>,
+    _request_layout: &mut Self::RequestLayoutState,
+    prepaint: &mut Self::PrepaintState,
+    window: &mut Window,
+    cx: &mut App,
+  ) {
+    window.with_content_mask(Some(ContentMask { bounds }), |window| {
+      for bar in prepaint.text.drain(..) {
+        window.paint_quad(bar);
+      }
+      window.paint_quad(fill(prepaint.layout.slider, rgba(0x0000001a)));
+    });
+    let layout = prepaint.layout;
+    self.input.update(cx, |input, _cx| {
+      input.last_minimap = Some(layout);
+    });
+  }
+}
+
+/// The bar for the columns `start..end` of the row at `top`.
+fn bar(bounds: Bounds<Pixels>, top: Pixels, start: usize, end: usize, color: Hsla) -> PaintQuad {
+  fill(
+    Bounds::new(
+      point(bounds.left() + COLUMN_WIDTH * start as f32, top),
+      size(COLUMN_WIDTH * (end - start) as f32, ROW_HEIGHT * 0.75),
+    ),
+    color,
+  )
+}
@@ -0,0 +1,170 @@
+use gpui::{
+  Context, Entity, EventEmitter, MouseButton, Subscription, Window, div, prelude::*, px, rgb,
+};
+
+use crate::{text_input::TextInput, ui::button};
+
+pub enum PaneEvent {
+  /// The pane was clicked, making it the one commands apply to.
+  Activated,
+  ActiveItemChanged,
+  /// The close button of the tab at this index was clicked.
+  CloseItem(usize),
+  ShowDiff,
+}
+
+/// A group of editors shown as tabs, one of the side by side splits of the
+/// window. A pane always has at least one editor.
+pub struct Pane {
+  pub items: Vec<Entity<TextInput>>,
+  pub active: usize,
+  /// Observations of `items`, at the same indices.
+  observations: Vec<Subscription>,
+}
+
+impl EventEmitter<PaneEvent> for Pane {}
+
+impl Pane {
+  pub fn new(items: Vec<Entity<TextInput>>, active: usize, cx: &mut Context<Self>) -> Self {
+    Self {
+      observations: items
+        .iter()
+        .map(|item| cx.observe(item, |_, _, cx| cx.notify()))
+        .collect(),
+      items,
+      active,
+    }
+  }
+
+  pub fn active_item(&self) -> Entity<TextInput> {
+    self.items[self.active].clone()
+  }
+
+  /// Adds `item` after the active tab and activates it.
+  pub fn add_item(&mut self, item: Entity<TextInput>, cx: &mut Context<Self>) {
+    let ix = (self.active + 1).min(self.items.len());
+    self
+      .observations
+      .insert(ix, cx.observe(&item, |_, _, cx| cx.notify()));
+    self.items.insert(ix, item);
+    self.activate(ix, cx);
+  }
+
+  /// Removes the tab at `ix`, activating its neighbour if it was active.
+  pub fn remove_item(&mut self, ix: usize, cx: &mut Context<Self>) {
+    self.items.remove(ix);
+    drop(self.observations.remove(ix));
+    if self.active > ix || self.active == self.items.len() {
+      self.active = self.active.saturating_sub(1);
+    }
+    cx.emit(PaneEvent::ActiveItemChanged);
+    cx.notify();
+  }
+
+  pub fn activate(&mut self, ix: usize, cx: &mut Context<Self>) {
+    self.active = ix;
+    cx.emit(PaneEvent::ActiveItemChanged);
+    cx.notify();
+  }
+
+  /// Activates the tab `delta` places from the active one, wrapping around.
+  pub fn activate_relative(&mut self, delta: isize, cx: &mut Context<Self>) {
+    let len = self.items.len() as isize;
+    self.activate((self.active as isize + delta).rem_euclid(len) as usize, cx);
+  }
+
+  fn render_tab(&self, ix: usize, cx: &mut Context<Self>) -> impl IntoElement + use<> {
+    let editor = self.items[ix].read(cx);
+    let mut title = editor.title().to_string();
+    let deleted = editor.deleted_on_disk;
+    if deleted {
+      title.push_str(" (deleted)");
+    }
+    let dirty = editor.is_dirty();
+    div()
+      .flex()
+      .items_center()
+      .gap(px(6.))
+      .px(px(12.))
+      .when(ix == self.active, |this| this.bg(rgb(0xeeeeee)))
+      .on_mouse_down(
+        MouseButton::Left,
+        cx.listener(move |this, _, _, cx| this.activate(ix, cx)),
+      )
+      .child(div().when(deleted, |this| this.line_through()).child(title))
+      .child(
+        div()
+          .hover(|style| style.bg(rgb(0xcccccc)))
+          .child(if dirty { "\u{25CF}" } else { "\u{00D7}" })
+          .on_mouse_down(
+            MouseButton::Left,
+            cx.listener(move |_, _, _, cx| {
+              cx.stop_propagation();
+              cx.emit(PaneEvent::Activated);
+              cx.emit(PaneEvent::CloseItem(ix));
+            }),
+          ),
+      )
+  }
+
+  /// Offers to resolve a change on disk to a file with unsaved changes.
+  fn render_conflict_banner(&self, cx: &mut Context<Self>) -> impl IntoElement {
+    let editor = self.active_item();
+    let large_file = editor.read(cx).large_file;
+    let reload_editor = editor.clone();
+    div()
+      .flex()
+      .items_center()
+      .gap(px(8.))
+      .px(px(8.))
+      .py(px(4.))
+      .bg(rgb(0xffe8a0))
+      .child(div().flex_1().child("The file has changed on disk."))
+      .child(
+        button("Reload").on_mouse_down(MouseButton::Left, move |_, _, cx| {
+          reload_editor
+            .update(cx, |editor, cx| editor.reload(cx))
+            .detach();
+        }),
+      )
+      .child(
+        button("Keep Mine").on_mouse_down(MouseButton::Left, move |_, _, cx| {
+          editor.update(cx, |editor, cx| editor.keep_local_changes(cx));
+        }),
+      )
+      // Diffing compares whole files line by line.
+      .when(!large_file, |this| {
+        this.child(button("Show Diff").on_mouse_down(
+          MouseButton::Left,
+          cx.listener(|_, _, _, cx| cx.emit(PaneEvent::ShowDiff)),
+        ))
+      })
+  }
+}
+
+impl Render for Pane {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    let editor = self.active_item();
+    div()
+      .flex()
+      .flex_col()
+      .flex_1()
+      .min_w_0()
+      .h_full()
+      .on_mouse_down(
+        MouseButton::Left,
+        cx.listener(|_, _, _, cx| cx.emit(PaneEvent::Activated)),
+      )
+      .child(
+        div()
+          .flex()
+          .h(px(28.))
+          .bg(rgb(0xcccccc))
+          .children((0..self.items.len()).map(|ix| self.render_tab(ix, cx))),
+      )
+      .when(editor.read(cx).disk_conflict, |this| {
+        this.child(self.render_conflict_banner(cx))
+      })
+      .child(div().flex_1().min_h_0().child(editor))
+  }
+}
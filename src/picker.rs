@@ -0,0 +1,124 @@
+use gpui::{
+  App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, MouseButton,
+  SharedString, Subscription, Window, div, prelude::*, px, rgb,
+};
+
+use crate::text_input::{Cancel, Down, Newline, TextInput, Up};
+
+type ConfirmHandler = Box<dyn Fn(usize, &mut Window, &mut App)>;
+
+/// A modal list that filters its items by the typed query and reports the
+/// index of the confirmed item.
+pub struct Picker {
+  title: SharedString,
+  items: Vec<SharedString>,
+  query: Entity<TextInput>,
+  matches: Vec<usize>,
+  selected: usize,
+  on_confirm: ConfirmHandler,
+  _subscription: Subscription,
+}
+
+impl EventEmitter<DismissEvent> for Picker {}
+
+impl Picker {
+  pub fn new(
+    title: impl Into<SharedString>,
+    items: Vec<SharedString>,
+    selected: usize,
+    on_confirm: impl Fn(usize, &mut Window, &mut App) + 'static,
+    cx: &mut Context<Self>,
+  ) -> Self {
+    let query = cx.new(|cx| {
+      let mut input = TextInput::new("Filter...", cx);
+      input.single_line = true;
+      input
+    });
+    let subscription = cx.observe(&query, |this, _, cx| this.update_matches(cx));
+    Self {
+      title: title.into(),
+      matches: (0..items.len()).collect(),
+      items,
+      query,
+      selected,
+      on_confirm: Box::new(on_confirm),
+      _subscription: subscription,
+    }
+  }
+
+  fn update_matches(&mut self, cx: &mut Context<Self>) {
+    let query = self.query.read(cx).content.to_string().to_lowercase();
+    let matches = (0..self.items.len())
+      .filter(|&ix| self.items[ix].to_lowercase().contains(query.trim()))
+      .collect::<Vec<_>>();
+    if matches != self.matches {
+      self.matches = matches;
+      self.selected = 0;
+      cx.notify();
+    }
+  }
+
+  fn up(&mut self, _: &Up, _: &mut Window, cx: &mut Context<Self>) {
+    self.selected = self.selected.saturating_sub(1);
+    cx.notify();
+  }
+
+  fn down(&mut self, _: &Down, _: &mut Window, cx: &mut Context<Self>) {
+    if self.selected + 1 < self.matches.len() {
+      self.selected += 1;
+      cx.notify();
+    }
+  }
+
+  fn confirm(&mut self, _: &Newline, window: &mut Window, cx: &mut Context<Self>) {
+    self.confirm_match(self.selected, window, cx);
+  }
+
+  fn confirm_match(&mut self, ix: usize, window: &mut Window, cx: &mut Context<Self>) {
+    if let Some(&item) = self.matches.get(ix) {
+      (self.on_confirm)(item, window, cx);
+    }
+    cx.emit(DismissEvent);
+  }
+
+  fn cancel(&mut self, _: &Cancel, _: &mut Window, cx: &mut Context<Self>) {
+    cx.emit(DismissEvent);
+  }
+}
+
+impl Focusable for Picker {
+  fn focus_handle(&self, cx: &App) -> FocusHandle {
+    self.query.read(cx).focus_handle.clone()
+  }
+}
+
+impl Render for Picker {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    div()
+      .key_context("Picker")
+      .on_action(cx.listener(Self::up))
+      .on_action(cx.listener(Self::down))
+      .on_action(cx.listener(Self::confirm))
+      .on_action(cx.listener(Self::cancel))
+      .w(px(400.))
+      .p(px(8.))
+      .flex()
+      .flex_col()
+      .gap(px(4.))
+      .bg(rgb(0xdddddd))
+      .border_1()
+      .border_color(rgb(0x999999))
+      .child(div().text_sm().child(self.title.clone()))
+      .child(div().h(px(30. + 4. * 2.)).child(self.query.clone()))
+      .children(self.matches.iter().enumerate().map(|(ix, &item)| {
+        div()
+          .px(px(4.))
+          .when(ix == self.selected, |this| this.bg(rgb(0xbbccff)))
+          .on_mouse_down(
+            MouseButton::Left,
+            cx.listener(move |this, _, window, cx| this.confirm_match(ix, window, cx)),
+          )
+          .child(self.items[item].clone())
+      }))
+  }
+}
@@ -0,0 +1,703 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use gpui::{
+  App, Context, Entity, EventEmitter, FocusHandle, Focusable, MouseButton, PromptLevel,
+  ScrollHandle, Task, Window, actions, div, prelude::*, px, rgb,
+};
+
+use crate::{
+  gitignore::Gitignore,
+  text_input::{Cancel, Delete, Down, Left, Newline, Right, TextInput, Up},
+  ui::show_error,
+  watcher,
+};
+
+actions!(
+  project_panel,
+  [
+    ToggleProjectPanel,
+    ToggleFocus,
+    RevealActiveFile,
+    NewEntry,
+    NewDirectory,
+    RenameEntry,
+    DuplicateEntry,
+    DeleteEntry
+  ]
+);
+
+const ROW_HEIGHT: f32 = 22.;
+const INDENT: f32 = 12.;
+
+#[derive(Clone, PartialEq)]
+struct Entry {
+  path: PathBuf,
+  is_dir: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EditKind {
+  NewFile,
+  NewDirectory,
+  Rename,
+  Duplicate,
+}
+
+/// A name being typed for a new, renamed or duplicated entry.
+struct Edit {
+  kind: EditKind,
+  /// The directory to create in, or the entry to rename or duplicate.
+  target: PathBuf,
+  input: Entity<TextInput>,
+}
+
+pub enum ProjectPanelEvent {
+  OpenFile(PathBuf),
+  Renamed { from: PathBuf, to: PathBuf },
+}
+
+/// The file tree of the workspace directory. Directories are listed when
+/// first expanded and relisted periodically to follow changes on disk.
+/// Entries matched by a `.gitignore` are hidden.
+pub struct ProjectPanel {
+  root: PathBuf,
+  /// The entries of each listed directory, directories first.
+  children: HashMap<PathBuf, Vec<Entry>>,
+  expanded: HashSet<PathBuf>,
+  selected: Option<PathBuf>,
+  /// Scrolls the selection into view on the next render.
+  scroll_to_selected: bool,
+  edit: Option<Edit>,
+  focus_handle: FocusHandle,
+  scroll_handle: ScrollHandle,
+  _poll_task: Task<()>,
+}
+
+impl EventEmitter<ProjectPanelEvent> for ProjectPanel {}
+
+impl ProjectPanel {
+  pub fn new(root: PathBuf, cx: &mut Context<Self>) -> Self {
+    let poll_task = cx.spawn(async move |this, cx| {
+      loop {
+        if this.update(cx, |this, cx| this.refresh(cx)).is_err() {
+          break;
+        }
+        cx.background_executor().timer(watcher::POLL_INTERVAL).await;
+      }
+    });
+    Self {
+      expanded: HashSet::from([root.clone()]),
+      root,
+      children: HashMap::new(),
+      selected: None,
+      scroll_to_selected: false,
+      edit: None,
+      focus_handle: cx.focus_handle(),
+      scroll_handle: ScrollHandle::new(),
+      _poll_task: poll_task,
+    }
+  }
+
+  /// Relists every expanded directory.
+  fn refresh(&mut self, cx: &mut Context<Self>) {
+    let dirs = self.expanded.iter().cloned().collect();
+    self.list(dirs, cx);
+  }
+
+  /// Lists `dirs` in the background. Directories that are gone are
+  /// collapsed.
+  fn list(&mut self, dirs: Vec<PathBuf>, cx: &mut Context<Self>) {
+    let root = self.root.clone();
+    cx.spawn(async move |this, cx| {
+      let listings = cx
+        .background_spawn(async move {
+          dirs
+            .into_iter()
+            .map(|dir| {
+              let entries = list_dir(&root, &dir).ok();
+              (dir, entries)
+            })
+            .collect::<Vec<_>>()
+        })
+        .await;
+      this
+        .update(cx, |this, cx| {
+          for (dir, entries) in listings {
+            match entries {
+              Some(entries) if this.children.get(&dir) != Some(&entries) => {
+                this.children.insert(dir, entries);
+                cx.notify();
+              }
+              Some(_) => {}
+              None if dir != this.root => {
+                this.children.remove(&dir);
+                this.expanded.remove(&dir);
+                cx.notify();
+              }
+              None => {}
+            }
+          }
+        })
+        .ok();
+    })
+    .detach();
+  }
+
+  /// The visible entries with their depth, in display order.
+  fn rows(&self) -> Vec<(usize, &Entry)> {
+    let mut rows = Vec::new();
+    self.push_rows(&self.root, 0, &mut rows);
+    rows
+  }
+
+  fn push_rows<'a>(&'a self, dir: &Path, depth: usize, rows: &mut Vec<(usize, &'a Entry)>) {
+    for entry in self.children.get(dir).into_iter().flatten() {
+      rows.push((depth, entry));
+      if entry.is_dir && self.expanded.contains(&entry.path) {
+        self.push_rows(&entry.path, depth + 1, rows);
+      }
+    }
+  }
+
+  fn selected_entry(&self) -> Option<Entry> {
+    let selected = self.selected.as_ref()?;
+    self
+      .rows()
+      .into_iter()
+      .find(|(_, entry)| &entry.path == selected)
+      .map(|(_, entry)| entry.clone())
+  }
+
+  fn select(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+    self.selected = Some(path);
+    self.scroll_to_selected = true;
+    cx.notify();
+  }
+
+  fn set_expanded(&mut self, dir: &Path, expanded: bool, cx: &mut Context<Self>) {
+    if expanded {
+      self.expanded.insert(dir.to_path_buf());
+      if !self.children.contains_key(dir) {
+        self.list(vec![dir.to_path_buf()], cx);
+      }
+    } else {
+      self.expanded.remove(dir);
+    }
+    cx.notify();
+  }
+
+  /// Expands the directories leading to `path` and selects it.
+  pub fn reveal(&mut self, path: &Path, cx: &mut Context<Self>) {
+    let Ok(relative) = path.strip_prefix(&self.root) else {
+      return;
+    };
+    let mut dir = self.root.clone();
+    let mut unlisted = Vec::new();
+    for component in relative.parent().into_iter().flat_map(Path::components) {
+      dir.push(component);
+      if self.expanded.insert(dir.clone()) && !self.children.contains_key(&dir) {
+        unlisted.push(dir.clone());
+      }
+    }
+    self.list(unlisted, cx);
+    self.select(path.to_path_buf(), cx);
+  }
+
+  fn open(&mut self, entry: &Entry, cx: &mut Context<Self>) {
+    if entry.is_dir {
+      let expanded = self.expanded.contains(&entry.path);
+      self.set_expanded(&entry.path, !expanded, cx);
+    } else {
+      cx.emit(ProjectPanelEvent::OpenFile(entry.path.clone()));
+    }
+  }
+
+  fn select_relative(&mut self, delta: isize, cx: &mut Context<Self>) {
+    if self.edit.is_some() {
+      return;
+    }
+    let rows = self.rows();
+    let Some(last) = rows.len().checked_sub(1) else {
+      return;
+    };
+    let ix = match rows
+      .iter()
+      .position(|(_, entry)| Some(&entry.path) == self.selected.as_ref())
+    {
+      Some(ix) => ix.saturating_add_signed(delta).min(last),
+      None => 0,
+    };
+    let path = rows[ix].1.path.clone();
+    self.select(path, cx);
+  }
+
+  fn up(&mut self, _: &Up, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_relative(-1, cx);
+  }
+
+  fn down(&mut self, _: &Down, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_relative(1, cx);
+  }
+
+  /// Expands the selected directory, or moves into it once expanded.
+  fn right(&mut self, _: &Right, _: &mut Window, cx: &mut Context<Self>) {
+    let Some(entry) = self.selected_entry().filter(|entry| entry.is_dir) else {
+      return;
+    };
+    if self.expanded.contains(&entry.path) {
+      self.select_relative(1, cx);
+    } else {
+      self.set_expanded(&entry.path, true, cx);
+    }
+  }
+
+  /// Collapses the selected directory, or moves to the parent.
+  fn left(&mut self, _: &Left, _: &mut Window, cx: &mut Context<Self>) {
+    let Some(entry) = self.selected_entry() else {
+      return;
+    };
+    if entry.is_dir && self.expanded.contains(&entry.path) {
+      self.set_expanded(&entry.path, false, cx);
+    } else if let Some(parent) = entry.path.parent()
+      && parent != self.root
+    {
+      self.select(parent.to_path_buf(), cx);
+    }
+  }
+
+  fn confirm(&mut self, _: &Newline, window: &mut Window, cx: &mut Context<Self>) {
+    if self.edit.is_some() {
+      self.confirm_edit(window, cx);
+    } else if let Some(entry) = self.selected_entry() {
+      self.open(&entry, cx);
+    }
+  }
+
+  fn cancel(&mut self, _: &Cancel, window: &mut Window, cx: &mut Context<Self>) {
+    if self.edit.take().is_some() {
+      window.focus(&self.focus_handle);
+      cx.notify();
+    } else {
+      cx.propagate();
+    }
+  }
+
+  /// The directory new entries go in: the selected one, or that of the
+  /// selected file.
+  fn target_dir(&self) -> PathBuf {
+    match self.selected_entry() {
+      Some(entry) if entry.is_dir => entry.path,
+      Some(entry) => entry
+        .path
+        .parent()
+        .map_or(self.root.clone(), Path::to_path_buf),
+      None => self.root.clone(),
+    }
+  }
+
+  fn start_edit(
+    &mut self,
+    kind: EditKind,
+    target: PathBuf,
+    name: &str,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    // The name is selected up to its extension.
+    let stem_len = match name.rfind('.') {
+      Some(ix) if ix > 0 => ix,
+      _ => name.len(),
+    };
+    let input = cx.new(|cx| {
+      let mut input = TextInput::new("Name", cx);
+      input.single_line = true;
+      input.set_text(name, 0..stem_len, cx);
+      input
+    });
+    window.focus(&input.read(cx).focus_handle);
+    self.edit = Some(Edit {
+      kind,
+      target,
+      input,
+    });
+    cx.notify();
+  }
+
+  fn new_entry(&mut self, _: &NewEntry, window: &mut Window, cx: &mut Context<Self>) {
+    let dir = self.target_dir();
+    self.set_expanded(&dir, true, cx);
+    self.start_edit(EditKind::NewFile, dir, "", window, cx);
+  }
+
+  fn new_directory(&mut self, _: &NewDirectory, window: &mut Window, cx: &mut Context<Self>) {
+    let dir = self.target_dir();
+    self.set_expanded(&dir, true, cx);
+    self.start_edit(EditKind::NewDirectory, dir, "", window, cx);
+  }
+
+  fn rename_entry(&mut self, _: &RenameEntry, window: &mut Window, cx: &mut Context<Self>) {
+    let Some(entry) = self.selected_entry() else {
+      return;
+    };
+    let name = file_name(&entry.path);
+    self.start_edit(EditKind::Rename, entry.path, &name, window, cx);
+  }
+
+  fn duplicate_entry(&mut self, _: &DuplicateEntry, window: &mut Window, cx: &mut Context<Self>) {
+    let Some(entry) = self.selected_entry() else {
+      return;
+    };
+    let name = file_name(&entry.path);
+    let copy = match name.rfind('.') {
+      Some(ix) if ix > 0 && !entry.is_dir => format!("{} copy{}", &name[..ix], &name[ix..]),
+      _ => format!("{name} copy"),
+    };
+    self.start_edit(EditKind::Duplicate, entry.path, &copy, window, cx);
+  }
+
+  fn confirm_edit(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+    let Some(edit) = self.edit.take() else {
+      return;
+    };
+    window.focus(&self.focus_handle);
+    cx.notify();
+    let name = edit.input.read(cx).content.to_string().trim().to_string();
+    if name.is_empty() {
+      return;
+    }
+    let path = match edit.kind {
+      EditKind::NewFile | EditKind::NewDirectory => edit.target.join(&name),
+      EditKind::Rename | EditKind::Duplicate => edit
+        .target
+        .parent()
+        .map_or(self.root.join(&name), |parent| parent.join(&name)),
+    };
+    let (kind, target) = (edit.kind, edit.target);
+    // Duplicating a directory can take a while, so the file system is
+    // changed in the background.
+    let result = cx.background_spawn({
+      let (name, path, target) = (name.clone(), path.clone(), target.clone());
+      async move {
+        if name.contains(['/', '\\']) {
+          Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Names can't contain path separators",
+          ))
+        } else if path.exists() && path != target {
+          Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{name} already exists"),
+          ))
+        } else {
+          match kind {
+            EditKind::NewFile => fs::File::create_new(&path).map(drop),
+            EditKind::NewDirectory => fs::create_dir(&path),
+            EditKind::Rename => fs::rename(&target, &path),
+            EditKind::Duplicate => copy_recursive(&target, &path),
+          }
+        }
+      }
+    });
+    cx.spawn_in(window, async move |this, cx| {
+      let result = result.await;
+      this
+        .update_in(cx, |this, window, cx| match result {
+          Ok(()) => {
+            match kind {
+              EditKind::NewFile => cx.emit(ProjectPanelEvent::OpenFile(path.clone())),
+              EditKind::Rename => cx.emit(ProjectPanelEvent::Renamed {
+                from: target,
+                to: path.clone(),
+              }),
+              _ => {}
+            }
+            this.refresh(cx);
+            this.select(path, cx);
+          }
+          Err(error) => {
+            let message = match kind {
+              EditKind::Rename => format!("Couldn't rename to {name}"),
+              _ => format!("Couldn't create {name}"),
+            };
+            show_error(&message, &error, window, cx)
+          }
+        })
+        .ok();
+    })
+    .detach();
+  }
+
+  fn delete_entry(&mut self, _: &DeleteEntry, window: &mut Window, cx: &mut Context<Self>) {
+    let Some(entry) = self.selected_entry() else {
+      return;
+    };
+    let answer = window.prompt(
+      PromptLevel::Warning,
+      &format!("Delete {}?", file_name(&entry.path)),
+      Some("This can't be undone."),
+      &["Delete", "Cancel"],
+      cx,
+    );
+    cx.spawn_in(window, async move |this, cx| {
+      if answer.await != Ok(0) {
+        return;
+      }
+      let path = entry.path.clone();
+      let result = cx
+        .background_spawn(async move {
+          if entry.is_dir {
+            fs::remove_dir_all(&path)
+          } else {
+            fs::remove_file(&path)
+          }
+        })
+        .await;
+      this
+        .update_in(cx, |this, window, cx| {
+          if let Err(error) = result {
+            let message = format!("Couldn't delete {}", entry.path.display());
+            show_error(&message, &error, window, cx);
+          }
+          this.refresh(cx);
+        })
+        .ok();
+    })
+    .detach();
+  }
+
+  /// The delete key deletes the selected entry too.
+  fn delete(&mut self, _: &Delete, window: &mut Window, cx: &mut Context<Self>) {
+    self.delete_entry(&DeleteEntry, window, cx);
+  }
+
+  fn render_row(
+    &self,
+    depth: usize,
+    entry: &Entry,
+    cx: &mut Context<Self>,
+  ) -> impl IntoElement + use<> {
+    let selected = self.selected.as_ref() == Some(&entry.path);
+    let icon = match (entry.is_dir, self.expanded.contains(&entry.path)) {
+      (true, true) => "\u{25BE}",
+      (true, false) => "\u{25B8}",
+      (false, _) => "",
+    };
+    let clicked = entry.clone();
+    div()
+      .flex()
+      .flex_none()
+      .items_center()
+      .gap(px(4.))
+      .h(px(ROW_HEIGHT))
+      .pl(px(4. + INDENT * depth as f32))
+      .when(selected, |this| this.bg(rgb(0xc8d8f0)))
+      .hover(|style| style.bg(rgb(0xdddddd)))
+      .child(div().w(px(10.)).child(icon))
+      .child(file_name(&entry.path))
+      .on_mouse_down(
+        MouseButton::Left,
+        cx.listener(move |this, _, window, cx| {
+          window.focus(&this.focus_handle);
+          this.select(clicked.path.clone(), cx);
+          this.open(&clicked, cx);
+        }),
+      )
+  }
+
+  fn render_edit(&self, depth: usize) -> Option<impl IntoElement + use<>> {
+    let edit = self.edit.as_ref()?;
+    Some(
+      div()
+        .flex_none()
+        .h(px(ROW_HEIGHT))
+        .pl(px(18. + INDENT * depth as f32))
+        .bg(rgb(0xffffff))
+        .child(edit.input.clone()),
+    )
+  }
+}
+
+impl Focusable for ProjectPanel {
+  fn focus_handle(&self, _: &App) -> FocusHandle {
+    self.focus_handle.clone()
+  }
+}
+
+impl Render for ProjectPanel {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    let edit = self
+      .edit
+      .as_ref()
+      .map(|edit| (edit.kind, edit.target.clone()));
+    let rows = self
+      .rows()
+      .into_iter()
+      .map(|(depth, entry)| (depth, entry.clone()))
+      .collect::<Vec<_>>();
+    if std::mem::take(&mut self.scroll_to_selected)
+      && let Some(ix) = rows
+        .iter()
+        .position(|(_, entry)| Some(&entry.path) == self.selected.as_ref())
+    {
+      self.scroll_handle.scroll_to_item(ix);
+    }
+
+    let mut children = Vec::new();
+    if let Some((EditKind::NewFile | EditKind::NewDirectory, target)) = &edit
+      && *target == self.root
+    {
+      children.extend(self.render_edit(0).map(IntoElement::into_any_element));
+    }
+    for (depth, entry) in &rows {
+      let editing = edit
+        .as_ref()
+        .map(|(kind, target)| (*kind, target == &entry.path));
+      if editing == Some((EditKind::Rename, true)) {
+        children.extend(self.render_edit(*depth).map(IntoElement::into_any_element));
+        continue;
+      }
+      children.push(self.render_row(*depth, entry, cx).into_any_element());
+      match editing {
+        Some((EditKind::NewFile | EditKind::NewDirectory, true)) => children.extend(
+          self
+            .render_edit(depth + 1)
+            .map(IntoElement::into_any_element),
+        ),
+        Some((EditKind::Duplicate, true)) => {
+          children.extend(self.render_edit(*depth).map(IntoElement::into_any_element))
+        }
+        _ => {}
+      }
+    }
+
+    div()
+      .key_context(if edit.is_some() {
+        "ProjectPanel editing"
+      } else {
+        "ProjectPanel"
+      })
+      .track_focus(&self.focus_handle)
+      .on_action(cx.listener(Self::up))
+      .on_action(cx.listener(Self::down))
+      .on_action(cx.listener(Self::left))
+      .on_action(cx.listener(Self::right))
+      .on_action(cx.listener(Self::confirm))
+      .on_action(cx.listener(Self::cancel))
+      .on_action(cx.listener(Self::new_entry))
+      .on_action(cx.listener(Self::new_directory))
+      .on_action(cx.listener(Self::rename_entry))
+      .on_action(cx.listener(Self::duplicate_entry))
+      .on_action(cx.listener(Self::delete_entry))
+      .on_action(cx.listener(Self::delete))
+      .flex_none()
+      .w(px(220.))
+      .h_full()
+      .bg(rgb(0xeeeeee))
+      .text_sm()
+      .child(
+        div()
+          .id("project-panel-scroll")
+          .size_full()
+          .flex()
+          .flex_col()
+          .overflow_y_scroll()
+          .track_scroll(&self.scroll_handle)
+          .children(children),
+      )
+  }
+}
+
+fn file_name(path: &Path) -> String {
+  path.file_name().map_or_else(
+    || path.display().to_string(),
+    |name| name.to_string_lossy().into_owned(),
+  )
+}
+
+/// The entries of `dir` that aren't ignored, directories first, each group
+/// sorted by name.
+fn list_dir(root: &Path, dir: &Path) -> io::Result<Vec<Entry>> {
+  let gitignore = Gitignore::for_dir(root, dir);
+  let mut entries = fs::read_dir(dir)?
+    .flatten()
+    .filter(|entry| entry.file_name() != ".git")
+    .map(|entry| {
+      let path = entry.path();
+      Entry {
+        is_dir: path.is_dir(),
+        path,
+      }
+    })
+    .filter(|entry| !gitignore.is_ignored(&entry.path, entry.is_dir))
+    .collect::<Vec<_>>();
+  entries.sort_by_cached_key(|entry| (!entry.is_dir, file_name(&entry.path).to_lowercase()));
+  Ok(entries)
+}
+
+/// Copies a file or directory tree. Symlinks are copied as links rather
+/// than followed, so a link to a parent directory can't recurse forever.
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+  let file_type = fs::symlink_metadata(from)?.file_type();
+  if file_type.is_symlink() {
+    copy_symlink(from, to)
+  } else if file_type.is_dir() {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+      let entry = entry?;
+      copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+  } else {
+    fs::copy(from, to).map(drop)
+  }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+  std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+  let target = fs::read_link(from)?;
+  if fs::metadata(from).is_ok_and(|metadata| metadata.is_dir()) {
+    std::os::windows::fs::symlink_dir(target, to)
+  } else {
+    std::os::windows::fs::symlink_file(target, to)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[cfg(unix)]
+  #[test]
+  fn copy_keeps_symlinks_as_links() {
+    let root = std::env::temp_dir().join(format!("zeta-copy-{}", std::process::id()));
+    let from = root.join("from");
+    fs::create_dir_all(from.join("dir")).unwrap();
+    fs::write(from.join("dir/file"), "text").unwrap();
+    // A link back up the tree would recurse forever if followed.
+    std::os::unix::fs::symlink("..", from.join("dir/parent")).unwrap();
+    std::os::unix::fs::symlink("dir/file", from.join("link")).unwrap();
+
+    let to = root.join("to");
+    copy_recursive(&from, &to).unwrap();
+    assert_eq!(fs::read_to_string(to.join("dir/file")).unwrap(), "text");
+    assert_eq!(
+      fs::read_link(to.join("dir/parent")).unwrap(),
+      Path::new("..")
+    );
+    assert_eq!(
+      fs::read_link(to.join("link")).unwrap(),
+      Path::new("dir/file")
+    );
+
+    fs::remove_dir_all(&root).ok();
+  }
+}
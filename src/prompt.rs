@@ -0,0 +1,72 @@
+use gpui::{
+  App, Context, DismissEvent, Entity, EventEmitter, FocusHandle, Focusable, SharedString, Window,
+  div, prelude::*, px, rgb,
+};
+
+use crate::text_input::{Cancel, Newline, TextInput};
+
+type ConfirmHandler = Box<dyn Fn(&str, &mut Window, &mut App)>;
+
+/// A modal asking for a line of text, reported when confirmed with Enter.
+pub struct Prompt {
+  title: SharedString,
+  input: Entity<TextInput>,
+  on_confirm: ConfirmHandler,
+}
+
+impl EventEmitter<DismissEvent> for Prompt {}
+
+impl Prompt {
+  pub fn new(
+    title: impl Into<SharedString>,
+    placeholder: impl Into<SharedString>,
+    on_confirm: impl Fn(&str, &mut Window, &mut App) + 'static,
+    cx: &mut Context<Self>,
+  ) -> Self {
+    let input = cx.new(|cx| {
+      let mut input = TextInput::new(placeholder, cx);
+      input.single_line = true;
+      input
+    });
+    Self {
+      title: title.into(),
+      input,
+      on_confirm: Box::new(on_confirm),
+    }
+  }
+
+  fn confirm(&mut self, _: &Newline, window: &mut Window, cx: &mut Context<Self>) {
+    let text = self.input.read(cx).content.to_string();
+    (self.on_confirm)(text.trim(), window, cx);
+    cx.emit(DismissEvent);
+  }
+
+  fn cancel(&mut self, _: &Cancel, _: &mut Window, cx: &mut Context<Self>) {
+    cx.emit(DismissEvent);
+  }
+}
+
+impl Focusable for Prompt {
+  fn focus_handle(&self, cx: &App) -> FocusHandle {
+    self.input.read(cx).focus_handle.clone()
+  }
+}
+
+impl Render for Prompt {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    div()
+      .key_context("Prompt")
+      .on_action(cx.listener(Self::confirm))
+      .on_action(cx.listener(Self::cancel))
+      .w(px(400.))
+      .p(px(8.))
+      .flex()
+      .flex_col()
+      .gap(px(4.))
+      .bg(rgb(0xdddddd))
+      .border_1()
+      .border_color(rgb(0x999999))
+      .child(div().text_sm().child(self.title.clone()))
+      .child(div().h(px(30. + 4. * 2.)).child(self.input.clone()))
+  }
+}
@@ -0,0 +1,155 @@
+use std::{
+  fs::File,
+  io::{self, Write},
+  path::Path,
+  sync::mpsc,
+  thread,
+};
+
+/// A shell running on a pseudo-terminal. The shell is killed when this is
+/// dropped.
+pub struct Pty {
+  master: File,
+  /// Input waiting for the writer thread.
+  writer: mpsc::Sender<Vec<u8>>,
+  #[cfg(unix)]
+  child: std::process::Child,
+}
+
+impl Pty {
+  /// Starts the user's shell in `cwd` on a terminal of the given size.
+  #[cfg(unix)]
+  pub fn spawn(cwd: &Path, rows: u16, cols: u16) -> io::Result<Self> {
+    use std::{
+      env,
+      os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::process::CommandExt,
+      },
+      process::{Command, Stdio},
+      ptr,
+    };
+
+    let (mut master, mut slave) = (-1, -1);
+    let size = winsize(rows, cols);
+    // SAFETY: the out pointers are valid and the names are unused.
+    if unsafe {
+      libc::openpty(
+        &mut master,
+        &mut slave,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        &size,
+      )
+    } != 0
+    {
+      return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `openpty` returned two fresh descriptors that nothing else owns.
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    // SAFETY: `master` is a valid descriptor.
+    unsafe { libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    let shell = env::var_os("SHELL").unwrap_or_else(|| "/bin/sh".into());
+    let mut command = Command::new(shell);
+    command
+      .current_dir(cwd)
+      .env("TERM", "xterm-256color")
+      .env("COLORTERM", "truecolor")
+      .stdin(Stdio::from(slave.try_clone()?))
+      .stdout(Stdio::from(slave.try_clone()?))
+      .stderr(Stdio::from(slave));
+    // SAFETY: only async-signal-safe calls are made between fork and exec.
+    unsafe {
+      command.pre_exec(|| {
+        // The shell leads a new session with the terminal as its controlling
+        // terminal, so job control and ctrl-c work.
+        if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+          return Err(io::Error::last_os_error());
+        }
+        Ok(())
+      });
+    }
+    let child = command.spawn()?;
+    let master = File::from(master);
+    Ok(Self {
+      writer: spawn_writer(master.try_clone()?)?,
+      master,
+      child,
+    })
+  }
+
+  #[cfg(not(unix))]
+  pub fn spawn(_cwd: &Path, _rows: u16, _cols: u16) -> io::Result<Self> {
+    Err(io::Error::new(
+      io::ErrorKind::Unsupported,
+      "Terminals aren't supported on this platform",
+    ))
+  }
+
+  /// A handle reading the shell's output, which blocks until there is some.
+  pub fn reader(&self) -> io::Result<File> {
+    self.master.try_clone()
+  }
+
+  /// Queues input for the shell. It's written on another thread, since a
+  /// shell that isn't reading would otherwise block the caller.
+  pub fn write(&self, bytes: &[u8]) -> io::Result<()> {
+    self
+      .writer
+      .send(bytes.to_vec())
+      .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+  }
+
+  /// Tells the shell the terminal's new size.
+  #[cfg(unix)]
+  pub fn resize(&self, rows: u16, cols: u16) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let size = winsize(rows, cols);
+    // SAFETY: `size` outlives the call and the descriptor is valid.
+    if unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size) } == -1 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(())
+  }
+
+  #[cfg(not(unix))]
+  pub fn resize(&self, _rows: u16, _cols: u16) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Starts the thread writing queued input to the terminal, which stops once
+/// the `Pty` is dropped or the terminal is closed.
+fn spawn_writer(mut master: File) -> io::Result<mpsc::Sender<Vec<u8>>> {
+  let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+  thread::Builder::new()
+    .name("pty writer".into())
+    .spawn(move || {
+      for bytes in receiver {
+        if master.write_all(&bytes).is_err() {
+          break;
+        }
+      }
+    })?;
+  Ok(sender)
+}
+
+#[cfg(unix)]
+fn winsize(rows: u16, cols: u16) -> libc::winsize {
+  libc::winsize {
+    ws_row: rows,
+    ws_col: cols,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+  }
+}
+
+#[cfg(unix)]
+impl Drop for Pty {
+  fn drop(&mut self) {
+    self.child.kill().ok();
+    self.child.wait().ok();
+  }
+}
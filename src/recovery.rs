@@ -0,0 +1,277 @@
+use std::{
+  collections::HashMap,
+  fs,
+  io::{self, Write},
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use ropey::Rope;
+
+use crate::{encoding::Encoding, line_ending::LineEnding, settings, text_input::TextInput};
+
+/// How often unsaved buffers are snapshotted.
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+
+const EXTENSION: &str = "snapshot";
+
+/// The unsaved content of a buffer, as written to the recovery directory.
+pub struct Snapshot {
+  pub path: Option<PathBuf>,
+  pub encoding: Encoding,
+  pub line_ending: LineEnding,
+  pub cursor: usize,
+  pub text: String,
+}
+
+/// Snapshot files to write and remove, prepared on the main thread and
+/// written by `write`.
+#[derive(Default)]
+pub struct Writes {
+  files: Vec<(PathBuf, String, Rope)>,
+  removals: Vec<PathBuf>,
+}
+
+/// Keeps a snapshot of every buffer with unsaved changes in
+/// `~/.config/zeta/recovery`, so they survive quitting and crashes. Each
+/// snapshot is named after the process and buffer it belongs to, so
+/// instances running side by side leave each other's alone.
+pub struct Recovery {
+  dir: Option<PathBuf>,
+  /// The version last snapshotted for each buffer id.
+  versions: HashMap<usize, usize>,
+  /// Snapshots from earlier sessions that were loaded into this one. They
+  /// are removed by the next update, after its own snapshots are written.
+  adopted: Vec<PathBuf>,
+}
+
+impl Recovery {
+  pub fn new() -> Self {
+    Self {
+      dir: settings::config_dir().map(|dir| dir.join("recovery")),
+      versions: HashMap::new(),
+      adopted: Vec::new(),
+    }
+  }
+
+  /// Reads the snapshots left by sessions that are no longer running, which
+  /// this one takes over. Unreadable snapshots are skipped and left in place.
+  pub fn load(&mut self) -> Vec<Snapshot> {
+    let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+      return Vec::new();
+    };
+    let mut paths = entries
+      .flatten()
+      .map(|entry| entry.path())
+      .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+      .map(|path| (snapshot_owner(&path), path))
+      .filter(|&(owner, _)| owner.is_none_or(|(pid, _)| !is_running(pid)))
+      .collect::<Vec<_>>();
+    paths.sort();
+    paths
+      .into_iter()
+      .filter_map(|(_, path)| {
+        let snapshot = parse(&fs::read_to_string(&path).ok()?)?;
+        self.adopted.push(path);
+        Some(snapshot)
+      })
+      .collect()
+  }
+
+  /// Prepares a snapshot of every dirty buffer changed since the last one,
+  /// and the removal of snapshots for buffers that are now clean or closed.
+  pub fn update<'a>(&mut self, buffers: impl IntoIterator<Item = &'a TextInput>) -> Writes {
+    let Some(dir) = self.dir.clone() else {
+      return Writes::default();
+    };
+    let mut writes = Writes::default();
+    let mut versions = HashMap::new();
+    for buffer in buffers {
+      if !buffer.is_dirty() {
+        continue;
+      }
+      versions.insert(buffer.buffer_id, buffer.version);
+      if self.versions.get(&buffer.buffer_id) != Some(&buffer.version) {
+        writes.files.push((
+          snapshot_path(&dir, buffer.buffer_id),
+          header(
+            buffer.path.as_deref(),
+            buffer.encoding,
+            buffer.line_ending,
+            buffer.cursor_offset(),
+          ),
+          buffer.content.clone(),
+        ));
+      }
+    }
+    writes.removals = self
+      .versions
+      .keys()
+      .filter(|buffer_id| !versions.contains_key(buffer_id))
+      .map(|&buffer_id| snapshot_path(&dir, buffer_id))
+      .chain(self.adopted.drain(..))
+      .collect();
+    self.versions = versions;
+    writes
+  }
+}
+
+impl Writes {
+  /// Writes the snapshots, each to a temporary file first so a crash never
+  /// leaves a partial one.
+  pub fn write(self) -> io::Result<()> {
+    for (path, header, text) in self.files {
+      if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+      }
+      let temp = path.with_extension("tmp");
+      let mut file = io::BufWriter::new(fs::File::create(&temp)?);
+      file.write_all(header.as_bytes())?;
+      text.write_to(&mut file)?;
+      file.into_inner()?.sync_all()?;
+      fs::rename(&temp, &path)?;
+    }
+    for path in self.removals {
+      match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+      }
+    }
+    Ok(())
+  }
+}
+
+fn snapshot_path(dir: &Path, buffer_id: usize) -> PathBuf {
+  dir.join(format!("{}-{buffer_id}.{EXTENSION}", std::process::id()))
+}
+
+/// The process and buffer ids a snapshot's file is named after.
+fn snapshot_owner(path: &Path) -> Option<(u32, usize)> {
+  let (pid, buffer_id) = path.file_stem()?.to_str()?.split_once('-')?;
+  Some((pid.parse().ok()?, buffer_id.parse().ok()?))
+}
+
+/// Whether the process `pid` is alive. Signal 0 only checks that it exists;
+/// `EPERM` means it does but belongs to another user.
+fn is_running(pid: u32) -> bool {
+  let Ok(pid) = libc::pid_t::try_from(pid) else {
+    return false;
+  };
+  let signalled = unsafe { libc::kill(pid, 0) } == 0;
+  signalled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// `key = value` lines describing a buffer, ended by an empty line.
+fn header(
+  path: Option<&Path>,
+  encoding: Encoding,
+  line_ending: LineEnding,
+  cursor: usize,
+) -> String {
+  let path = path
+    .map(|path| path.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  format!(
+    "path = {path}\nencoding = {}\nline_ending = {}\ncursor = {cursor}\n\n",
+    encoding.label(),
+    line_ending.label(),
+  )
+}
+
+fn parse(contents: &str) -> Option<Snapshot> {
+  let (header, text) = contents.split_once("\n\n")?;
+  let mut snapshot = Snapshot {
+    path: None,
+    encoding: Encoding::default(),
+    line_ending: LineEnding::default(),
+    cursor: 0,
+    text: text.to_string(),
+  };
+  for line in header.lines() {
+    let Some((key, value)) = line.split_once(" = ") else {
+      continue;
+    };
+    match key {
+      "path" if !value.is_empty() => snapshot.path = Some(PathBuf::from(value)),
+      "encoding" => {
+        if let Some(encoding) = Encoding::ALL.into_iter().find(|e| e.label() == value) {
+          snapshot.encoding = encoding;
+        }
+      }
+      "line_ending" => {
+        if let Some(line_ending) = LineEnding::ALL.into_iter().find(|l| l.label() == value) {
+          snapshot.line_ending = line_ending;
+        }
+      }
+      "cursor" => snapshot.cursor = value.parse().unwrap_or(0),
+      _ => {}
+    }
+  }
+  Some(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let path = Path::new("/work/My Project/notes.txt");
+    let text = "first\n\n\nafter blank lines\r\n\n";
+    let contents = header(Some(path), Encoding::Utf16Le, LineEnding::Crlf, 17) + text;
+
+    let snapshot = parse(&contents).unwrap();
+    assert_eq!(snapshot.path.as_deref(), Some(path));
+    assert_eq!(snapshot.encoding, Encoding::Utf16Le);
+    assert_eq!(snapshot.line_ending, LineEnding::Crlf);
+    assert_eq!(snapshot.cursor, 17);
+    // The header ends at the first blank line, so the text keeps its own.
+    assert_eq!(snapshot.text, text);
+  }
+
+  #[test]
+  fn untitled_and_empty_buffers() {
+    let contents = header(None, Encoding::Utf8, LineEnding::Lf, 0);
+    let snapshot = parse(&contents).unwrap();
+    assert_eq!(snapshot.path, None);
+    assert_eq!(snapshot.text, "");
+  }
+
+  #[test]
+  fn unknown_values_fall_back_to_defaults() {
+    let snapshot = parse(
+      "path = /a.txt\n\
+       encoding = EBCDIC\n\
+       line_ending = CR\n\
+       cursor = -1\n\
+       future = key\n\
+       \n\
+       text",
+    )
+    .unwrap();
+    assert_eq!(snapshot.path.as_deref(), Some(Path::new("/a.txt")));
+    assert_eq!(snapshot.encoding, Encoding::default());
+    assert_eq!(snapshot.line_ending, LineEnding::default());
+    assert_eq!(snapshot.cursor, 0);
+    assert_eq!(snapshot.text, "text");
+  }
+
+  #[test]
+  fn snapshots_without_a_header_end_are_unreadable() {
+    assert!(parse("path = /a.txt\nencoding = UTF-8\n").is_none());
+    assert!(parse("").is_none());
+  }
+
+  #[test]
+  fn snapshot_file_names() {
+    let path = snapshot_path(Path::new("/recovery"), 7);
+    assert_eq!(snapshot_owner(&path), Some((std::process::id(), 7)));
+    assert_eq!(
+      snapshot_owner(Path::new("/r/123-45.snapshot")),
+      Some((123, 45))
+    );
+    assert_eq!(snapshot_owner(Path::new("/r/123.snapshot")), None);
+    assert_eq!(snapshot_owner(Path::new("/r/pid-45.snapshot")), None);
+    assert_eq!(snapshot_owner(Path::new("/r/123-4-5.snapshot")), None);
+  }
+}
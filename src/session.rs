@@ -0,0 +1,253 @@
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use gpui::{Bounds, WindowBounds, point, px, size};
+
+use crate::settings;
+
+/// An open file, reopened with its cursor and scroll position.
+pub struct TabState {
+  pub path: PathBuf,
+  pub cursor: usize,
+  /// The vertical scroll offset, which is zero or negative.
+  pub scroll_y: f32,
+}
+
+pub struct PaneState {
+  pub tabs: Vec<TabState>,
+  pub active: usize,
+}
+
+/// The window, split panes and open files of a workspace directory, kept in
+/// `~/.config/zeta/sessions` and restored when the directory is reopened.
+pub struct Session {
+  pub window: Option<WindowBounds>,
+  pub project_panel_open: bool,
+  pub panes: Vec<PaneState>,
+  pub active_pane: usize,
+}
+
+impl Default for Session {
+  fn default() -> Self {
+    Self {
+      window: None,
+      project_panel_open: true,
+      panes: Vec::new(),
+      active_pane: 0,
+    }
+  }
+}
+
+impl Session {
+  /// Reads the session of `workspace`, which is empty if there is none.
+  pub fn load(workspace: &Path) -> Self {
+    session_path(workspace)
+      .and_then(|path| fs::read_to_string(path).ok())
+      .map(|contents| parse(&contents))
+      .unwrap_or_default()
+  }
+
+  /// Writes the session of `workspace` to a temporary file first, so a crash
+  /// never leaves a partial one.
+  pub fn save(&self, workspace: &Path) -> io::Result<()> {
+    let Some(path) = session_path(workspace) else {
+      return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, self.serialize())?;
+    fs::rename(&temp, &path)
+  }
+
+  /// `key = value` lines, where each `pane` line is followed by its `tab`s.
+  fn serialize(&self) -> String {
+    let mut contents = String::new();
+    if let Some(window) = self.window {
+      let (state, bounds) = match window {
+        WindowBounds::Windowed(bounds) => ("windowed", bounds),
+        WindowBounds::Maximized(bounds) => ("maximized", bounds),
+        WindowBounds::Fullscreen(bounds) => ("fullscreen", bounds),
+      };
+      contents.push_str(&format!(
+        "window = {state} {} {} {} {}\n",
+        f32::from(bounds.origin.x),
+        f32::from(bounds.origin.y),
+        f32::from(bounds.size.width),
+        f32::from(bounds.size.height),
+      ));
+    }
+    contents.push_str(&format!(
+      "project_panel = {}\n",
+      if self.project_panel_open {
+        "open"
+      } else {
+        "closed"
+      }
+    ));
+    contents.push_str(&format!("active_pane = {}\n", self.active_pane));
+    for pane in &self.panes {
+      contents.push_str(&format!("pane = {}\n", pane.active));
+      for tab in &pane.tabs {
+        contents.push_str(&format!(
+          "tab = {} {} {}\n",
+          tab.cursor,
+          tab.scroll_y,
+          tab.path.display()
+        ));
+      }
+    }
+    contents
+  }
+}
+
+/// The session file is named after the workspace path, with separators
+/// escaped.
+fn session_path(workspace: &Path) -> Option<PathBuf> {
+  let name = workspace
+    .to_string_lossy()
+    .replace('%', "%25")
+    .replace('/', "%2F")
+    .replace('\\', "%5C")
+    .replace(':', "%3A");
+  settings::config_dir().map(|dir| dir.join("sessions").join(name))
+}
+
+fn parse(contents: &str) -> Session {
+  let mut session = Session::default();
+  for line in contents.lines() {
+    let Some((key, value)) = line.split_once(" = ") else {
+      continue;
+    };
+    match key {
+      "window" => session.window = parse_window(value),
+      "project_panel" => session.project_panel_open = value != "closed",
+      "active_pane" => session.active_pane = value.parse().unwrap_or(0),
+      "pane" => session.panes.push(PaneState {
+        tabs: Vec::new(),
+        active: value.parse().unwrap_or(0),
+      }),
+      "tab" => {
+        let mut fields = value.splitn(3, ' ');
+        let (Some(cursor), Some(scroll_y), Some(path), Some(pane)) = (
+          fields.next(),
+          fields.next(),
+          fields.next(),
+          session.panes.last_mut(),
+        ) else {
+          continue;
+        };
+        pane.tabs.push(TabState {
+          path: PathBuf::from(path),
+          cursor: cursor.parse().unwrap_or(0),
+          scroll_y: scroll_y.parse().unwrap_or(0.),
+        });
+      }
+      _ => {}
+    }
+  }
+  session.panes.retain(|pane| !pane.tabs.is_empty());
+  session
+}
+
+fn parse_window(value: &str) -> Option<WindowBounds> {
+  let mut fields = value.split(' ');
+  let state = fields.next()?;
+  let mut number = || fields.next()?.parse::<f32>().ok().map(px);
+  let bounds = Bounds::new(point(number()?, number()?), size(number()?, number()?));
+  match state {
+    "windowed" => Some(WindowBounds::Windowed(bounds)),
+    "maximized" => Some(WindowBounds::Maximized(bounds)),
+    "fullscreen" => Some(WindowBounds::Fullscreen(bounds)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tab(path: &str, cursor: usize, scroll_y: f32) -> TabState {
+    TabState {
+      path: PathBuf::from(path),
+      cursor,
+      scroll_y,
+    }
+  }
+
+  #[test]
+  fn round_trip() {
+    let session = Session {
+      window: Some(WindowBounds::Maximized(Bounds::new(
+        point(px(10.), px(-20.5)),
+        size(px(1280.), px(800.)),
+      ))),
+      project_panel_open: false,
+      panes: vec![
+        PaneState {
+          tabs: vec![
+            tab("/work/src/main.rs", 42, -120.5),
+            tab("/work/My Project/notes and ideas.md", 0, 0.),
+          ],
+          active: 1,
+        },
+        PaneState {
+          tabs: vec![tab("/work/ trailing space ", 7, -3.)],
+          active: 0,
+        },
+      ],
+      active_pane: 1,
+    };
+    let contents = session.serialize();
+    let parsed = parse(&contents);
+
+    assert_eq!(parsed.window, session.window);
+    assert!(!parsed.project_panel_open);
+    assert_eq!(parsed.active_pane, 1);
+    assert_eq!(parsed.panes.len(), 2);
+    assert_eq!(parsed.panes[0].active, 1);
+    let tabs = &parsed.panes[0].tabs;
+    assert_eq!(tabs[0].path, Path::new("/work/src/main.rs"));
+    assert_eq!((tabs[0].cursor, tabs[0].scroll_y), (42, -120.5));
+    assert_eq!(
+      tabs[1].path,
+      Path::new("/work/My Project/notes and ideas.md")
+    );
+    assert_eq!(
+      parsed.panes[1].tabs[0].path,
+      Path::new("/work/ trailing space ")
+    );
+    assert_eq!(parsed.serialize(), contents);
+  }
+
+  #[test]
+  fn empty_session_round_trip() {
+    let parsed = parse(&Session::default().serialize());
+    assert!(parsed.window.is_none());
+    assert!(parsed.project_panel_open);
+    assert!(parsed.panes.is_empty());
+  }
+
+  #[test]
+  fn malformed_lines_are_skipped() {
+    let parsed = parse(
+      "window = tiled 1 2 3 4\n\
+       tab = 1 0 /orphan.rs\n\
+       pane = 0\n\
+       pane = x\n\
+       tab = 5\n\
+       tab = 3 nope /a b.rs\n\
+       garbage\n",
+    );
+    assert!(parsed.window.is_none());
+    // The orphan tab had no pane and the first pane no tabs.
+    assert_eq!(parsed.panes.len(), 1);
+    let tabs = &parsed.panes[0].tabs;
+    assert_eq!(tabs.len(), 1);
+    assert_eq!(tabs[0].path, Path::new("/a b.rs"));
+    assert_eq!((tabs[0].cursor, tabs[0].scroll_y), (3, 0.));
+  }
+}
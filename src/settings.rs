@@ -0,0 +1,249 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use gpui::Global;
+
+use crate::language::{self, Language};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeymapPreset {
+  #[default]
+  Default,
+  Emacs,
+}
+
+impl KeymapPreset {
+  fn parse(value: &str) -> Option<Self> {
+    match value {
+      "default" => Some(Self::Default),
+      "emacs" => Some(Self::Emacs),
+      _ => None,
+    }
+  }
+}
+
+/// Which spaces and tabs are drawn as visible markers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderWhitespace {
+  #[default]
+  None,
+  All,
+  /// Only whitespace inside the selection.
+  Selection,
+  /// All but single spaces between words.
+  Boundary,
+}
+
+impl RenderWhitespace {
+  fn parse(value: &str) -> Option<Self> {
+    match value {
+      "none" => Some(Self::None),
+      "all" => Some(Self::All),
+      "selection" => Some(Self::Selection),
+      "boundary" => Some(Self::Boundary),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorShape {
+  #[default]
+  Bar,
+  Block,
+  Underline,
+}
+
+impl CursorShape {
+  fn parse(value: &str) -> Option<Self> {
+    match value {
+      "bar" => Some(Self::Bar),
+      "block" => Some(Self::Block),
+      "underline" => Some(Self::Underline),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+  pub keymap: KeymapPreset,
+  pub tab_size: usize,
+  pub hard_tabs: bool,
+  pub language: &'static Language,
+  pub auto_close: bool,
+  /// Per-language `auto_close` overrides, keyed by language name.
+  pub language_auto_close: HashMap<&'static str, bool>,
+  /// Files larger than this many bytes open in large file mode.
+  pub large_file_threshold: u64,
+  pub indent_guides: bool,
+  pub render_whitespace: RenderWhitespace,
+  /// The shape of the cursor while focused. Unfocused editors show a
+  /// hollow block.
+  pub cursor_shape: CursorShape,
+  pub cursor_blink: bool,
+  pub current_line_highlight: bool,
+  /// Animates the cursor between positions instead of jumping.
+  pub smooth_cursor: bool,
+  /// Saved macros bound to keystrokes by `macro.<name> = <keystrokes>`.
+  pub macro_bindings: Vec<(String, String)>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      keymap: KeymapPreset::default(),
+      tab_size: 4,
+      hard_tabs: false,
+      language: &language::PLAIN_TEXT,
+      auto_close: true,
+      language_auto_close: HashMap::new(),
+      large_file_threshold: 16 * 1024 * 1024,
+      indent_guides: true,
+      render_whitespace: RenderWhitespace::default(),
+      cursor_shape: CursorShape::default(),
+      cursor_blink: true,
+      current_line_highlight: true,
+      smooth_cursor: false,
+      macro_bindings: Vec::new(),
+    }
+  }
+}
+
+impl Global for Settings {}
+
+impl Settings {
+  /// Reads `key = value` pairs from `~/.config/zeta/settings`, falling back to
+  /// defaults for a missing file or unknown values.
+  pub fn load() -> Self {
+    let mut settings = Self::default();
+    let Some(contents) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+      return settings;
+    };
+
+    for line in contents.lines() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let Some((key, value)) = line.split_once('=') else {
+        continue;
+      };
+      settings.apply(key.trim(), value.trim().trim_matches('"'));
+    }
+
+    settings
+  }
+
+  /// The text inserted for one level of indentation.
+  pub fn indent_unit(&self) -> String {
+    if self.hard_tabs {
+      "\t".into()
+    } else {
+      " ".repeat(self.tab_size)
+    }
+  }
+
+  pub fn auto_close(&self, language: &Language) -> bool {
+    self
+      .language_auto_close
+      .get(language.name)
+      .copied()
+      .unwrap_or(self.auto_close)
+  }
+
+  /// Applies one setting. Keys may be scoped to a language as
+  /// `<language>.<key>`, e.g. `rust.auto_close = false`, and keystrokes are
+  /// bound to saved macros as `macro.<name> = <keystrokes>`.
+  fn apply(&mut self, key: &str, value: &str) {
+    if let Some(name) = key.strip_prefix("macro.") {
+      self
+        .macro_bindings
+        .push((name.to_string(), value.to_string()));
+      return;
+    }
+    if let Some((language, key)) = key.split_once('.') {
+      if let Some(language) = Language::by_name(language)
+        && key == "auto_close"
+        && let Ok(auto_close) = value.parse()
+      {
+        self.language_auto_close.insert(language.name, auto_close);
+      }
+      return;
+    }
+
+    match key {
+      "keymap" => {
+        if let Some(keymap) = KeymapPreset::parse(value) {
+          self.keymap = keymap;
+        }
+      }
+      "tab_size" => {
+        if let Ok(tab_size) = value.parse::<usize>()
+          && tab_size > 0
+        {
+          self.tab_size = tab_size;
+        }
+      }
+      "hard_tabs" => {
+        if let Ok(hard_tabs) = value.parse() {
+          self.hard_tabs = hard_tabs;
+        }
+      }
+      "auto_close" => {
+        if let Ok(auto_close) = value.parse() {
+          self.auto_close = auto_close;
+        }
+      }
+      "language" => {
+        if let Some(language) = Language::by_name(value) {
+          self.language = language;
+        }
+      }
+      "large_file_threshold" => {
+        if let Ok(threshold) = value.parse() {
+          self.large_file_threshold = threshold;
+        }
+      }
+      "indent_guides" => {
+        if let Ok(indent_guides) = value.parse() {
+          self.indent_guides = indent_guides;
+        }
+      }
+      "render_whitespace" => {
+        if let Some(render_whitespace) = RenderWhitespace::parse(value) {
+          self.render_whitespace = render_whitespace;
+        }
+      }
+      "cursor_shape" => {
+        if let Some(cursor_shape) = CursorShape::parse(value) {
+          self.cursor_shape = cursor_shape;
+        }
+      }
+      "cursor_blink" => {
+        if let Ok(cursor_blink) = value.parse() {
+          self.cursor_blink = cursor_blink;
+        }
+      }
+      "current_line_highlight" => {
+        if let Ok(current_line_highlight) = value.parse() {
+          self.current_line_highlight = current_line_highlight;
+        }
+      }
+      "smooth_cursor" => {
+        if let Ok(smooth_cursor) = value.parse() {
+          self.smooth_cursor = smooth_cursor;
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("settings"))
+  }
+}
+
+/// `~/.config/zeta`, where settings and editor state are kept.
+pub fn config_dir() -> Option<PathBuf> {
+  std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/zeta"))
+}
@@ -0,0 +1,652 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs,
+  iter::Peekable,
+  ops::Range,
+  path::PathBuf,
+  str::Chars,
+};
+
+use gpui::{Global, actions};
+
+use crate::{language::Language, settings};
+
+actions!(snippets, [InsertSnippet]);
+
+#[derive(Clone, Debug)]
+pub struct SnippetDefinition {
+  /// The word that expands to the snippet when followed by Tab.
+  pub prefix: String,
+  pub description: String,
+  /// The TextMate-style template.
+  pub body: String,
+}
+
+/// The user's snippets, from one file per language in
+/// `~/.config/zeta/snippets`, plus `all` for those offered everywhere.
+#[derive(Default)]
+pub struct Snippets {
+  /// Keyed by lowercased language name, or `all`.
+  user: HashMap<String, Vec<SnippetDefinition>>,
+}
+
+impl Global for Snippets {}
+
+impl Snippets {
+  /// Reads the snippet files, each holding definitions like
+  ///
+  /// ```text
+  /// snippet fn A function
+  /// fn ${1:name}(${2}) {
+  ///   $0
+  /// }
+  /// endsnippet
+  /// ```
+  pub fn load() -> Self {
+    let mut snippets = Self::default();
+    let Some(entries) = snippets_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+      return snippets;
+    };
+    for entry in entries.flatten() {
+      let (Ok(name), Ok(contents)) = (
+        entry.file_name().into_string(),
+        fs::read_to_string(entry.path()),
+      ) else {
+        continue;
+      };
+      snippets
+        .user
+        .insert(name.to_lowercase(), parse_definitions(&contents));
+    }
+    snippets
+  }
+
+  /// The snippets offered in `language`: the user's, then the language's
+  /// own.
+  pub fn for_language(&self, language: &Language) -> Vec<SnippetDefinition> {
+    let user = [language.name.to_lowercase(), "all".into()]
+      .into_iter()
+      .filter_map(|key| self.user.get(&key))
+      .flatten()
+      .cloned();
+    let builtin = language
+      .snippets
+      .iter()
+      .map(|&(prefix, body)| SnippetDefinition {
+        prefix: prefix.into(),
+        description: body.lines().next().unwrap_or_default().into(),
+        body: body.into(),
+      });
+    user.chain(builtin).collect()
+  }
+}
+
+fn snippets_dir() -> Option<PathBuf> {
+  settings::config_dir().map(|dir| dir.join("snippets"))
+}
+
+fn parse_definitions(contents: &str) -> Vec<SnippetDefinition> {
+  let mut definitions = Vec::new();
+  let mut lines = contents.lines();
+  while let Some(line) = lines.next() {
+    let Some(header) = line.strip_prefix("snippet ") else {
+      continue;
+    };
+    let (prefix, description) = header.trim().split_once(' ').unwrap_or((header.trim(), ""));
+    let body = lines
+      .by_ref()
+      .take_while(|line| line.trim_end() != "endsnippet")
+      .collect::<Vec<_>>()
+      .join("\n");
+    definitions.push(SnippetDefinition {
+      prefix: prefix.into(),
+      description: description.trim().into(),
+      body,
+    });
+  }
+  definitions
+}
+
+/// A place Tab stops at in an inserted snippet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TabStop {
+  /// The placeholder first, then its mirrors, which follow its text.
+  pub ranges: Vec<Range<usize>>,
+  /// The values offered for the placeholder, if it is a choice.
+  pub choices: Vec<String>,
+}
+
+/// An inserted snippet whose tab stops Tab and Shift-Tab move between.
+pub struct SnippetSession {
+  pub stops: Vec<TabStop>,
+  /// The index of the selected stop, never the last since reaching it ends
+  /// the session.
+  pub active: usize,
+  /// The index of the choice shown at the active stop.
+  pub choice: usize,
+}
+
+impl SnippetSession {
+  /// Whether `selection` lies within one of the tab stops, placeholders
+  /// and mirrors alike. Moving anywhere else ends the session.
+  pub fn contains(&self, selection: &Range<usize>) -> bool {
+    self
+      .stops
+      .iter()
+      .flat_map(|stop| &stop.ranges)
+      .any(|range| range.start <= selection.start && selection.end <= range.end)
+  }
+}
+
+/// A snippet's text, with its tab stops in the order Tab visits them. The
+/// last stop is `$0`, or the end of the text when there is none.
+#[derive(Debug, PartialEq)]
+pub struct ExpandedSnippet {
+  pub text: String,
+  pub stops: Vec<TabStop>,
+}
+
+/// How a snippet's text is fitted to where it's inserted.
+pub struct ExpandOptions<'a> {
+  pub line_ending: &'a str,
+  /// The indentation of the line the snippet is inserted on, added after
+  /// each line break.
+  pub indent: &'a str,
+  /// What each tab in the template becomes.
+  pub indent_unit: &'a str,
+}
+
+/// Expands the TextMate-style template `body`: `$1`, `${1:placeholder}`,
+/// `${1|one,two|}`, `$0`, `$NAME` and `${NAME:default}`. A tab stop used
+/// more than once mirrors the first placeholder given for it. Variables
+/// are looked up with `variable`; unknown ones insert their name.
+pub fn expand(
+  body: &str,
+  options: &ExpandOptions,
+  variable: impl Fn(&str) -> Option<String>,
+) -> ExpandedSnippet {
+  let nodes = Parser {
+    chars: body.chars().peekable(),
+  }
+  .parse(false);
+  let mut placeholders = HashMap::new();
+  collect_placeholders(&nodes, &mut placeholders);
+  let mut renderer = Renderer {
+    options,
+    variable: &variable,
+    placeholders,
+    text: String::new(),
+    stops: BTreeMap::new(),
+    rendering: Vec::new(),
+  };
+  renderer.render(&nodes);
+
+  let mut stops = renderer.stops;
+  let last = stops.remove(&0).unwrap_or_else(|| {
+    let end = renderer.text.len();
+    TabStop {
+      ranges: std::iter::once(end..end).collect(),
+      choices: Vec::new(),
+    }
+  });
+  ExpandedSnippet {
+    text: renderer.text,
+    stops: stops.into_values().chain([last]).collect(),
+  }
+}
+
+enum Node {
+  Text(String),
+  TabStop {
+    index: usize,
+    placeholder: Vec<Node>,
+    choices: Vec<String>,
+  },
+  Variable {
+    name: String,
+    default: Vec<Node>,
+  },
+}
+
+struct Parser<'a> {
+  chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+  /// Parses up to the end of the template, or past the `}` closing the
+  /// enclosing placeholder when `nested`.
+  fn parse(&mut self, nested: bool) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+    while let Some(ch) = self.chars.next() {
+      match ch {
+        '\\' => match self.chars.peek() {
+          Some(&escaped @ ('$' | '}' | '\\')) => {
+            self.chars.next();
+            text.push(escaped);
+          }
+          _ => text.push('\\'),
+        },
+        '}' if nested => break,
+        '$' => {
+          let node = self.parse_dollar();
+          match node {
+            Some(node) => {
+              if !text.is_empty() {
+                nodes.push(Node::Text(std::mem::take(&mut text)));
+              }
+              nodes.push(node);
+            }
+            None => text.push('$'),
+          }
+        }
+        ch => text.push(ch),
+      }
+    }
+    if !text.is_empty() {
+      nodes.push(Node::Text(text));
+    }
+    nodes
+  }
+
+  /// Parses what follows a `$`, or nothing when it's a plain dollar sign.
+  fn parse_dollar(&mut self) -> Option<Node> {
+    match *self.chars.peek()? {
+      ch if ch.is_ascii_digit() => Some(Node::TabStop {
+        index: self.parse_index(),
+        placeholder: Vec::new(),
+        choices: Vec::new(),
+      }),
+      ch if is_name_start(ch) => Some(Node::Variable {
+        name: self.parse_name(),
+        default: Vec::new(),
+      }),
+      '{' => {
+        self.chars.next();
+        Some(self.parse_braced())
+      }
+      _ => None,
+    }
+  }
+
+  /// Parses the inside of `${...}`, having read the brace.
+  fn parse_braced(&mut self) -> Node {
+    if self.chars.peek().is_some_and(char::is_ascii_digit) {
+      let index = self.parse_index();
+      let (placeholder, choices) = match self.chars.next() {
+        Some(':') => (self.parse(true), Vec::new()),
+        Some('|') => (Vec::new(), self.parse_choices()),
+        Some('}') | None => (Vec::new(), Vec::new()),
+        Some(_) => {
+          self.skip_braced();
+          (Vec::new(), Vec::new())
+        }
+      };
+      return Node::TabStop {
+        index,
+        placeholder,
+        choices,
+      };
+    }
+    let name = self.parse_name();
+    let default = match self.chars.next() {
+      Some(':') => self.parse(true),
+      Some('}') | None => Vec::new(),
+      // Transforms aren't supported, so the variable is inserted as is.
+      Some(_) => {
+        self.skip_braced();
+        Vec::new()
+      }
+    };
+    Node::Variable { name, default }
+  }
+
+  /// Parses `one,two|}`, having read the opening `|`.
+  fn parse_choices(&mut self) -> Vec<String> {
+    let mut choices = vec![String::new()];
+    while let Some(ch) = self.chars.next() {
+      match ch {
+        '\\' => {
+          if let Some(escaped) = self.chars.next() {
+            choices.last_mut().unwrap().push(escaped);
+          }
+        }
+        ',' => choices.push(String::new()),
+        '|' if self.chars.peek() == Some(&'}') => {
+          self.chars.next();
+          break;
+        }
+        ch => choices.last_mut().unwrap().push(ch),
+      }
+    }
+    choices
+  }
+
+  /// Skips to past the `}` closing the current `${`.
+  fn skip_braced(&mut self) {
+    let mut depth = 1;
+    while let Some(ch) = self.chars.next() {
+      match ch {
+        '\\' => {
+          self.chars.next();
+        }
+        '{' => depth += 1,
+        '}' => {
+          depth -= 1;
+          if depth == 0 {
+            return;
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn parse_index(&mut self) -> usize {
+    let mut index = String::new();
+    while let Some(&ch) = self.chars.peek().filter(|ch| ch.is_ascii_digit()) {
+      index.push(ch);
+      self.chars.next();
+    }
+    index.parse().unwrap_or(usize::MAX)
+  }
+
+  fn parse_name(&mut self) -> String {
+    let mut name = String::new();
+    while let Some(&ch) = self
+      .chars
+      .peek()
+      .filter(|&&ch| ch.is_ascii_alphanumeric() || ch == '_')
+    {
+      name.push(ch);
+      self.chars.next();
+    }
+    name
+  }
+}
+
+fn is_name_start(ch: char) -> bool {
+  ch.is_ascii_alphabetic() || ch == '_'
+}
+
+/// Finds the first placeholder given for each tab stop, which its mirrors
+/// repeat.
+fn collect_placeholders<'a>(nodes: &'a [Node], placeholders: &mut HashMap<usize, &'a [Node]>) {
+  for node in nodes {
+    match node {
+      Node::TabStop {
+        index, placeholder, ..
+      } if !placeholder.is_empty() => {
+        placeholders.entry(*index).or_insert(placeholder);
+        collect_placeholders(placeholder, placeholders);
+      }
+      Node::Variable { default, .. } => collect_placeholders(default, placeholders),
+      _ => {}
+    }
+  }
+}
+
+struct Renderer<'a> {
+  options: &'a ExpandOptions<'a>,
+  variable: &'a dyn Fn(&str) -> Option<String>,
+  placeholders: HashMap<usize, &'a [Node]>,
+  text: String,
+  stops: BTreeMap<usize, TabStop>,
+  /// The tab stops whose placeholders are being rendered, so a stop nested
+  /// in its own placeholder isn't rendered forever.
+  rendering: Vec<usize>,
+}
+
+impl<'a> Renderer<'a> {
+  fn render(&mut self, nodes: &'a [Node]) {
+    for node in nodes {
+      match node {
+        Node::Text(text) => self.push_template_text(text),
+        Node::TabStop { index, choices, .. } => {
+          let start = self.text.len();
+          if !self.rendering.contains(index) {
+            self.rendering.push(*index);
+            match self.placeholders.get(index) {
+              Some(placeholder) => self.render(placeholder),
+              None => {
+                let choice = choices.first().cloned().or_else(|| {
+                  self
+                    .stops
+                    .get(index)
+                    .and_then(|stop| stop.choices.first().cloned())
+                });
+                if let Some(choice) = choice {
+                  self.text.push_str(&choice);
+                }
+              }
+            }
+            self.rendering.pop();
+          }
+          let stop = self.stops.entry(*index).or_insert_with(|| TabStop {
+            ranges: Vec::new(),
+            choices: Vec::new(),
+          });
+          stop.ranges.push(start..self.text.len());
+          if stop.choices.is_empty() {
+            stop.choices = choices.clone();
+          }
+        }
+        Node::Variable { name, default } => match (self.variable)(name) {
+          Some(value) => self.text.push_str(&value),
+          None if !default.is_empty() => self.render(default),
+          None => self.text.push_str(name),
+        },
+      }
+    }
+  }
+
+  fn push_template_text(&mut self, text: &str) {
+    for ch in text.chars() {
+      match ch {
+        '\n' => {
+          self.text.push_str(self.options.line_ending);
+          self.text.push_str(self.options.indent);
+        }
+        '\r' => {}
+        '\t' => self.text.push_str(self.options.indent_unit),
+        ch => self.text.push(ch),
+      }
+    }
+  }
+}
+
+/// Shifts `range` to account for `edited` being replaced by `new_len`
+/// bytes. Edits within or overlapping the range grow or shrink it. Text
+/// inserted right at its edge only joins it when `sticky`, as for the stop
+/// being typed in, so neighbouring stops keep their own text.
+pub fn adjust_range(range: &mut Range<usize>, edited: &Range<usize>, new_len: usize, sticky: bool) {
+  let shift = |offset: usize| offset - edited.len() + new_len;
+  if !sticky && edited.is_empty() {
+    if edited.start == range.start {
+      range.start = shift(range.start);
+      range.end = shift(range.end);
+      return;
+    }
+    if edited.start == range.end {
+      return;
+    }
+  }
+  if edited.start >= range.start && edited.end <= range.end {
+    range.end = shift(range.end);
+  } else if edited.end <= range.start {
+    range.start = shift(range.start);
+    range.end = shift(range.end);
+  } else if edited.start < range.end {
+    // The edit overlaps the range's edge, so the range keeps what's left of
+    // it plus the new text.
+    range.start = range.start.min(edited.start);
+    range.end = shift(range.end.max(edited.end));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn expand_plain(body: &str) -> ExpandedSnippet {
+    let options = ExpandOptions {
+      line_ending: "\n",
+      indent: "",
+      indent_unit: "  ",
+    };
+    expand(body, &options, |_| None)
+  }
+
+  fn stop(ranges: &[(usize, usize)]) -> TabStop {
+    TabStop {
+      ranges: ranges.iter().map(|&(start, end)| start..end).collect(),
+      choices: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn placeholder_with_mirror() {
+    let expanded = expand_plain("${1:a} = $1;$0");
+    assert_eq!(expanded.text, "a = a;");
+    assert_eq!(expanded.stops, [stop(&[(0, 1), (4, 5)]), stop(&[(6, 6)])]);
+  }
+
+  #[test]
+  fn nested_placeholders() {
+    let expanded = expand_plain("f(${1:a, ${2:b}})");
+    assert_eq!(expanded.text, "f(a, b)");
+    assert_eq!(
+      expanded.stops,
+      [stop(&[(2, 6)]), stop(&[(5, 6)]), stop(&[(7, 7)])]
+    );
+  }
+
+  #[test]
+  fn choice() {
+    let expanded = expand_plain("${1|a,b|}");
+    assert_eq!(expanded.text, "a");
+    assert_eq!(
+      expanded.stops[0],
+      TabStop {
+        choices: vec!["a".into(), "b".into()],
+        ..stop(&[(0, 1)])
+      }
+    );
+  }
+
+  #[test]
+  fn escapes() {
+    let expanded = expand_plain(r"\$1 ${1:\}} \\ $ a\b");
+    assert_eq!(expanded.text, r"$1 } \ $ a\b");
+    assert_eq!(expanded.stops, [stop(&[(3, 4)]), stop(&[(12, 12)])]);
+  }
+
+  #[test]
+  fn missing_final_stop_goes_at_the_end() {
+    let expanded = expand_plain("a$1b");
+    assert_eq!(expanded.text, "ab");
+    assert_eq!(expanded.stops, [stop(&[(1, 1)]), stop(&[(2, 2)])]);
+  }
+
+  #[test]
+  fn variables() {
+    let options = ExpandOptions {
+      line_ending: "\n",
+      indent: "",
+      indent_unit: "  ",
+    };
+    let variable = |name: &str| (name == "TM_FILENAME").then(|| "main.rs".to_string());
+    let expanded = expand(
+      "$TM_FILENAME ${TM_SELECTED_TEXT:none} ${TM_FILENAME:x} $UNKNOWN",
+      &options,
+      variable,
+    );
+    assert_eq!(expanded.text, "main.rs none main.rs UNKNOWN");
+  }
+
+  #[test]
+  fn line_breaks_and_tabs_follow_the_insertion_point() {
+    let options = ExpandOptions {
+      line_ending: "\r\n",
+      indent: "  ",
+      indent_unit: "    ",
+    };
+    let expanded = expand("{\n\t$0\n}", &options, |_| None);
+    assert_eq!(expanded.text, "{\r\n      \r\n  }");
+    assert_eq!(expanded.stops, [stop(&[(9, 9)])]);
+  }
+
+  #[test]
+  fn parse_snippet_files() {
+    let definitions = parse_definitions("snippet fn A function\nfn $1() {\n\t$0\n}\nendsnippet\n");
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].prefix, "fn");
+    assert_eq!(definitions[0].description, "A function");
+    assert_eq!(definitions[0].body, "fn $1() {\n\t$0\n}");
+  }
+
+  #[test]
+  fn adjust_range_around_edits() {
+    let adjusted = |range: Range<usize>, edited: Range<usize>, new_len, sticky| {
+      let mut range = range;
+      adjust_range(&mut range, &edited, new_len, sticky);
+      range
+    };
+    // Before, inside and after the range.
+    assert_eq!(adjusted(4..6, 0..1, 3, false), 6..8);
+    assert_eq!(adjusted(4..6, 5..6, 0, false), 4..5);
+    assert_eq!(adjusted(4..6, 7..8, 3, false), 4..6);
+    // Overlapping its edge.
+    assert_eq!(adjusted(4..6, 3..5, 1, false), 3..5);
+    // Replacing it entirely.
+    assert_eq!(adjusted(4..6, 4..6, 1, false), 4..5);
+  }
+
+  #[test]
+  fn adjust_range_at_touching_edges() {
+    let adjusted = |range: Range<usize>, edited: Range<usize>, new_len, sticky| {
+      let mut range = range;
+      adjust_range(&mut range, &edited, new_len, sticky);
+      range
+    };
+    // Typing at either edge of the active stop extends it.
+    assert_eq!(adjusted(4..6, 4..4, 1, true), 4..7);
+    assert_eq!(adjusted(4..6, 6..6, 1, true), 4..7);
+    assert_eq!(adjusted(4..4, 4..4, 1, true), 4..5);
+    // Other stops let it go before or after them.
+    assert_eq!(adjusted(4..6, 4..4, 1, false), 5..7);
+    assert_eq!(adjusted(4..6, 6..6, 1, false), 4..6);
+    assert_eq!(adjusted(4..4, 4..4, 1, false), 5..5);
+  }
+
+  #[test]
+  fn typing_between_adjacent_stops() {
+    // `${1:a}${2:b}` with the first stop active.
+    let mut first = 0..1;
+    let mut second = 1..2;
+    adjust_range(&mut first, &(1..1), 1, true);
+    adjust_range(&mut second, &(1..1), 1, false);
+    assert_eq!((first, second), (0..2, 2..3));
+
+    // And with the second one active.
+    let mut first = 0..1;
+    let mut second = 1..2;
+    adjust_range(&mut first, &(1..1), 1, false);
+    adjust_range(&mut second, &(1..1), 1, true);
+    assert_eq!((first, second), (0..1, 1..3));
+  }
+
+  #[test]
+  fn session_contains_selections_within_stops() {
+    let session = SnippetSession {
+      stops: vec![stop(&[(0, 1), (4, 5)]), stop(&[(8, 8)])],
+      active: 0,
+      choice: 0,
+    };
+    assert!(session.contains(&(0..1)));
+    assert!(session.contains(&(5..5)));
+    assert!(session.contains(&(8..8)));
+    assert!(!session.contains(&(2..2)));
+    assert!(!session.contains(&(0..5)));
+  }
+}
@@ -0,0 +1,130 @@
+use gpui::{
+  Action, Context, Div, Entity, MouseButton, SharedString, Subscription, Window, actions, div,
+  prelude::*, px, rgb,
+};
+
+use crate::{go_to_line, macros::ToggleMacroRecording, settings::Settings, text_input::TextInput};
+
+actions!(
+  status_bar,
+  [
+    SelectLanguage,
+    SelectIndentation,
+    SelectLineEnding,
+    SelectEncoding
+  ]
+);
+
+pub struct StatusBar {
+  editor: Entity<TextInput>,
+  pub recording_macro: bool,
+  _editor_subscription: Subscription,
+  _settings_subscription: Subscription,
+}
+
+impl StatusBar {
+  pub fn new(editor: Entity<TextInput>, cx: &mut Context<Self>) -> Self {
+    Self {
+      _editor_subscription: cx.observe(&editor, |_, _, cx| cx.notify()),
+      editor,
+      recording_macro: false,
+      _settings_subscription: cx.observe_global::<Settings>(|_, cx| cx.notify()),
+    }
+  }
+
+  /// Shows the state of `editor` instead, when another one becomes active.
+  pub fn set_editor(&mut self, editor: Entity<TextInput>, cx: &mut Context<Self>) {
+    self._editor_subscription = cx.observe(&editor, |_, _, cx| cx.notify());
+    self.editor = editor;
+    cx.notify();
+  }
+}
+
+/// A status bar entry, dispatching `action` from the focused element when
+/// clicked.
+fn item(label: impl Into<SharedString>, action: Option<Box<dyn Action>>) -> Div {
+  div()
+    .px(px(6.))
+    .child(label.into())
+    .when_some(action, |this, action| {
+      this
+        .hover(|style| style.bg(rgb(0xcccccc)))
+        .on_mouse_down(MouseButton::Left, move |_, window, cx| {
+          window.dispatch_action(action.boxed_clone(), cx)
+        })
+    })
+}
+
+impl Render for StatusBar {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    let editor = self.editor.read(cx);
+    let settings = cx.global::<Settings>();
+
+    let (line, column) = editor.line_column_for_offset(editor.cursor_offset());
+    let selection = (!editor.selected_range.is_empty()).then(|| {
+      let range = editor.selected_range.clone();
+      let lines = editor.line_column_for_offset(range.end).0
+        - editor.line_column_for_offset(range.start).0
+        + 1;
+      let chars = editor.content.byte_slice(range).len_chars();
+      format!("({chars} chars, {lines} lines selected)")
+    });
+    let diagnostic = editor
+      .diagnostics
+      .iter()
+      .find(|diagnostic| diagnostic.line == line)
+      .map(|diagnostic| diagnostic.message.clone());
+    let indentation = if settings.hard_tabs {
+      format!("Tab Size: {}", settings.tab_size)
+    } else {
+      format!("Spaces: {}", settings.tab_size)
+    };
+
+    div()
+      .flex()
+      .justify_between()
+      .h(px(24.))
+      .px(px(4.))
+      .items_center()
+      .text_sm()
+      .bg(rgb(0xdddddd))
+      .child(
+        div()
+          .flex()
+          .child(item(
+            format!("Ln {}, Col {}", line + 1, column + 1),
+            Some(Box::new(go_to_line::Toggle)),
+          ))
+          .children(selection.map(|selection| item(selection, None)))
+          .children(diagnostic.map(|message| item(message, None)))
+          .when(self.recording_macro, |this| {
+            this.child(item(
+              "Recording Macro",
+              Some(Box::new(ToggleMacroRecording)),
+            ))
+          }),
+      )
+      .child(
+        div()
+          .flex()
+          .child(item(
+            editor.encoding.label(),
+            Some(Box::new(SelectEncoding)),
+          ))
+          .child(item(
+            if editor.mixed_line_endings {
+              "Mixed"
+            } else {
+              editor.line_ending.label()
+            },
+            Some(Box::new(SelectLineEnding)),
+          ))
+          .child(item(indentation, Some(Box::new(SelectIndentation))))
+          .child(item(editor.language.name, Some(Box::new(SelectLanguage))))
+          .when(editor.large_file, |this| {
+            this.child(item("Large File", None))
+          })
+          .when(editor.is_dirty(), |this| this.child(item("Modified", None))),
+      )
+  }
+}
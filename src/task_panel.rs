@@ -0,0 +1,315 @@
+use std::{
+  io::{PipeReader, Read},
+  path::{Path, PathBuf},
+  process::Child,
+};
+
+use gpui::{
+  Context, EventEmitter, MouseButton, ScrollStrategy, SharedString, Task, UniformListScrollHandle,
+  Window, actions, div, prelude::*, px, rgb, uniform_list,
+};
+
+use crate::{
+  diagnostics::{Diagnostic, Problem, ProblemMatcher, Severity},
+  tasks::{self, TaskDefinition},
+  ui::button,
+};
+
+actions!(task_panel, [RunTask, RerunLastTask]);
+
+const HEIGHT: f32 = 200.;
+const LINE_HEIGHT: f32 = 18.;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub enum TaskPanelEvent {
+  /// The problems found in the output changed.
+  ProblemsChanged,
+  /// A problem in the output was clicked.
+  OpenProblem(Problem),
+  Close,
+}
+
+enum TaskStatus {
+  Running,
+  /// The task exited, with its exit code unless a signal stopped it.
+  Exited(Option<i32>),
+  Failed(String),
+}
+
+struct OutputLine {
+  text: SharedString,
+  /// The index into `problems` of the problem found on this line.
+  problem: Option<usize>,
+}
+
+/// The output of the last task run, with compiler-style problems in it
+/// shown as links to their locations.
+pub struct TaskPanel {
+  workspace: PathBuf,
+  pub last_task: Option<TaskDefinition>,
+  status: Option<TaskStatus>,
+  lines: Vec<OutputLine>,
+  /// The end of the output not yet terminated by a newline.
+  partial_line: Vec<u8>,
+  pub problems: Vec<Problem>,
+  matcher: ProblemMatcher,
+  child: Option<Child>,
+  scroll_handle: UniformListScrollHandle,
+  _run_task: Task<()>,
+}
+
+impl EventEmitter<TaskPanelEvent> for TaskPanel {}
+
+impl TaskPanel {
+  pub fn new(workspace: PathBuf) -> Self {
+    Self {
+      matcher: ProblemMatcher::new(workspace.clone()),
+      workspace,
+      last_task: None,
+      status: None,
+      lines: Vec::new(),
+      partial_line: Vec::new(),
+      problems: Vec::new(),
+      child: None,
+      scroll_handle: UniformListScrollHandle::new(),
+      _run_task: Task::ready(()),
+    }
+  }
+
+  /// The diagnostics found for the file at `path`.
+  pub fn diagnostics_for(&self, path: &Path) -> Vec<Diagnostic> {
+    self
+      .problems
+      .iter()
+      .filter(|problem| problem.path == path)
+      .map(|problem| problem.diagnostic.clone())
+      .collect()
+  }
+
+  /// Runs `task`, replacing the output of the previous run and stopping it
+  /// if it is still running.
+  pub fn run(&mut self, task: TaskDefinition, cx: &mut Context<Self>) {
+    self.stop(cx);
+    if let Some(mut child) = self.child.take() {
+      cx.background_spawn(async move { child.wait().ok() })
+        .detach();
+    }
+    self.lines.clear();
+    self.partial_line.clear();
+    self.problems.clear();
+    self.matcher = ProblemMatcher::new(self.workspace.clone());
+    cx.emit(TaskPanelEvent::ProblemsChanged);
+
+    self.push_line(format!("> {}", task.command), cx);
+    match tasks::spawn(&task.command, &self.workspace) {
+      Ok((child, reader)) => {
+        self.child = Some(child);
+        self.status = Some(TaskStatus::Running);
+        self._run_task = self.read_output(reader, cx);
+      }
+      Err(error) => self.status = Some(TaskStatus::Failed(error.to_string())),
+    }
+    self.last_task = Some(task);
+    cx.notify();
+  }
+
+  fn read_output(&mut self, mut reader: PipeReader, cx: &mut Context<Self>) -> Task<()> {
+    cx.spawn(async move |this, cx| {
+      let mut buffer = vec![0; READ_BUFFER_SIZE];
+      loop {
+        let read;
+        (reader, buffer, read) = cx
+          .background_spawn(async move {
+            let read = reader.read(&mut buffer);
+            (reader, buffer, read)
+          })
+          .await;
+        let len = match read {
+          Ok(0) | Err(_) => break,
+          Ok(len) => len,
+        };
+        let appended = this.update(cx, |this: &mut Self, cx| {
+          this.append_output(&buffer[..len], cx)
+        });
+        if appended.is_err() {
+          return;
+        }
+      }
+
+      let Ok(child) = this.update(cx, |this, cx| {
+        let partial_line = std::mem::take(&mut this.partial_line);
+        if !partial_line.is_empty() {
+          this.push_line(String::from_utf8_lossy(&partial_line).into_owned(), cx);
+        }
+        this.child.take()
+      }) else {
+        return;
+      };
+      // The output ends when the task exits, so waiting is brief.
+      let status = match child {
+        Some(mut child) => cx.background_spawn(async move { child.wait() }).await.ok(),
+        None => None,
+      };
+      this
+        .update(cx, |this, cx| {
+          if let Some(status) = status {
+            this.status = Some(TaskStatus::Exited(status.code()));
+          }
+          cx.notify();
+        })
+        .ok();
+    })
+  }
+
+  fn append_output(&mut self, output: &[u8], cx: &mut Context<Self>) {
+    self.partial_line.extend_from_slice(output);
+    let Some(end) = self.partial_line.iter().rposition(|&byte| byte == b'\n') else {
+      return;
+    };
+    let complete = self.partial_line.drain(..=end).collect::<Vec<_>>();
+    let problem_count = self.problems.len();
+    for line in String::from_utf8_lossy(&complete[..end]).split('\n') {
+      self.push_line(line.trim_end_matches('\r').to_string(), cx);
+    }
+    if self.problems.len() != problem_count {
+      cx.emit(TaskPanelEvent::ProblemsChanged);
+    }
+  }
+
+  fn push_line(&mut self, text: String, cx: &mut Context<Self>) {
+    let problem = self.matcher.match_line(&text).map(|problem| {
+      self.problems.push(problem);
+      self.problems.len() - 1
+    });
+    self.lines.push(OutputLine {
+      text: text.into(),
+      problem,
+    });
+    self
+      .scroll_handle
+      .scroll_to_item(self.lines.len() - 1, ScrollStrategy::Bottom);
+    cx.notify();
+  }
+
+  /// Stops the running task, if any.
+  pub fn stop(&mut self, cx: &mut Context<Self>) {
+    if let Some(child) = self.child.as_mut() {
+      tasks::kill(child);
+      cx.notify();
+    }
+  }
+
+  fn render_status(&self) -> String {
+    let label = self
+      .last_task
+      .as_ref()
+      .map_or("", |task| task.label.as_str());
+    let status = match &self.status {
+      None => String::new(),
+      Some(TaskStatus::Running) => "running".into(),
+      Some(TaskStatus::Exited(Some(0))) => "succeeded".into(),
+      Some(TaskStatus::Exited(Some(code))) => format!("failed with exit code {code}"),
+      Some(TaskStatus::Exited(None)) => "stopped".into(),
+      Some(TaskStatus::Failed(error)) => format!("couldn't start: {error}"),
+    };
+    let errors = self
+      .problems
+      .iter()
+      .filter(|problem| problem.diagnostic.severity == Severity::Error)
+      .count();
+    let warnings = self
+      .problems
+      .iter()
+      .filter(|problem| problem.diagnostic.severity == Severity::Warning)
+      .count();
+    format!("{label}: {status} ({errors} errors, {warnings} warnings)")
+  }
+}
+
+impl Render for TaskPanel {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    let running = matches!(self.status, Some(TaskStatus::Running));
+    div()
+      .flex()
+      .flex_col()
+      .flex_none()
+      .h(px(HEIGHT))
+      .bg(rgb(0xffffff))
+      .child(
+        div()
+          .flex()
+          .items_center()
+          .gap(px(6.))
+          .h(px(28.))
+          .px(px(8.))
+          .bg(rgb(0xcccccc))
+          .child(div().flex_1().child(self.render_status()))
+          .when(running, |this| {
+            this.child(button("Stop").on_mouse_down(
+              MouseButton::Left,
+              cx.listener(|this, _, _, cx| this.stop(cx)),
+            ))
+          })
+          .when(!running && self.last_task.is_some(), |this| {
+            this.child(button("Rerun").on_mouse_down(
+              MouseButton::Left,
+              cx.listener(|this, _, _, cx| {
+                if let Some(task) = this.last_task.clone() {
+                  this.run(task, cx);
+                }
+              }),
+            ))
+          })
+          .child(
+            div()
+              .hover(|style| style.bg(rgb(0xdddddd)))
+              .child("\u{00D7}")
+              .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_, _, _, cx| cx.emit(TaskPanelEvent::Close)),
+              ),
+          ),
+      )
+      .child(
+        uniform_list(
+          "task-output",
+          self.lines.len(),
+          cx.processor(|this, range: std::ops::Range<usize>, _, cx| {
+            range
+              .map(|ix| {
+                let line = &this.lines[ix];
+                div()
+                  .h(px(LINE_HEIGHT))
+                  .px(px(8.))
+                  .text_sm()
+                  .whitespace_nowrap()
+                  .child(line.text.clone())
+                  .when_some(line.problem, |this, problem| {
+                    this
+                      .text_color(rgb(0x0044cc))
+                      .underline()
+                      .cursor_pointer()
+                      .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                          cx.emit(TaskPanelEvent::OpenProblem(this.problems[problem].clone()))
+                        }),
+                      )
+                  })
+              })
+              .collect()
+          }),
+        )
+        .track_scroll(self.scroll_handle.clone())
+        .flex_1(),
+      )
+  }
+}
+
+impl Drop for TaskPanel {
+  fn drop(&mut self) {
+    if let Some(child) = self.child.as_mut() {
+      tasks::kill(child);
+    }
+  }
+}
@@ -0,0 +1,83 @@
+use std::{
+  fs,
+  io::{self, PipeReader},
+  path::{Path, PathBuf},
+  process::{Child, Command},
+};
+
+/// A command the project defines for running from the editor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskDefinition {
+  pub label: String,
+  pub command: String,
+}
+
+/// The project's tasks file, relative to the workspace.
+pub fn tasks_path(workspace: &Path) -> PathBuf {
+  workspace.join(".zeta").join("tasks")
+}
+
+/// Reads the `label = command` lines of the project's tasks file.
+pub fn load(workspace: &Path) -> io::Result<Vec<TaskDefinition>> {
+  let contents = fs::read_to_string(tasks_path(workspace))?;
+  Ok(
+    contents
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .filter_map(|line| {
+        let (label, command) = line.split_once('=')?;
+        Some(TaskDefinition {
+          label: label.trim().to_string(),
+          command: command.trim().to_string(),
+        })
+      })
+      .collect(),
+  )
+}
+
+/// Runs `command` through the shell in `cwd`, returning the process and a
+/// reader of its combined stdout and stderr.
+pub fn spawn(command: &str, cwd: &Path) -> io::Result<(Child, PipeReader)> {
+  let (reader, writer) = io::pipe()?;
+  let mut shell = shell_command(command);
+  shell
+    .current_dir(cwd)
+    .stdin(std::process::Stdio::null())
+    .stdout(writer.try_clone()?)
+    .stderr(writer);
+  // The task and everything it starts share a process group, so stopping it
+  // stops them all.
+  #[cfg(unix)]
+  std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+  let child = shell.spawn()?;
+  // Dropping the command closes its copies of the pipe's write end, so
+  // reading ends when the task's processes exit.
+  drop(shell);
+  Ok((child, reader))
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+  let mut shell = Command::new("/bin/sh");
+  shell.arg("-c").arg(command);
+  shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+  let mut shell = Command::new("cmd");
+  shell.arg("/C").arg(command);
+  shell
+}
+
+/// Stops a task started by `spawn`, along with the processes it started.
+pub fn kill(child: &mut Child) {
+  #[cfg(unix)]
+  // SAFETY: signalling a process group has no memory safety requirements.
+  unsafe {
+    libc::kill(-(child.id() as i32), libc::SIGTERM);
+  }
+  #[cfg(not(unix))]
+  child.kill().ok();
+}
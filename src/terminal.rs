@@ -0,0 +1,372 @@
+use std::{io::Read, path::Path};
+
+use gpui::{
+  App, Bounds, ClipboardItem, Context, Entity, EventEmitter, FocusHandle, Focusable, KeyDownEvent,
+  Keystroke, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels, Point,
+  ScrollWheelEvent, Task, Window, div, prelude::*,
+};
+
+use crate::{
+  pty::Pty,
+  terminal_element::TerminalElement,
+  text_input::{Copy, Paste},
+  vt::Screen,
+};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub enum TerminalEvent {
+  /// The shell exited.
+  Exited,
+}
+
+/// The cell grid as last painted, for mapping mouse positions to cells.
+pub struct TerminalLayout {
+  pub bounds: Bounds<Pixels>,
+  pub cell_width: Pixels,
+  pub line_height: Pixels,
+}
+
+/// A shell running in a pseudo-terminal, shown as a grid of cells.
+pub struct Terminal {
+  pub screen: Screen,
+  pty: Pty,
+  pub focus_handle: FocusHandle,
+  /// How many lines the view is scrolled back into the history.
+  pub scroll_offset: usize,
+  /// The anchor and head of the selection, as `(line, column)` with lines
+  /// counted from the oldest line of scrollback.
+  pub selection: Option<((usize, usize), (usize, usize))>,
+  is_selecting: bool,
+  pub last_layout: Option<TerminalLayout>,
+  _read_task: Task<()>,
+}
+
+impl EventEmitter<TerminalEvent> for Terminal {}
+
+impl Terminal {
+  /// Starts the user's shell in `cwd`.
+  pub fn spawn(cwd: &Path, cx: &mut App) -> std::io::Result<Entity<Self>> {
+    let (rows, cols) = (24, 80);
+    let pty = Pty::spawn(cwd, rows, cols)?;
+    let mut reader = pty.reader()?;
+    Ok(cx.new(|cx| {
+      let read_task = cx.spawn(async move |this, cx| {
+        let mut buffer = vec![0; READ_BUFFER_SIZE];
+        loop {
+          let read;
+          (reader, buffer, read) = cx
+            .background_spawn(async move {
+              let read = reader.read(&mut buffer);
+              (reader, buffer, read)
+            })
+            .await;
+          let len = match read {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            // Reading fails once the shell has exited and closed the terminal.
+            Err(_) => break,
+          };
+          if this
+            .update(cx, |this: &mut Self, cx| this.process(&buffer[..len], cx))
+            .is_err()
+          {
+            return;
+          }
+        }
+        this.update(cx, |_, cx| cx.emit(TerminalEvent::Exited)).ok();
+      });
+      Self {
+        screen: Screen::new(rows as usize, cols as usize),
+        pty,
+        focus_handle: cx.focus_handle(),
+        scroll_offset: 0,
+        selection: None,
+        is_selecting: false,
+        last_layout: None,
+        _read_task: read_task,
+      }
+    }))
+  }
+
+  fn process(&mut self, output: &[u8], cx: &mut Context<Self>) {
+    let scrollback = self.screen.scrollback.len();
+    let responses = self.screen.advance(output);
+    if !responses.is_empty() {
+      self.pty.write(&responses).ok();
+    }
+    // A view scrolled into the history stays on the same lines.
+    if self.scroll_offset > 0 {
+      self.scroll_offset = (self.scroll_offset + self.screen.scrollback.len())
+        .saturating_sub(scrollback)
+        .min(self.screen.scrollback.len());
+    }
+    cx.notify();
+  }
+
+  /// The title for the terminal's tab.
+  pub fn title(&self) -> String {
+    self
+      .screen
+      .title
+      .clone()
+      .filter(|title| !title.is_empty())
+      .unwrap_or_else(|| "Terminal".into())
+  }
+
+  /// The line shown at the top of the view.
+  pub fn first_line(&self) -> usize {
+    self.screen.scrollback.len() - self.scroll_offset
+  }
+
+  pub fn resize(&mut self, rows: usize, cols: usize) {
+    if (rows, cols) == (self.screen.rows, self.screen.cols) {
+      return;
+    }
+    self.screen.resize(rows, cols);
+    self.scroll_offset = self.scroll_offset.min(self.screen.scrollback.len());
+    self.pty.resize(rows as u16, cols as u16).ok();
+  }
+
+  /// Sends input to the shell, scrolling back to the bottom.
+  fn write(&mut self, input: &[u8], cx: &mut Context<Self>) {
+    self.scroll_offset = 0;
+    self.selection = None;
+    self.pty.write(input).ok();
+    cx.notify();
+  }
+
+  fn key_down(&mut self, event: &KeyDownEvent, _: &mut Window, cx: &mut Context<Self>) {
+    if let Some(input) = key_input(&event.keystroke, self.screen.app_cursor_keys) {
+      cx.stop_propagation();
+      self.write(&input, cx);
+    }
+  }
+
+  fn copy(&mut self, _: &Copy, _: &mut Window, cx: &mut Context<Self>) {
+    if let Some(text) = self.selected_text() {
+      cx.write_to_clipboard(ClipboardItem::new_string(text));
+    }
+  }
+
+  fn paste(&mut self, _: &Paste, _: &mut Window, cx: &mut Context<Self>) {
+    let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+      return;
+    };
+    let text = text.replace("\r\n", "\r").replace('\n', "\r");
+    let input = if self.screen.bracketed_paste {
+      format!("\x1b[200~{text}\x1b[201~")
+    } else {
+      text
+    };
+    self.write(input.as_bytes(), cx);
+  }
+
+  fn selected_text(&self) -> Option<String> {
+    let (anchor, head) = self.selection?;
+    let (start, end) = (anchor.min(head), anchor.max(head));
+    let lines = (start.0..=end.0)
+      .filter_map(|line| {
+        let cells = self.screen.line(line)?;
+        let from = if line == start.0 { start.1 } else { 0 };
+        let to = if line == end.0 { end.1 } else { cells.len() };
+        let text = cells[from.min(cells.len())..to.min(cells.len())]
+          .iter()
+          .filter(|cell| !cell.spacer)
+          .map(|cell| cell.ch)
+          .collect::<String>();
+        Some(text.trim_end().to_string())
+      })
+      .collect::<Vec<_>>();
+    Some(lines.join("\n"))
+  }
+
+  /// The cell boundary closest to `position`, as `(line, column)`.
+  fn cell_for_position(&self, position: Point<Pixels>) -> Option<(usize, usize)> {
+    let layout = self.last_layout.as_ref()?;
+    let local = position - layout.bounds.origin;
+    let row = (local.y / layout.line_height).floor().max(0.) as usize;
+    let col = (local.x / layout.cell_width).round().max(0.) as usize;
+    Some((
+      self.first_line() + row.min(self.screen.rows - 1),
+      col.min(self.screen.cols),
+    ))
+  }
+
+  fn on_mouse_down(&mut self, event: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+    window.focus(&self.focus_handle);
+    self.selection = self
+      .cell_for_position(event.position)
+      .map(|cell| (cell, cell));
+    self.is_selecting = true;
+    cx.notify();
+  }
+
+  fn on_mouse_move(&mut self, event: &MouseMoveEvent, _: &mut Window, cx: &mut Context<Self>) {
+    if !self.is_selecting {
+      return;
+    }
+    if let (Some((anchor, _)), Some(head)) =
+      (self.selection, self.cell_for_position(event.position))
+    {
+      self.selection = Some((anchor, head));
+      cx.notify();
+    }
+  }
+
+  fn on_mouse_up(&mut self, _: &MouseUpEvent, _: &mut Window, cx: &mut Context<Self>) {
+    self.is_selecting = false;
+    if self.selection.is_some_and(|(anchor, head)| anchor == head) {
+      self.selection = None;
+    }
+    cx.notify();
+  }
+
+  fn on_scroll_wheel(&mut self, event: &ScrollWheelEvent, _: &mut Window, cx: &mut Context<Self>) {
+    let Some(layout) = self.last_layout.as_ref() else {
+      return;
+    };
+    let lines = (event.delta.pixel_delta(layout.line_height).y / layout.line_height).round();
+    // Full screen programs have no scrollback, so the wheel moves their
+    // cursor instead, like xterm's alternate scroll mode.
+    if self.screen.is_alternate_screen() {
+      let key = match (lines > 0., self.screen.app_cursor_keys) {
+        (true, false) => "\x1b[A",
+        (true, true) => "\x1bOA",
+        (false, false) => "\x1b[B",
+        (false, true) => "\x1bOB",
+      };
+      self
+        .pty
+        .write(key.repeat(lines.abs() as usize).as_bytes())
+        .ok();
+      return;
+    }
+    self.scroll_offset = (self.scroll_offset as isize + lines as isize)
+      .clamp(0, self.screen.scrollback.len() as isize) as usize;
+    cx.notify();
+  }
+}
+
+/// The bytes an xterm sends for a keystroke. Keystrokes with the platform
+/// modifier are left to the application's bindings.
+fn key_input(keystroke: &Keystroke, app_cursor_keys: bool) -> Option<Vec<u8>> {
+  let modifiers = &keystroke.modifiers;
+  if modifiers.platform {
+    return None;
+  }
+  // The xterm modifier parameter: 1 plus shift 1, alt 2 and control 4.
+  let modifier_param =
+    1 + modifiers.shift as u8 + 2 * modifiers.alt as u8 + 4 * modifiers.control as u8;
+  let cursor_key = |code: char| {
+    Some(if modifier_param > 1 {
+      format!("\x1b[1;{modifier_param}{code}").into_bytes()
+    } else if app_cursor_keys {
+      format!("\x1bO{code}").into_bytes()
+    } else {
+      format!("\x1b[{code}").into_bytes()
+    })
+  };
+  let tilde_key = |number: u8| {
+    Some(if modifier_param > 1 {
+      format!("\x1b[{number};{modifier_param}~").into_bytes()
+    } else {
+      format!("\x1b[{number}~").into_bytes()
+    })
+  };
+  let meta = |bytes: &[u8]| {
+    let mut input = if modifiers.alt {
+      vec![0x1b]
+    } else {
+      Vec::new()
+    };
+    input.extend_from_slice(bytes);
+    Some(input)
+  };
+
+  match keystroke.key.as_str() {
+    "enter" => meta(b"\r"),
+    "backspace" if modifiers.control => meta(b"\x08"),
+    "backspace" => meta(b"\x7f"),
+    "tab" if modifiers.shift => Some(b"\x1b[Z".to_vec()),
+    "tab" => meta(b"\t"),
+    "escape" => Some(b"\x1b".to_vec()),
+    "space" if modifiers.control => meta(b"\0"),
+    "up" => cursor_key('A'),
+    "down" => cursor_key('B'),
+    "right" => cursor_key('C'),
+    "left" => cursor_key('D'),
+    "home" => cursor_key('H'),
+    "end" => cursor_key('F'),
+    "insert" => tilde_key(2),
+    "delete" => tilde_key(3),
+    "pageup" => tilde_key(5),
+    "pagedown" => tilde_key(6),
+    "f1" => Some(b"\x1bOP".to_vec()),
+    "f2" => Some(b"\x1bOQ".to_vec()),
+    "f3" => Some(b"\x1bOR".to_vec()),
+    "f4" => Some(b"\x1bOS".to_vec()),
+    "f5" => tilde_key(15),
+    "f6" => tilde_key(17),
+    "f7" => tilde_key(18),
+    "f8" => tilde_key(19),
+    "f9" => tilde_key(20),
+    "f10" => tilde_key(21),
+    "f11" => tilde_key(23),
+    "f12" => tilde_key(24),
+    key if modifiers.control && key.len() == 1 => {
+      let byte = match key.as_bytes()[0] {
+        byte @ b'a'..=b'z' => byte - b'a' + 1,
+        b'@' | b'2' => 0,
+        b'[' | b'3' => 0x1b,
+        b'\\' | b'4' => 0x1c,
+        b']' | b'5' => 0x1d,
+        b'^' | b'6' => 0x1e,
+        b'_' | b'-' | b'7' => 0x1f,
+        b'?' | b'8' => 0x7f,
+        _ => return None,
+      };
+      meta(&[byte])
+    }
+    // Alt is meta rather than a way to type other characters.
+    key if modifiers.alt && key.chars().count() == 1 => {
+      let key = if modifiers.shift {
+        key.to_uppercase()
+      } else {
+        key.to_string()
+      };
+      meta(key.as_bytes())
+    }
+    _ => keystroke
+      .key_char
+      .as_ref()
+      .map(|text| text.as_bytes().to_vec()),
+  }
+}
+
+impl Focusable for Terminal {
+  fn focus_handle(&self, _: &App) -> FocusHandle {
+    self.focus_handle.clone()
+  }
+}
+
+impl Render for Terminal {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    div()
+      .key_context("Terminal")
+      .track_focus(&self.focus_handle)
+      .on_key_down(cx.listener(Self::key_down))
+      .on_action(cx.listener(Self::copy))
+      .on_action(cx.listener(Self::paste))
+      .on_mouse_down(MouseButton::Left, cx.listener(Self::on_mouse_down))
+      .on_mouse_move(cx.listener(Self::on_mouse_move))
+      .on_mouse_up(MouseButton::Left, cx.listener(Self::on_mouse_up))
+      .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_mouse_up))
+      .on_scroll_wheel(cx.listener(Self::on_scroll_wheel))
+      .size_full()
+      .child(TerminalElement {
+        terminal: cx.entity(),
+      })
+  }
+}
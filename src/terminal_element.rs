@@ -0,0 +1,268 @@
+use gpui::{
+  App, Bounds, ElementId, Entity, Font, FontStyle, FontWeight, GlobalElementId, Hsla, LayoutId,
+  PaintQuad, Pixels, ShapedLine, Style, TextRun, UnderlineStyle, Window, fill, outline, point,
+  prelude::*, px, relative, rgb, rgba, size,
+};
+
+use crate::{
+  terminal::{Terminal, TerminalLayout},
+  vt::{self, Attrs, Color},
+};
+
+const DEFAULT_FOREGROUND: u32 = 0x333333;
+const DEFAULT_BACKGROUND: u32 = 0xffffff;
+
+#[cfg(target_os = "macos")]
+const FONT_FAMILY: &str = "Menlo";
+#[cfg(target_os = "windows")]
+const FONT_FAMILY: &str = "Consolas";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const FONT_FAMILY: &str = "DejaVu Sans Mono";
+
+pub struct PrepaintState {
+  lines: Vec<ShapedLine>,
+  backgrounds: Vec<PaintQuad>,
+  selections: Vec<PaintQuad>,
+  cursor: Option<PaintQuad>,
+  line_height: Pixels,
+}
+
+/// Paints a terminal's screen, sizing the terminal to fit the element.
+pub struct TerminalElement {
+  pub terminal: Entity<Terminal>,
+}
+
+impl IntoElement for TerminalElement {
+  type Element = Self;
+
+  fn into_element(self) -> Self::Element {
+    self
+  }
+}
+
+fn color_rgb(color: Color, default: u32) -> u32 {
+  match color {
+    Color::Default => default,
+    Color::Indexed(index) => vt::indexed_rgb(index),
+    Color::Rgb(r, g, b) => (r as u32) << 16 | (g as u32) << 8 | b as u32,
+  }
+}
+
+/// The foreground and background colors of a cell.
+fn cell_colors(attrs: &Attrs) -> (Hsla, Hsla) {
+  let mut fg = attrs.fg;
+  // Bold text in one of the eight basic colors uses its bright variant.
+  if attrs.bold
+    && let Color::Indexed(index @ 0..8) = fg
+  {
+    fg = Color::Indexed(index + 8);
+  }
+  let fg = color_rgb(fg, DEFAULT_FOREGROUND);
+  let bg = color_rgb(attrs.bg, DEFAULT_BACKGROUND);
+  let (fg, bg) = if attrs.inverse { (bg, fg) } else { (fg, bg) };
+  (rgb(fg).into(), rgb(bg).into())
+}
+
+impl Element for TerminalElement {
+  type RequestLayoutState = ();
+  type PrepaintState = PrepaintState;
+
+  fn id(&self) -> Option<ElementId> {
+    None
+  }
+
+  fn source_location(&self) -> Option<&'static core::panic::Location<'static>> {
+    None
+  }
+
+  fn request_layout(
+    &mut self,
+    _id: Option<&GlobalElementId>,
+    _inspector_id: Option<&gpui::InspectorElementId>,
+    window: &mut Window,
+    cx: &mut App,
+  ) -> (LayoutId, Self::RequestLayoutState) {
+    let mut style = Style::default();
+    style.size.width = relative(1.).into();
+    style.size.height = relative(1.).into();
+    (window.request_layout(style, [], cx), ())
+  }
+
+  fn prepaint(
+    &mut self,
+    _id: Option<&GlobalElementId>,
+    _inspector_id: Option<&gpui::InspectorElementId>,
+    bounds: Bounds<Pixels>,
+    _request_layout: &mut Self::RequestLayoutState,
+    window: &mut Window,
+    cx: &mut App,
+  ) -> Self::PrepaintState {
+    let font = gpui::font(FONT_FAMILY);
+    let font_size = window.text_style().font_size.to_pixels(window.rem_size());
+    let line_height = window.line_height();
+    let font_id = window.text_system().resolve_font(&font);
+    let cell_width = window
+      .text_system()
+      .advance(font_id, font_size, 'm')
+      .map_or(font_size * 0.6, |advance| advance.width);
+
+    let rows = ((bounds.size.height / line_height).floor() as usize).max(1);
+    let cols = ((bounds.size.width / cell_width).floor() as usize).max(1);
+    let is_focused = self.terminal.read(cx).focus_handle.is_focused(window);
+    self.terminal.update(cx, |terminal, _| {
+      terminal.resize(rows, cols);
+      terminal.last_layout = Some(TerminalLayout {
+        bounds,
+        cell_width,
+        line_height,
+      });
+    });
+
+    let terminal = self.terminal.read(cx);
+    let screen = &terminal.screen;
+    let first_line = terminal.first_line();
+    let selection = terminal
+      .selection
+      .map(|(anchor, head)| (anchor.min(head), anchor.max(head)));
+    let cell_bounds = |row: usize, cols: std::ops::Range<usize>| {
+      Bounds::new(
+        bounds.origin + point(cell_width * cols.start as f32, line_height * row as f32),
+        size(cell_width * cols.len() as f32, line_height),
+      )
+    };
+
+    let mut lines = Vec::new();
+    let mut backgrounds = Vec::new();
+    let mut selections = Vec::new();
+    for row in 0..screen.rows {
+      let line = first_line + row;
+      let Some(cells) = screen.line(line) else {
+        break;
+      };
+
+      let mut text = String::new();
+      let mut runs: Vec<TextRun> = Vec::new();
+      let mut background: Option<(usize, Hsla)> = None;
+      for (col, cell) in cells.iter().enumerate() {
+        let (fg, bg) = cell_colors(&cell.attrs);
+        let run = TextRun {
+          len: cell.ch.len_utf8(),
+          font: Font {
+            weight: if cell.attrs.bold {
+              FontWeight::BOLD
+            } else {
+              FontWeight::NORMAL
+            },
+            style: if cell.attrs.italic {
+              FontStyle::Italic
+            } else {
+              FontStyle::Normal
+            },
+            ..font.clone()
+          },
+          color: fg,
+          background_color: None,
+          underline: cell.attrs.underline.then_some(UnderlineStyle {
+            color: Some(fg),
+            thickness: px(1.),
+            wavy: false,
+          }),
+          strikethrough: None,
+        };
+        text.push(cell.ch);
+        match runs.last_mut() {
+          Some(last)
+            if last.font == run.font
+              && last.color == run.color
+              && last.underline == run.underline =>
+          {
+            last.len += run.len
+          }
+          _ => runs.push(run),
+        }
+
+        // Consecutive cells with the same background share a quad.
+        let bg = (bg != rgb(DEFAULT_BACKGROUND).into()).then_some(bg);
+        match (background, bg) {
+          (Some((_, current)), Some(bg)) if current == bg => {}
+          _ => {
+            if let Some((start, color)) = background.take() {
+              backgrounds.push(fill(cell_bounds(row, start..col), color));
+            }
+            background = bg.map(|bg| (col, bg));
+          }
+        }
+      }
+      if let Some((start, color)) = background {
+        backgrounds.push(fill(cell_bounds(row, start..cells.len()), color));
+      }
+
+      if let Some((start, end)) = selection
+        && (start.0..=end.0).contains(&line)
+      {
+        let from = if line == start.0 { start.1 } else { 0 };
+        let to = if line == end.0 { end.1 } else { cells.len() };
+        if from < to {
+          selections.push(fill(cell_bounds(row, from..to), rgba(0x3311ff30)));
+        }
+      }
+
+      lines.push(
+        window
+          .text_system()
+          .shape_line(text.into(), font_size, &runs, Some(cell_width)),
+      );
+    }
+
+    let cursor = (terminal.scroll_offset == 0 && screen.cursor_visible).then(|| {
+      let col = screen.cursor_col.min(screen.cols - 1);
+      let bounds = cell_bounds(screen.cursor_row, col..col + 1);
+      let color = rgb(DEFAULT_FOREGROUND);
+      if is_focused {
+        fill(bounds, rgba(0x33333380))
+      } else {
+        outline(bounds, color, gpui::BorderStyle::Solid)
+      }
+    });
+
+    PrepaintState {
+      lines,
+      backgrounds,
+      selections,
+      cursor,
+      line_height,
+    }
+  }
+
+  fn paint(
+    &mut self,
+    _id: Option<&GlobalElementId>,
+    _inspector_id: Option<&gpui::InspectorElementId>,
+    bounds: Bounds<Pixels>,
+    _request_layout: &mut Self::RequestLayoutState,
+    prepaint: &mut Self::PrepaintState,
+    window: &mut Window,
+    cx: &mut App,
+  ) {
+    window.paint_quad(fill(bounds, rgb(DEFAULT_BACKGROUND)));
+    for background in prepaint.backgrounds.drain(..) {
+      window.paint_quad(background);
+    }
+    for selection in prepaint.selections.drain(..) {
+      window.paint_quad(selection);
+    }
+    for (row, line) in prepaint.lines.iter().enumerate() {
+      line
+        .paint(
+          bounds.origin + point(px(0.), prepaint.line_height * row as f32),
+          prepaint.line_height,
+          window,
+          cx,
+        )
+        .unwrap();
+    }
+    if let Some(cursor) = prepaint.cursor.take() {
+      window.paint_quad(cursor);
+    }
+  }
+}
@@ -0,0 +1,172 @@
+use std::{io, path::PathBuf};
+
+use gpui::{
+  App, Context, Entity, EventEmitter, FocusHandle, Focusable, MouseButton, Subscription, Window,
+  actions, div, prelude::*, px, rgb,
+};
+
+use crate::{
+  terminal::{Terminal, TerminalEvent},
+  ui::show_error,
+};
+
+actions!(terminal_panel, [ToggleTerminalPanel, NewTerminal]);
+
+const HEIGHT: f32 = 240.;
+
+pub enum TerminalPanelEvent {
+  /// The last terminal was closed or exited.
+  Empty,
+}
+
+/// The dock below the editors holding terminals as tabs, each running a
+/// shell in the workspace directory.
+pub struct TerminalPanel {
+  cwd: PathBuf,
+  terminals: Vec<Entity<Terminal>>,
+  active: usize,
+  /// Subscriptions to `terminals`, at the same indices.
+  subscriptions: Vec<Subscription>,
+  focus_handle: FocusHandle,
+}
+
+impl EventEmitter<TerminalPanelEvent> for TerminalPanel {}
+
+impl TerminalPanel {
+  pub fn new(cwd: PathBuf, cx: &mut Context<Self>) -> Self {
+    Self {
+      cwd,
+      terminals: Vec::new(),
+      active: 0,
+      subscriptions: Vec::new(),
+      focus_handle: cx.focus_handle(),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.terminals.is_empty()
+  }
+
+  pub fn active_terminal(&self) -> Option<&Entity<Terminal>> {
+    self.terminals.get(self.active)
+  }
+
+  pub fn focus_active_terminal(&self, window: &mut Window, cx: &App) {
+    if let Some(terminal) = self.active_terminal() {
+      window.focus(&terminal.read(cx).focus_handle);
+    }
+  }
+
+  /// Starts a shell in a new tab and focuses it.
+  pub fn add_terminal(&mut self, window: &mut Window, cx: &mut Context<Self>) -> io::Result<()> {
+    let terminal = Terminal::spawn(&self.cwd, cx)?;
+    let subscription = cx.subscribe_in(
+      &terminal,
+      window,
+      |this, terminal, event, _, cx| match event {
+        TerminalEvent::Exited => {
+          if let Some(ix) = this.terminals.iter().position(|t| t == terminal) {
+            this.remove_terminal(ix, cx);
+          }
+        }
+      },
+    );
+    window.focus(&terminal.read(cx).focus_handle);
+    self.terminals.push(terminal);
+    self.subscriptions.push(subscription);
+    self.active = self.terminals.len() - 1;
+    cx.notify();
+    Ok(())
+  }
+
+  /// Closes the tab at `ix`, killing its shell.
+  pub fn remove_terminal(&mut self, ix: usize, cx: &mut Context<Self>) {
+    self.terminals.remove(ix);
+    drop(self.subscriptions.remove(ix));
+    if self.active > ix || self.active == self.terminals.len() {
+      self.active = self.active.saturating_sub(1);
+    }
+    if self.terminals.is_empty() {
+      cx.emit(TerminalPanelEvent::Empty);
+    }
+    cx.notify();
+  }
+
+  fn render_tab(&self, ix: usize, cx: &mut Context<Self>) -> impl IntoElement + use<> {
+    let title = self.terminals[ix].read(cx).title();
+    let focus_handle = self.terminals[ix].read(cx).focus_handle.clone();
+    div()
+      .flex()
+      .items_center()
+      .gap(px(6.))
+      .px(px(12.))
+      .when(ix == self.active, |this| this.bg(rgb(0xeeeeee)))
+      .on_mouse_down(
+        MouseButton::Left,
+        cx.listener(move |this, _, window, cx| {
+          this.active = ix;
+          window.focus(&focus_handle);
+          cx.notify();
+        }),
+      )
+      .child(title)
+      .child(
+        div()
+          .hover(|style| style.bg(rgb(0xcccccc)))
+          .child("\u{00D7}")
+          .on_mouse_down(
+            MouseButton::Left,
+            cx.listener(move |this, _, _, cx| {
+              cx.stop_propagation();
+              this.remove_terminal(ix, cx);
+            }),
+          ),
+      )
+  }
+}
+
+impl Focusable for TerminalPanel {
+  fn focus_handle(&self, _: &App) -> FocusHandle {
+    self.focus_handle.clone()
+  }
+}
+
+impl Render for TerminalPanel {
+  fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    div()
+      .track_focus(&self.focus_handle)
+      .flex()
+      .flex_col()
+      .flex_none()
+      .h(px(HEIGHT))
+      .child(
+        div()
+          .flex()
+          .h(px(28.))
+          .bg(rgb(0xcccccc))
+          .children((0..self.terminals.len()).map(|ix| self.render_tab(ix, cx)))
+          .child(
+            div()
+              .px(px(12.))
+              .hover(|style| style.bg(rgb(0xdddddd)))
+              .child("+")
+              .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| {
+                  if let Err(error) = this.add_terminal(window, cx) {
+                    show_error("Couldn't start a terminal", &error, window, cx);
+                  }
+                }),
+              ),
+          ),
+      )
+      .child(
+        div()
+          .flex_1()
+          .min_h_0()
+          .p(px(4.))
+          .bg(rgb(0xffffff))
+          .children(self.active_terminal().cloned()),
+      )
+  }
+}
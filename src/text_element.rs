@@ -1,15 +1,142 @@
+use std::{
+  ops::Range,
+  time::{Duration, Instant},
+};
+
 use gpui::{
-  App, Bounds, ElementId, ElementInputHandler, Entity, GlobalElementId, LayoutId, PaintQuad,
-  Pixels, ShapedLine, Style, TextRun, UnderlineStyle, Window, fill, hsla, point, prelude::*, px,
-  relative, rgba, size,
+  App, BorderStyle, Bounds, ElementId, ElementInputHandler, Entity, GlobalElementId, Hsla,
+  LayoutId, PaintQuad, Pixels, Point, ShapedLine, Style, TextRun, UnderlineStyle, Window, fill,
+  hsla, outline, point, prelude::*, px, relative, rgb, rgba, size,
+};
+
+use crate::{
+  diagnostics::Severity,
+  folding::indent_columns,
+  settings::{CursorShape, RenderWhitespace, Settings},
+  text_input::TextInput,
 };
 
-use crate::text_input::TextInput;
+/// The width of the margin left of the text holding fold toggles.
+pub const GUTTER_WIDTH: Pixels = px(16.);
+
+/// The shaped lines in view. Lines outside the viewport aren't shaped, so
+/// offsets on them have no position. Offsets in folded text are placed at
+/// the end of the line they are folded under.
+pub struct TextLayout {
+  /// The display row of the first shaped line.
+  pub first_row: usize,
+  pub lines: Vec<ShapedLine>,
+  pub line_starts: Vec<usize>,
+  /// The end of the last shaped line.
+  pub end: usize,
+  pub line_height: Pixels,
+}
+
+impl TextLayout {
+  /// The offsets covered by the shaped lines.
+  pub fn range(&self) -> Range<usize> {
+    self.line_starts.first().copied().unwrap_or(self.end)..self.end
+  }
+
+  /// The index into `lines` of the shaped line holding `offset`.
+  pub fn line_for_offset(&self, offset: usize) -> Option<usize> {
+    if self.lines.is_empty() || !self.range().contains(&offset) && offset != self.end {
+      return None;
+    }
+    Some(
+      self
+        .line_starts
+        .partition_point(|&start| start <= offset)
+        .saturating_sub(1),
+    )
+  }
+
+  pub fn position_for_offset(&self, offset: usize) -> Option<Point<Pixels>> {
+    let row = self.line_for_offset(offset)?;
+    Some(point(
+      self.lines[row].x_for_index(offset - self.line_starts[row]),
+      self.line_height * (self.first_row + row) as f32,
+    ))
+  }
+
+  pub fn closest_offset_for_position(&self, position: Point<Pixels>) -> Option<usize> {
+    let row = (position.y / self.line_height).floor().max(0.) as usize;
+    let row = row
+      .saturating_sub(self.first_row)
+      .min(self.lines.len().checked_sub(1)?);
+    Some(self.line_starts[row] + self.lines[row].closest_index_for_x(position.x))
+  }
+
+  pub fn offset_for_position(&self, position: Point<Pixels>) -> Option<usize> {
+    let row = (position.y / self.line_height).floor() as usize;
+    let row = row.checked_sub(self.first_row)?;
+    let line = self.lines.get(row)?;
+    Some(self.line_starts[row] + line.index_for_x(position.x)?)
+  }
+}
+
+const CURSOR_ANIMATION_DURATION: Duration = Duration::from_millis(80);
+
+/// The cursor gliding between two positions relative to the text.
+pub struct CursorAnimation {
+  from: Point<Pixels>,
+  to: Point<Pixels>,
+  start: Instant,
+}
+
+impl CursorAnimation {
+  fn progress(&self) -> f32 {
+    (self.start.elapsed().as_secs_f32() / CURSOR_ANIMATION_DURATION.as_secs_f32()).min(1.)
+  }
+
+  fn is_done(&self) -> bool {
+    self.progress() >= 1.
+  }
+
+  /// The position reached so far, easing out towards the end.
+  fn position(&self) -> Point<Pixels> {
+    let eased = 1. - (1. - self.progress()).powi(3);
+    point(
+      self.from.x + (self.to.x - self.from.x) * eased,
+      self.from.y + (self.to.y - self.from.y) * eased,
+    )
+  }
+}
+
+pub struct ChoicePopup {
+  background: PaintQuad,
+  border: PaintQuad,
+  /// Behind the choice shown in the placeholder.
+  highlight: PaintQuad,
+  lines: Vec<(Point<Pixels>, ShapedLine)>,
+}
 
 pub struct PrepaintState {
-  line: Option<ShapedLine>,
+  layout: Option<TextLayout>,
   cursor: Option<PaintQuad>,
-  selection: Option<PaintQuad>,
+  /// Where the cursor is, relative to the text, when shown.
+  cursor_target: Option<Point<Pixels>>,
+  /// Where the cursor is drawn, which trails the target while animating.
+  cursor_position: Option<Point<Pixels>>,
+  current_line: Option<PaintQuad>,
+  /// The list of a snippet's choices under its placeholder.
+  choice_popup: Option<ChoicePopup>,
+  selections: Vec<PaintQuad>,
+  bracket_highlights: Vec<PaintQuad>,
+  /// Wavy underlines under diagnostics, as their origin, width and style.
+  diagnostic_underlines: Vec<(Point<Pixels>, Pixels, UnderlineStyle)>,
+  /// The toggles in the gutter of the lines starting foldable regions.
+  fold_toggles: Vec<(Point<Pixels>, ShapedLine)>,
+  /// The markers after folded lines, with their backgrounds.
+  fold_placeholders: Vec<(Point<Pixels>, ShapedLine, PaintQuad)>,
+  /// A line at each level of indentation, darker in the cursor's block.
+  indent_guides: Vec<PaintQuad>,
+  /// The backgrounds of visible trailing whitespace.
+  trailing_whitespace: Vec<PaintQuad>,
+  /// The dots and arrows drawn over visible spaces and tabs.
+  whitespace_markers: Vec<(Point<Pixels>, ShapedLine)>,
+  /// The element's bounds, less the gutter.
+  text_bounds: Bounds<Pixels>,
 }
 
 pub struct TextElement {
@@ -24,6 +151,60 @@ impl IntoElement for TextElement {
   }
 }
 
+/// Clips `runs`, which cover the whole text, to the given byte range of it.
+fn runs_for_range(runs: &[TextRun], range: Range<usize>) -> Vec<TextRun> {
+  let mut start = 0;
+  runs
+    .iter()
+    .filter_map(|run| {
+      let run_range = start..start + run.len;
+      start = run_range.end;
+      let len = run_range
+        .end
+        .min(range.end)
+        .saturating_sub(run_range.start.max(range.start));
+      (len > 0).then(|| TextRun { len, ..run.clone() })
+    })
+    .collect()
+}
+
+/// Where the given column of `text`'s indentation starts on its shaped
+/// `line`. Columns past the indentation are placed a space apart after it.
+fn x_for_column(
+  line: &ShapedLine,
+  text: &str,
+  column: usize,
+  tab_size: usize,
+  space_width: Pixels,
+) -> Pixels {
+  let mut current = 0;
+  let mut end = 0;
+  for (ix, ch) in text.char_indices() {
+    if current >= column {
+      return line.x_for_index(ix);
+    }
+    match ch {
+      ' ' => current += 1,
+      '\t' => current += tab_size - current % tab_size,
+      _ => break,
+    }
+    end = ix + ch.len_utf8();
+  }
+  if current >= column {
+    return line.x_for_index(end);
+  }
+  line.x_for_index(end) + space_width * (column - current) as f32
+}
+
+/// Whether the space or tab at `ix` in `text` is shown in boundary mode:
+/// tabs always are, and spaces next to other whitespace or a line edge.
+fn is_boundary_whitespace(text: &str, ix: usize) -> bool {
+  let is_blank = |ch: Option<char>| ch.is_none_or(|ch| ch == ' ' || ch == '\t');
+  text[ix..].starts_with('\t')
+    || is_blank(text[..ix].chars().next_back())
+    || is_blank(text[ix + 1..].chars().next())
+}
+
 impl Element for TextElement {
   type RequestLayoutState = ();
   type PrepaintState = PrepaintState;
@@ -43,9 +224,16 @@ impl Element for TextElement {
     window: &mut Window,
     cx: &mut App,
   ) -> (LayoutId, Self::RequestLayoutState) {
+    let input = self.input.read(cx);
+    let line_count = if input.content.len_bytes() == 0 {
+      input.placeholder.split('\n').count()
+    } else {
+      input.display_map().row_count()
+    };
+
     let mut style = Style::default();
     style.size.width = relative(1.).into();
-    style.size.height = window.line_height().into();
+    style.size.height = (window.line_height() * line_count as f32).into();
     (window.request_layout(style, [], cx), ())
   }
 
@@ -59,19 +247,32 @@ impl Element for TextElement {
     cx: &mut App,
   ) -> Self::PrepaintState {
     let input = self.input.read(cx);
-    let content = input.content.clone();
+    let gutter_width = if input.single_line {
+      px(0.)
+    } else {
+      GUTTER_WIDTH
+    };
+    let gutter = bounds;
+    let bounds = Bounds::new(
+      bounds.origin + point(gutter_width, px(0.)),
+      size(bounds.size.width - gutter_width, bounds.size.height),
+    );
+    let display_map = input.display_map();
     let selected_range = input.selected_range.clone();
     let cursor = input.cursor_offset();
+    let highlighted_brackets = input.highlighted_brackets();
     let style = window.text_style();
+    let line_height = window.line_height();
 
-    let (display_text, text_color) = if content.is_empty() {
-      (input.placeholder.clone(), hsla(0., 0., 0., 0.2))
+    let content_is_empty = input.content.len_bytes() == 0;
+    let (display_len, text_color) = if content_is_empty {
+      (input.placeholder.len(), hsla(0., 0., 0., 0.2))
     } else {
-      (content, style.color)
+      (input.content.len_bytes(), style.color)
     };
 
     let run = TextRun {
-      len: display_text.len(),
+      len: display_len,
       font: style.font(),
       color: text_color,
       background_color: None,
@@ -94,7 +295,7 @@ impl Element for TextElement {
           ..run.clone()
         },
         TextRun {
-          len: display_text.len() - marked_range.end,
+          len: display_len - marked_range.end,
           ..run
         },
       ]
@@ -105,45 +306,444 @@ impl Element for TextElement {
       vec![run]
     };
 
+    // Only the lines intersecting the visible part of the element are shaped.
+    let visible = window.content_mask().bounds;
+    let row_count = if content_is_empty {
+      input.placeholder.split('\n').count()
+    } else {
+      display_map.row_count()
+    };
+    let last_row = (((visible.bottom() - bounds.top()) / line_height)
+      .ceil()
+      .max(0.) as usize)
+      .min(row_count);
+    let first_row = (((visible.top() - bounds.top()) / line_height)
+      .floor()
+      .max(0.) as usize)
+      .min(last_row);
+    let shaped_lines = if content_is_empty {
+      Vec::new()
+    } else {
+      (first_row..last_row)
+        .map(|row| display_map.line_for_row(row))
+        .collect::<Vec<_>>()
+    };
+    let line_ranges = if content_is_empty {
+      input
+        .placeholder
+        .split('\n')
+        .scan(0, |start, line| {
+          let range = *start..*start + line.len();
+          *start = range.end + 1;
+          Some(range)
+        })
+        .skip(first_row)
+        .take(last_row - first_row)
+        .collect::<Vec<_>>()
+    } else {
+      shaped_lines
+        .iter()
+        .map(|&line| input.line_range(line))
+        .collect()
+    };
+
+    let line_texts = line_ranges
+      .iter()
+      .map(|range| {
+        if content_is_empty {
+          input.placeholder[range.clone()].to_string()
+        } else {
+          input.text(range.clone()).into_owned()
+        }
+      })
+      .collect::<Vec<_>>();
+
     let font_size = style.font_size.to_pixels(window.rem_size());
-    let line = window
-      .text_system()
-      .shape_line(display_text, font_size, &runs, None);
+    let lines = line_ranges
+      .iter()
+      .zip(&line_texts)
+      .map(|(range, text)| {
+        window.text_system().shape_line(
+          text.clone().into(),
+          font_size,
+          &runs_for_range(&runs, range.clone()),
+          None,
+        )
+      })
+      .collect();
+    let layout = TextLayout {
+      first_row,
+      lines,
+      line_starts: line_ranges.iter().map(|range| range.start).collect(),
+      end: line_ranges.last().map_or(0, |range| range.end),
+      line_height,
+    };
 
-    let cursor_pos = line.x_for_index(cursor);
-    let (selection, cursor) = if selected_range.is_empty() {
-      (
+    let settings = cx.global::<Settings>();
+    let shape = |text: &str, color: Hsla| {
+      window.text_system().shape_line(
+        text.to_string().into(),
+        font_size,
+        &[TextRun {
+          len: text.len(),
+          font: style.font(),
+          color,
+          background_color: None,
+          underline: None,
+          strikethrough: None,
+        }],
         None,
-        Some(fill(
+      )
+    };
+
+    let focused = input.focus_handle.is_focused(window);
+    let cursor_target = selected_range
+      .is_empty()
+      .then(|| layout.position_for_offset(cursor))
+      .flatten();
+    let cursor_position = cursor_target.map(|target| match &input.cursor_animation {
+      Some(animation) if settings.smooth_cursor => animation.position(),
+      _ => target,
+    });
+    // Block and underline cursors span the character after the cursor, or a
+    // space at the end of a line.
+    let cursor_width = layout
+      .line_for_offset(cursor)
+      .and_then(|row| {
+        let next = input
+          .char_after(cursor)
+          .filter(|&ch| ch != '\n' && ch != '\r')?;
+        let index = cursor - layout.line_starts[row];
+        let line = &layout.lines[row];
+        Some(line.x_for_index(index + next.len_utf8()) - line.x_for_index(index))
+      })
+      .unwrap_or_else(|| shape(" ", text_color).width);
+    let cursor = cursor_position.and_then(|position| {
+      let origin = bounds.origin + position;
+      let block = Bounds::new(origin, size(cursor_width, line_height));
+      if !focused {
+        return (!input.single_line).then(|| outline(block, gpui::blue(), BorderStyle::Solid));
+      }
+      if !input.cursor_visible {
+        return None;
+      }
+      Some(match settings.cursor_shape {
+        CursorShape::Bar => fill(Bounds::new(origin, size(px(2.), line_height)), gpui::blue()),
+        CursorShape::Block => fill(block, gpui::blue().opacity(0.5)),
+        CursorShape::Underline => fill(
           Bounds::new(
-            point(bounds.left() + cursor_pos, bounds.top()),
-            size(px(2.), bounds.bottom() - bounds.top()),
+            origin + point(px(0.), line_height - px(2.)),
+            size(cursor_width, px(2.)),
           ),
           gpui::blue(),
-        )),
-      )
+        ),
+      })
+    });
+    let current_line =
+      (settings.current_line_highlight && !input.single_line && selected_range.is_empty())
+        .then(|| layout.line_for_offset(selected_range.start))
+        .flatten()
+        .map(|row| {
+          let top = bounds.top() + line_height * (first_row + row) as f32;
+          fill(
+            Bounds::from_corners(
+              point(gutter.left(), top),
+              point(bounds.right(), top + line_height),
+            ),
+            rgba(0x0000000a),
+          )
+        });
+
+    let choice_popup = input.snippet_choices().and_then(|(choices, current)| {
+      let position = layout.position_for_offset(selected_range.start)?;
+      let origin = bounds.origin + point(position.x, position.y + line_height);
+      let lines = choices
+        .iter()
+        .map(|choice| shape(choice, style.color))
+        .collect::<Vec<_>>();
+      let width = lines
+        .iter()
+        .fold(px(0.), |width, line| width.max(line.width))
+        + px(16.);
+      let popup = Bounds::new(origin, size(width, line_height * lines.len() as f32));
+      let highlight = Bounds::new(
+        origin + point(px(0.), line_height * current as f32),
+        size(width, line_height),
+      );
+      let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(ix, line)| (origin + point(px(8.), line_height * ix as f32), line))
+        .collect();
+      Some(ChoicePopup {
+        background: fill(popup, rgb(0xffffff)),
+        border: outline(popup, rgb(0x999999), BorderStyle::Solid),
+        highlight: fill(highlight, rgb(0xbbccff)),
+        lines,
+      })
+    });
+
+    let selections = if selected_range.is_empty() {
+      Vec::new()
     } else {
-      (
+      // Clip the selection to the shaped lines.
+      let visible_range = layout.range();
+      let start = selected_range.start.max(visible_range.start);
+      let end = selected_range.end.min(visible_range.end);
+      let rows = (start <= end)
+        .then(|| Some((layout.line_for_offset(start)?, layout.line_for_offset(end)?)))
+        .flatten();
+      rows
+        .into_iter()
+        .flat_map(|(start_row, end_row)| start_row..=end_row)
+        .map(|row| {
+          let line = &layout.lines[row];
+          let start_x = if layout.line_starts[row] <= start {
+            line.x_for_index(start - layout.line_starts[row])
+          } else {
+            px(0.)
+          };
+          let end_x = if row + 1 == layout.lines.len() || layout.line_starts[row + 1] > end {
+            line.x_for_index(end - layout.line_starts[row])
+          } else {
+            line.width
+          };
+          let top = bounds.top() + line_height * (first_row + row) as f32;
+          fill(
+            Bounds::from_corners(
+              point(bounds.left() + start_x, top),
+              point(bounds.left() + end_x, top + line_height),
+            ),
+            rgba(0x3311ff30),
+          )
+        })
+        .collect()
+    };
+    let bracket_highlights = highlighted_brackets
+      .into_iter()
+      .flat_map(|(open, close)| [open, close])
+      .filter_map(|offset| {
+        let start = layout.position_for_offset(offset)?;
+        let end = layout.position_for_offset(offset + 1)?;
         Some(fill(
           Bounds::from_corners(
-            point(
-              bounds.left() + line.x_for_index(selected_range.start),
-              bounds.top(),
-            ),
-            point(
-              bounds.left() + line.x_for_index(selected_range.end),
-              bounds.bottom(),
-            ),
+            bounds.origin + start,
+            bounds.origin + point(end.x, start.y + line_height),
           ),
-          rgba(0x3311ff30),
-        )),
-        None,
-      )
-    };
+          rgba(0x00000018),
+        ))
+      })
+      .collect();
+    // Each diagnostic underlines the word at its position, or the character
+    // there when it isn't on a word.
+    let diagnostic_underlines = input
+      .diagnostics
+      .iter()
+      .filter(|diagnostic| {
+        diagnostic.line < input.line_count()
+          && !display_map.is_hidden(diagnostic.line)
+          && (first_row..last_row).contains(&display_map.row_for_line(diagnostic.line))
+      })
+      .filter_map(|diagnostic| {
+        let line_range = input.line_range(diagnostic.line);
+        let line_text = input.text(line_range.clone());
+        let mut start = input
+          .offset_for_line_column(diagnostic.line, diagnostic.column)
+          .min(line_range.end);
+        let mut end = line_text[start - line_range.start..]
+          .find(|c: char| !c.is_alphanumeric() && c != '_')
+          .map_or(line_range.end, |len| start + len);
+        if end == start {
+          match input.char_after(start).filter(|_| start < line_range.end) {
+            Some(c) => end = start + c.len_utf8(),
+            None => {
+              start -= input
+                .char_before(start)
+                .filter(|_| start > line_range.start)?
+                .len_utf8()
+            }
+          }
+        }
+        let start = layout.position_for_offset(start)?;
+        let end = layout.position_for_offset(end)?;
+        let color = match diagnostic.severity {
+          Severity::Error => rgb(0xe51400),
+          Severity::Warning => rgb(0xd9a000),
+          Severity::Note => rgb(0x3c78d8),
+        };
+        Some((
+          bounds.origin + point(start.x, start.y + line_height * 0.75),
+          end.x - start.x,
+          UnderlineStyle {
+            color: Some(color.into()),
+            thickness: px(1.),
+            wavy: true,
+          },
+        ))
+      })
+      .collect();
+
+    let mut fold_toggles = Vec::new();
+    let mut fold_placeholders = Vec::new();
+    for (row, &line) in shaped_lines.iter().enumerate() {
+      let top = bounds.top() + line_height * (first_row + row) as f32;
+      if input
+        .fold_regions
+        .binary_search_by_key(&line, |region| region.start_line)
+        .is_err()
+      {
+        continue;
+      }
+      let folded = input.is_folded(line);
+      fold_toggles.push((
+        point(gutter.left() + px(2.), top),
+        shape(
+          if folded { "\u{25B8}" } else { "\u{25BE}" },
+          hsla(0., 0., 0., 0.4),
+        ),
+      ));
+      if folded {
+        let placeholder = shape("\u{22EF}", hsla(0., 0., 0., 0.6));
+        let origin = point(bounds.left() + layout.lines[row].width + px(6.), top);
+        let background = fill(
+          Bounds::new(
+            origin - point(px(3.), px(-4.)),
+            size(placeholder.width + px(6.), line_height - px(8.)),
+          ),
+          rgba(0x0000001a),
+        );
+        fold_placeholders.push((origin, placeholder, background));
+      }
+    }
+
+    let tab_size = settings.tab_size;
+    let mut indent_guides = Vec::new();
+    if settings.indent_guides && !input.single_line && !shaped_lines.is_empty() {
+      // Blank lines take the shallower indentation of the lines around them
+      // so guides run through them.
+      let indents = line_texts
+        .iter()
+        .map(|text| (!text.trim().is_empty()).then(|| indent_columns(text, tab_size)))
+        .collect::<Vec<_>>();
+      let mut previous = Vec::with_capacity(indents.len());
+      let mut last = None;
+      for &indent in &indents {
+        last = indent.or(last);
+        previous.push(last);
+      }
+      let mut next = None;
+      let mut levels = vec![0; indents.len()];
+      for row in (0..indents.len()).rev() {
+        next = indents[row].or(next);
+        let columns = indents[row]
+          .or_else(|| Some(previous[row]?.min(next?)))
+          .unwrap_or(0);
+        levels[row] = columns / tab_size;
+      }
+
+      // The active block is the deepest one holding the cursor's line.
+      let cursor_line = input.line_column_for_offset(input.cursor_offset()).0;
+      let mut active = None;
+      if let Some(row) = shaped_lines.iter().position(|&line| line == cursor_line)
+        && levels[row] > 0
+      {
+        let level = levels[row];
+        let start = (0..row)
+          .rev()
+          .take_while(|&row| levels[row] >= level)
+          .last()
+          .unwrap_or(row);
+        let end = (row + 1..levels.len())
+          .take_while(|&row| levels[row] >= level)
+          .last()
+          .unwrap_or(row);
+        active = Some((level - 1, start..=end));
+      }
+
+      let space_width = shape(" ", text_color).width;
+      for (row, text) in line_texts.iter().enumerate() {
+        let top = bounds.top() + line_height * (first_row + row) as f32;
+        for level in 0..levels[row] {
+          let x = x_for_column(
+            &layout.lines[row],
+            text,
+            level * tab_size,
+            tab_size,
+            space_width,
+          );
+          let is_active = active
+            .as_ref()
+            .is_some_and(|(active, rows)| *active == level && rows.contains(&row));
+          indent_guides.push(fill(
+            Bounds::new(point(bounds.left() + x, top), size(px(1.), line_height)),
+            if is_active {
+              rgba(0x00000060)
+            } else {
+              rgba(0x00000020)
+            },
+          ));
+        }
+      }
+    }
+
+    let mut trailing_whitespace = Vec::new();
+    let mut whitespace_markers = Vec::new();
+    let mode = settings.render_whitespace;
+    if mode != RenderWhitespace::None && !input.single_line {
+      let dot = shape("\u{00B7}", hsla(0., 0., 0., 0.3));
+      let arrow = shape("\u{2192}", hsla(0., 0., 0., 0.3));
+      for (row, range) in line_ranges.iter().enumerate() {
+        let line = &layout.lines[row];
+        let text = &line_texts[row];
+        let top = bounds.top() + line_height * (first_row + row) as f32;
+        let trailing_start = text.trim_end_matches([' ', '\t']).len();
+        for (ix, ch) in text.char_indices() {
+          if ch != ' ' && ch != '\t' {
+            continue;
+          }
+          let shown = match mode {
+            RenderWhitespace::None => false,
+            RenderWhitespace::All => true,
+            RenderWhitespace::Selection => selected_range.contains(&(range.start + ix)),
+            RenderWhitespace::Boundary => is_boundary_whitespace(text, ix),
+          };
+          if !shown {
+            continue;
+          }
+          let start_x = line.x_for_index(ix);
+          let end_x = line.x_for_index(ix + 1);
+          if ix >= trailing_start {
+            trailing_whitespace.push(fill(
+              Bounds::from_corners(
+                point(bounds.left() + start_x, top),
+                point(bounds.left() + end_x, top + line_height),
+              ),
+              rgba(0xff000020),
+            ));
+          }
+          let marker = if ch == ' ' { &dot } else { &arrow };
+          let x = start_x + (end_x - start_x - marker.width).max(px(0.)) / 2.;
+          whitespace_markers.push((point(bounds.left() + x, top), marker.clone()));
+        }
+      }
+    }
+
     PrepaintState {
-      line: Some(line),
+      layout: Some(layout),
       cursor,
-      selection,
+      cursor_target,
+      cursor_position,
+      current_line,
+      choice_popup,
+      selections,
+      bracket_highlights,
+      diagnostic_underlines,
+      fold_toggles,
+      fold_placeholders,
+      indent_guides,
+      trailing_whitespace,
+      whitespace_markers,
+      text_bounds: bounds,
     }
   }
 
@@ -151,35 +751,130 @@ impl Element for TextElement {
     &mut self,
     _id: Option<&GlobalElementId>,
     _inspector_id: Option<&gpui::InspectorElementId>,
-    bounds: Bounds<Pixels>,
+    _bounds: Bounds<Pixels>,
     _request_layout: &mut Self::RequestLayoutState,
     prepaint: &mut Self::PrepaintState,
     window: &mut Window,
     cx: &mut App,
   ) {
+    let bounds = prepaint.text_bounds;
     let focus_handle = self.input.read(cx).focus_handle.clone();
     window.handle_input(
       &focus_handle,
       ElementInputHandler::new(bounds, self.input.clone()),
       cx,
     );
-    if let Some(selection) = prepaint.selection.take() {
+    if let Some(current_line) = prepaint.current_line.take() {
+      window.paint_quad(current_line)
+    }
+    for guide in prepaint.indent_guides.drain(..) {
+      window.paint_quad(guide)
+    }
+    for background in prepaint.trailing_whitespace.drain(..) {
+      window.paint_quad(background)
+    }
+    for highlight in prepaint.bracket_highlights.drain(..) {
+      window.paint_quad(highlight)
+    }
+    for selection in prepaint.selections.drain(..) {
       window.paint_quad(selection)
     }
-    let line = prepaint.line.take().unwrap();
-    line
-      .paint(bounds.origin, window.line_height(), window, cx)
-      .unwrap();
+    let layout = prepaint.layout.take().unwrap();
+    for (row, line) in layout.lines.iter().enumerate() {
+      line
+        .paint(
+          bounds.origin + point(px(0.), layout.line_height * (layout.first_row + row) as f32),
+          layout.line_height,
+          window,
+          cx,
+        )
+        .unwrap();
+    }
+    for (origin, marker) in &prepaint.whitespace_markers {
+      marker
+        .paint(*origin, layout.line_height, window, cx)
+        .unwrap();
+    }
+    for (origin, width, style) in prepaint.diagnostic_underlines.drain(..) {
+      window.paint_underline(origin, width, &style);
+    }
+    for (origin, toggle) in &prepaint.fold_toggles {
+      toggle
+        .paint(*origin, layout.line_height, window, cx)
+        .unwrap();
+    }
+    for (origin, placeholder, background) in prepaint.fold_placeholders.drain(..) {
+      window.paint_quad(background);
+      placeholder
+        .paint(origin, layout.line_height, window, cx)
+        .unwrap();
+    }
 
-    if focus_handle.is_focused(window)
-      && let Some(cursor) = prepaint.cursor.take()
-    {
+    if let Some(cursor) = prepaint.cursor.take() {
       window.paint_quad(cursor);
     }
+    if let Some(popup) = prepaint.choice_popup.take() {
+      window.paint_quad(popup.background);
+      window.paint_quad(popup.highlight);
+      for (origin, line) in popup.lines {
+        line.paint(origin, layout.line_height, window, cx).unwrap();
+      }
+      window.paint_quad(popup.border);
+    }
 
-    self.input.update(cx, |input, _cx| {
-      input.last_layout = Some(line);
+    let focused = focus_handle.is_focused(window);
+    let smooth_cursor = cx.global::<Settings>().smooth_cursor;
+    let animating = self.input.update(cx, |input, cx| {
+      input.last_layout = Some(layout);
       input.last_bounds = Some(bounds);
+      input.cursor_painted(focused, cx);
+      match prepaint.cursor_target {
+        Some(target) if smooth_cursor => {
+          if input
+            .cursor_animation
+            .as_ref()
+            .is_none_or(|animation| animation.to != target)
+          {
+            input.cursor_animation = Some(CursorAnimation {
+              from: prepaint.cursor_position.unwrap_or(target),
+              to: target,
+              start: Instant::now(),
+            });
+          }
+        }
+        _ => input.cursor_animation = None,
+      }
+      input
+        .cursor_animation
+        .as_ref()
+        .is_some_and(|animation| !animation.is_done())
     });
+    if animating {
+      window.request_animation_frame();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn boundary_whitespace() {
+    let text = "  a b  c\td ";
+    let shown = text
+      .char_indices()
+      .filter(|&(ix, ch)| (ch == ' ' || ch == '\t') && is_boundary_whitespace(text, ix))
+      .map(|(ix, _)| ix)
+      .collect::<Vec<_>>();
+    assert_eq!(shown, [0, 1, 5, 6, 8, 10]);
+  }
+
+  #[test]
+  fn boundary_whitespace_after_multibyte_characters() {
+    let text = "café au  lait 日本 語";
+    assert!(!is_boundary_whitespace(text, "café".len()));
+    assert!(is_boundary_whitespace(text, "café au".len()));
+    assert!(!is_boundary_whitespace(text, "café au  lait 日本".len()));
   }
 }
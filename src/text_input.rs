@@ -1,13 +1,42 @@
-use std::ops::Range;
+use std::{
+  borrow::Cow,
+  collections::HashSet,
+  fs,
+  io::{self, Read},
+  ops::Range,
+  path::{Path, PathBuf},
+  sync::atomic::{self, AtomicUsize},
+  time::Duration,
+};
 
 use gpui::{
-  App, Bounds, ClipboardItem, Context, CursorStyle, EntityInputHandler, FocusHandle, Focusable,
-  MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels, Point, ShapedLine,
-  SharedString, UTF16Selection, Window, actions, div, point, prelude::*, px, rgb, white,
+  App, AsyncApp, Bounds, ClipboardItem, Context, CursorStyle, EntityInputHandler, FocusHandle,
+  Focusable, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels, Point,
+  ScrollHandle, SharedString, Task, UTF16Selection, WeakEntity, Window, actions, div, point,
+  prelude::*, px, rgb, white,
 };
+use ropey::Rope;
 use unicode_segmentation::*;
 
-use crate::text_element::TextElement;
+use crate::{
+  brackets::BracketPairs,
+  case::{self, Case},
+  clipboard_history::{ClipboardEntry, ClipboardHistory},
+  comments::{self, LineComments},
+  diagnostics::Diagnostic,
+  encoding::Encoding,
+  folding::{self, DisplayMap, FoldRegion},
+  history::History,
+  kill_ring::KillRing,
+  language::Language,
+  line_ending::{LineEnding, lines_with_breaks},
+  minimap::{MINIMAP_WIDTH, MinimapElement, MinimapLayout},
+  recovery::Snapshot,
+  settings::Settings,
+  snippets::{self, ExpandOptions, SnippetSession, Snippets},
+  text_element::{CursorAnimation, TextElement, TextLayout},
+  watcher::{self, FileStamp},
+};
 
 actions!(
   text_input,
@@ -34,24 +63,483 @@ actions!(
     Cut,
     Copy,
     Quit,
+    Up,
+    Down,
+    KillLine,
+    KillRegion,
+    KillRingSave,
+    Yank,
+    YankPop,
+    SetMark,
+    ExchangePointAndMark,
+    Cancel,
+    SelectUp,
+    SelectDown,
+    Newline,
+    Indent,
+    Outdent,
+    JumpToMatchingBracket,
+    SelectInsideBrackets,
+    Undo,
+    Redo,
+    MoveLineUp,
+    MoveLineDown,
+    DuplicateLine,
+    DeleteLine,
+    JoinLines,
+    InsertLineAbove,
+    InsertLineBelow,
+    SortLines,
+    ReverseLines,
+    UniqueLines,
+    ToggleComment,
+    ToggleBlockComment,
+    ConvertToUpperCase,
+    ConvertToLowerCase,
+    ConvertToTitleCase,
+    ConvertToSnakeCase,
+    ConvertToCamelCase,
+    ConvertToKebabCase,
+    ConvertToPascalCase,
+    ConvertIndentationToSpaces,
+    ConvertIndentationToTabs,
+    TrimTrailingWhitespace,
+    ConvertLineEndingsToLf,
+    ConvertLineEndingsToCrlf,
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    FoldAtLevel1,
+    FoldAtLevel2,
+    FoldAtLevel3,
+    FoldAtLevel4,
+    FoldAtLevel5,
   ]
 );
 
+pub const LINE_HEIGHT: Pixels = px(30.);
+
+const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+static NEXT_BUFFER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes read per step of a load, between progress updates.
+const LOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// A file read by `load`, decoded off the main thread.
+struct LoadedFile {
+  text: Rope,
+  encoding: Encoding,
+  line_endings: (LineEnding, bool),
+  stamp: FileStamp,
+}
+
 pub struct TextInput {
   pub focus_handle: FocusHandle,
-  pub content: SharedString,
+  pub content: Rope,
   pub placeholder: SharedString,
   pub selected_range: Range<usize>,
   pub selection_reversed: bool,
   pub marked_range: Option<Range<usize>>,
-  pub last_layout: Option<ShapedLine>,
+  pub last_layout: Option<TextLayout>,
   pub last_bounds: Option<Bounds<Pixels>>,
   pub is_selecting: bool,
+  pub mark: Option<usize>,
+  pub kill_ring: KillRing,
+  pub language: &'static Language,
+  pub history: History,
+  /// Keeps the content on one line and lets Enter, Escape, Up and Down reach
+  /// the parent, for inputs embedded in dialogs.
+  pub single_line: bool,
+  pub scroll_handle: ScrollHandle,
+  /// Incremented on every change to the content.
+  pub version: usize,
+  /// The history's state when the content matched the file, if it ever did.
+  saved_state: Option<usize>,
+  pub path: Option<PathBuf>,
+  pub encoding: Encoding,
+  /// The line break inserted by Enter and paste.
+  pub line_ending: LineEnding,
+  /// Whether the loaded file mixed LF and CRLF. Saving keeps each break as is.
+  pub mixed_line_endings: bool,
+  /// Set for files above the large file threshold, which skip features that
+  /// scan the whole content.
+  pub large_file: bool,
+  /// The fraction of a large file read so far, while it loads.
+  pub loading: Option<f32>,
+  /// The file on disk as of the last load or save.
+  pub disk_stamp: Option<FileStamp>,
+  /// Set when the file changed on disk while the buffer had unsaved changes.
+  pub disk_conflict: bool,
+  pub deleted_on_disk: bool,
+  /// Identifies the buffer's crash recovery snapshot.
+  pub buffer_id: usize,
+  /// Problems reported for the file by the last task run.
+  pub diagnostics: Vec<Diagnostic>,
+  /// The folded ranges, each from the end of a fold's first line to the end
+  /// of its last hidden line. Folds may nest. Kept up to date by `splice`,
+  /// which drops those an edit touches.
+  folds: Vec<Range<usize>>,
+  /// The foldable regions, as of `fold_regions_version`.
+  pub fold_regions: Vec<FoldRegion>,
+  fold_regions_version: Option<usize>,
+  /// The matched bracket pairs, as of `bracket_pairs_version`.
+  bracket_pairs: BracketPairs,
+  bracket_pairs_version: Option<usize>,
+  pub last_minimap: Option<MinimapLayout>,
+  /// While the minimap's slider is dragged, how far below its top it was
+  /// grabbed.
+  minimap_drag: Option<Pixels>,
+  /// Whether a blinking cursor is currently shown.
+  pub cursor_visible: bool,
+  /// Bumped to cancel the pending blink when the cursor moves.
+  blink_epoch: usize,
+  /// The selection and version as of the last paint, to notice changes.
+  painted_state: (Range<usize>, usize),
+  /// Whether the editor was focused as of the last paint.
+  painted_focused: bool,
+  blink_task: Task<()>,
+  pub cursor_animation: Option<CursorAnimation>,
+  /// The snippet being filled in, whose tab stops follow edits.
+  snippet: Option<SnippetSession>,
+  /// The closers inserted by auto-close that haven't been edited since,
+  /// which typing the closer skips over and backspace deletes with their
+  /// opener.
+  auto_closed: Vec<Range<usize>>,
 }
 
 impl TextInput {
+  pub fn new(placeholder: impl Into<SharedString>, cx: &mut Context<Self>) -> Self {
+    Self {
+      focus_handle: cx.focus_handle(),
+      content: Rope::new(),
+      placeholder: placeholder.into(),
+      selected_range: 0..0,
+      selection_reversed: false,
+      marked_range: None,
+      last_layout: None,
+      last_bounds: None,
+      is_selecting: false,
+      mark: None,
+      kill_ring: Default::default(),
+      language: cx.global::<Settings>().language,
+      history: Default::default(),
+      single_line: false,
+      scroll_handle: ScrollHandle::new(),
+      version: 0,
+      saved_state: Some(0),
+      path: None,
+      encoding: Encoding::default(),
+      line_ending: LineEnding::default(),
+      mixed_line_endings: false,
+      large_file: false,
+      loading: None,
+      disk_stamp: None,
+      disk_conflict: false,
+      deleted_on_disk: false,
+      buffer_id: NEXT_BUFFER_ID.fetch_add(1, atomic::Ordering::Relaxed),
+      diagnostics: Vec::new(),
+      folds: Vec::new(),
+      fold_regions: Vec::new(),
+      fold_regions_version: None,
+      bracket_pairs: BracketPairs::default(),
+      bracket_pairs_version: None,
+      last_minimap: None,
+      minimap_drag: None,
+      cursor_visible: true,
+      blink_epoch: 0,
+      painted_state: (0..0, 0),
+      painted_focused: false,
+      blink_task: Task::ready(()),
+      cursor_animation: None,
+      snippet: None,
+      auto_closed: Vec::new(),
+    }
+  }
+
+  /// Restores a crash recovery snapshot as unsaved changes.
+  pub fn restore(&mut self, snapshot: Snapshot, cx: &mut Context<Self>) {
+    self.disk_stamp = snapshot
+      .path
+      .as_deref()
+      .and_then(|path| FileStamp::read(path).ok());
+    if let Some(path) = snapshot.path {
+      self.set_path(path, cx);
+    }
+    self.encoding = snapshot.encoding;
+    self.line_ending = snapshot.line_ending;
+    self.mixed_line_endings = LineEnding::detect(&snapshot.text).1;
+    self.splice(0..self.content.len_bytes(), &snapshot.text);
+    self.saved_state = None;
+    self.restore_position(snapshot.cursor, self.scroll_handle.offset().y, cx);
+  }
+
+  /// Puts the cursor and scroll position back where a previous session left
+  /// them, as far as the content allows.
+  pub fn restore_position(&mut self, cursor: usize, scroll_y: Pixels, cx: &mut Context<Self>) {
+    let cursor = cursor.min(self.content.len_bytes());
+    let cursor = self.content.char_to_byte(self.content.byte_to_char(cursor));
+    self.selected_range = cursor..cursor;
+    self.selection_reversed = false;
+    self.scroll_handle.set_offset(point(px(0.), scroll_y));
+    cx.notify();
+  }
+
+  /// Replaces the content with the file at `path`, keeping its line endings.
+  /// The file is read in the background, showing progress if it is large.
+  /// The encoding is detected unless one is given. Leaves the content
+  /// untouched when the file can't be decoded.
+  pub fn load(
+    &mut self,
+    path: &Path,
+    encoding: Option<Encoding>,
+    cx: &mut Context<Self>,
+  ) -> Task<gpui::Result<()>> {
+    let path = path.to_path_buf();
+    let threshold = cx.global::<Settings>().large_file_threshold;
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > threshold) {
+      self.loading = Some(0.);
+      cx.notify();
+    }
+
+    cx.spawn(async move |this, cx| {
+      let result = Self::read_file(&path, encoding, &this, cx).await;
+      this.update(cx, |this, cx| {
+        this.loading = None;
+        cx.notify();
+        let file = result?;
+        this.large_file = file.text.len_bytes() as u64 > threshold;
+        this.set_file(path, file, cx);
+        Ok(())
+      })?
+    })
+  }
+
+  async fn read_file(
+    path: &Path,
+    encoding: Option<Encoding>,
+    this: &WeakEntity<Self>,
+    cx: &mut AsyncApp,
+  ) -> gpui::Result<LoadedFile> {
+    let mut file = fs::File::open(path)?;
+    let metadata = file.metadata()?;
+    let stamp = FileStamp::new(&metadata);
+    let len = metadata.len();
+    let mut bytes = Vec::with_capacity(len as usize);
+    loop {
+      let read;
+      (file, bytes, read) = cx
+        .background_spawn(async move {
+          let read = (&mut file).take(LOAD_CHUNK_SIZE).read_to_end(&mut bytes);
+          (file, bytes, read)
+        })
+        .await;
+      if read? == 0 {
+        break;
+      }
+      let progress = bytes.len() as f32 / len.max(1) as f32;
+      this.update(cx, |this, cx| {
+        if this.loading.is_some() {
+          this.loading = Some(progress.min(1.));
+          cx.notify();
+        }
+      })?;
+    }
+
+    cx.background_spawn(async move {
+      let encoding = encoding
+        .or_else(|| Encoding::detect(&bytes))
+        .ok_or_else(|| {
+          io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The file looks like binary data rather than text.",
+          )
+        })?;
+      let text = encoding.decode(bytes)?;
+      Ok(LoadedFile {
+        line_endings: LineEnding::detect(&text),
+        text: Rope::from_str(&text),
+        encoding,
+        stamp,
+      })
+    })
+    .await
+  }
+
+  fn set_file(&mut self, path: PathBuf, file: LoadedFile, cx: &App) {
+    self.disk_stamp = Some(file.stamp);
+    self.disk_conflict = false;
+    self.deleted_on_disk = false;
+    (self.line_ending, self.mixed_line_endings) = file.line_endings;
+    self.encoding = file.encoding;
+    self.content = file.text;
+    self.folds.clear();
+    self.snippet = None;
+    self.auto_closed.clear();
+    self.version += 1;
+    self.set_path(path, cx);
+    self.history = Default::default();
+    self.saved_state = Some(self.history.state());
+    self.selected_range = 0..0;
+    self.selection_reversed = false;
+    self.marked_range = None;
+    self.mark = None;
+    self.scroll_handle.set_offset(point(px(0.), px(0.)));
+  }
+
+  /// Points the buffer at the file `path`. A different file switches to the
+  /// language for its extension, and losing an extension that chose the
+  /// language goes back to the default one.
+  pub fn set_path(&mut self, path: PathBuf, cx: &App) {
+    if self.path.as_ref() == Some(&path) {
+      return;
+    }
+    match (
+      Language::for_path(&path),
+      self.path.as_deref().and_then(Language::for_path),
+    ) {
+      (Some(language), _) => self.set_language(language),
+      (None, Some(_)) => self.set_language(cx.global::<Settings>().language),
+      (None, None) => {}
+    }
+    self.path = Some(path);
+  }
+
+  /// Writes the content to `path` in `encoding`, which become the buffer's
+  /// path and encoding. Nothing is written when the content can't be encoded.
+  pub fn save(
+    &mut self,
+    path: &Path,
+    encoding: Encoding,
+    cx: &mut Context<Self>,
+  ) -> std::io::Result<()> {
+    fs::write(path, encoding.encode(self.content.chunks())?)?;
+    self.encoding = encoding;
+    self.set_path(path.to_path_buf(), cx);
+    self.saved_state = Some(self.history.state());
+    self.disk_stamp = FileStamp::read(path).ok();
+    self.disk_conflict = false;
+    self.deleted_on_disk = false;
+    cx.notify();
+    Ok(())
+  }
+
+  /// Loads the file again, keeping the cursor's line and column and the
+  /// scroll position.
+  pub fn reload(&mut self, cx: &mut Context<Self>) -> Task<gpui::Result<()>> {
+    let Some(path) = self.path.clone() else {
+      return Task::ready(Ok(()));
+    };
+    let (line, column) = self.line_column_for_offset(self.cursor_offset());
+    let scroll = self.scroll_handle.offset();
+    let load = self.load(&path, Some(self.encoding), cx);
+    cx.spawn(async move |this, cx| {
+      load.await?;
+      this.update(cx, |this, cx| {
+        let offset = this.offset_for_line_column(line, column);
+        this.selected_range = offset..offset;
+        this.scroll_handle.set_offset(scroll);
+        cx.notify();
+      })
+    })
+  }
+
+  /// Compares the file on disk with the one last loaded or saved. A clean
+  /// buffer reloads when the file changed, a dirty one flags a conflict.
+  /// Deleted files are looked for under a new name before being marked as
+  /// deleted.
+  pub fn check_disk(&mut self, cx: &mut Context<Self>) {
+    let (Some(path), Some(stamp)) = (self.path.clone(), self.disk_stamp.clone()) else {
+      return;
+    };
+    if self.loading.is_some() || self.disk_conflict {
+      return;
+    }
+
+    match FileStamp::read(&path) {
+      Ok(current) => {
+        if self.deleted_on_disk {
+          self.deleted_on_disk = false;
+          cx.notify();
+        }
+        if current == stamp {
+          return;
+        }
+        if self.is_dirty() {
+          self.disk_conflict = true;
+          cx.notify();
+          return;
+        }
+        let reload = self.reload(cx);
+        cx.spawn(async move |this, cx| {
+          if reload.await.is_err() {
+            this
+              .update(cx, |this, cx| {
+                this.disk_conflict = true;
+                cx.notify();
+              })
+              .ok();
+          }
+        })
+        .detach();
+      }
+      Err(_) if !self.deleted_on_disk => {
+        match watcher::find_renamed(&path, &stamp) {
+          Some(renamed) => self.set_path(renamed, cx),
+          None => self.deleted_on_disk = true,
+        }
+        cx.notify();
+      }
+      Err(_) => {}
+    }
+  }
+
+  /// Resolves a conflict with the disk by keeping the buffer's content, which
+  /// the next save writes over the file.
+  pub fn keep_local_changes(&mut self, cx: &mut Context<Self>) {
+    if let Some(path) = self.path.as_ref()
+      && let Ok(stamp) = FileStamp::read(path)
+    {
+      self.disk_stamp = Some(stamp);
+    }
+    self.disk_conflict = false;
+    cx.notify();
+  }
+
+  /// The file name, for tabs.
+  pub fn title(&self) -> SharedString {
+    self
+      .path
+      .as_deref()
+      .and_then(Path::file_name)
+      .map_or("untitled".into(), |name| {
+        name.to_string_lossy().into_owned().into()
+      })
+  }
+
+  /// Rewrites every line break as `line_ending`, keeping the cursor on its
+  /// line and column.
+  pub fn convert_line_endings(&mut self, line_ending: LineEnding, cx: &mut Context<Self>) {
+    self.line_ending = line_ending;
+    self.mixed_line_endings = false;
+    let text = line_ending.normalize(&self.content.to_string());
+    if self.content == text {
+      cx.notify();
+      return;
+    }
+    let (line, column) = self.line_column_for_offset(self.cursor_offset());
+    self.edit(0..self.content.len_bytes(), &text, cx);
+    self.move_to(self.offset_for_line_column(line, column), cx);
+  }
+
+  /// Whether the content differs from the file, as far as the history
+  /// tells. Undoing back to where the file was saved makes it clean again.
+  pub fn is_dirty(&self) -> bool {
+    self.saved_state != Some(self.history.state())
+  }
+
   fn left(&mut self, _: &Left, _: &mut Window, cx: &mut Context<Self>) {
-    if self.selected_range.is_empty() {
+    if self.selected_range.is_empty() || self.mark.is_some() {
       self.move_to(self.previous_boundary(self.cursor_offset()), cx);
     } else {
       self.move_to(self.selected_range.start, cx)
@@ -59,185 +547,1589 @@ impl TextInput {
   }
 
   fn alt_left(&mut self, _: &AltLeft, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(self.previous_word_boundary(self.selected_range.start), cx);
+    let offset = if self.mark.is_some() {
+      self.cursor_offset()
+    } else {
+      self.selected_range.start
+    };
+    self.move_to(self.previous_word_boundary(offset), cx);
   }
 
   fn cmd_left(&mut self, _: &CmdLeft, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(0, cx);
+    self.move_to(self.line_start(self.cursor_offset()), cx);
   }
 
   fn right(&mut self, _: &Right, _: &mut Window, cx: &mut Context<Self>) {
-    if self.selected_range.is_empty() {
-      self.move_to(self.next_boundary(self.selected_range.end), cx);
+    if self.selected_range.is_empty() || self.mark.is_some() {
+      self.move_to(self.next_boundary(self.cursor_offset()), cx);
     } else {
       self.move_to(self.selected_range.end, cx)
     }
   }
 
-  fn alt_right(&mut self, _: &AltRight, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(self.next_word_boundary(self.selected_range.end), cx);
+  fn alt_right(&mut self, _: &AltRight, _: &mut Window, cx: &mut Context<Self>) {
+    let offset = if self.mark.is_some() {
+      self.cursor_offset()
+    } else {
+      self.selected_range.end
+    };
+    self.move_to(self.next_word_boundary(offset), cx);
+  }
+
+  fn cmd_right(&mut self, _: &CmdRight, _: &mut Window, cx: &mut Context<Self>) {
+    self.move_to(self.line_end(self.cursor_offset()), cx);
+  }
+
+  fn up(&mut self, _: &Up, _: &mut Window, cx: &mut Context<Self>) {
+    if self.single_line {
+      cx.propagate();
+      return;
+    }
+    if self.cycle_snippet_choice(-1, cx) {
+      return;
+    }
+    self.move_to(self.offset_above(self.cursor_offset()), cx);
+  }
+
+  fn down(&mut self, _: &Down, _: &mut Window, cx: &mut Context<Self>) {
+    if self.single_line {
+      cx.propagate();
+      return;
+    }
+    if self.cycle_snippet_choice(1, cx) {
+      return;
+    }
+    self.move_to(self.offset_below(self.cursor_offset()), cx);
+  }
+
+  fn select_up(&mut self, _: &SelectUp, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.offset_above(self.cursor_offset()), cx);
+  }
+
+  fn select_down(&mut self, _: &SelectDown, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.offset_below(self.cursor_offset()), cx);
+  }
+
+  fn select_left(&mut self, _: &SelectLeft, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.previous_boundary(self.cursor_offset()), cx);
+  }
+
+  fn select_word_left(&mut self, _: &SelectWordLeft, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.previous_word_boundary(self.cursor_offset()), cx);
+  }
+
+  fn select_start(&mut self, _: &SelectStart, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.line_start(self.cursor_offset()), cx);
+  }
+
+  fn select_right(&mut self, _: &SelectRight, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.next_boundary(self.cursor_offset()), cx);
+  }
+
+  fn select_word_right(&mut self, _: &SelectWordRight, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.next_word_boundary(self.cursor_offset()), cx);
+  }
+
+  fn select_end(&mut self, _: &SelectEnd, _: &mut Window, cx: &mut Context<Self>) {
+    self.select_to(self.line_end(self.cursor_offset()), cx);
+  }
+
+  fn select_all(&mut self, _: &SelectAll, _: &mut Window, cx: &mut Context<Self>) {
+    self.move_to(0, cx);
+    // Selected directly, as the end of the content may be folded away.
+    self.selected_range = 0..self.content.len_bytes();
+    self.selection_reversed = false;
+    cx.notify();
+  }
+
+  fn home(&mut self, _: &Home, _: &mut Window, cx: &mut Context<Self>) {
+    self.move_to(self.line_start(self.cursor_offset()), cx);
+  }
+
+  fn end(&mut self, _: &End, _: &mut Window, cx: &mut Context<Self>) {
+    self.move_to(self.line_end(self.cursor_offset()), cx);
+  }
+
+  fn backspace(&mut self, _: &Backspace, window: &mut Window, cx: &mut Context<Self>) {
+    if self.selected_range.is_empty() {
+      let cursor = self.cursor_offset();
+      let before = self.char_before(cursor);
+      let after = self.char_after(cursor);
+      // Deleting the opener of an empty auto-closed pair removes its closer too.
+      if let (Some(open), Some(close)) = (before, after)
+        && self.language.auto_close_pair(open) == Some(close)
+        && self.is_auto_closed(cursor, close)
+      {
+        self.edit(cursor - open.len_utf8()..cursor + close.len_utf8(), "", cx);
+        return;
+      }
+      self.select_to(self.previous_boundary(cursor), cx)
+    }
+    self.replace_text_in_range(None, "", window, cx)
+  }
+
+  fn delete(&mut self, _: &Delete, window: &mut Window, cx: &mut Context<Self>) {
+    if self.selected_range.is_empty() {
+      self.select_to(self.next_boundary(self.cursor_offset()), cx)
+    }
+    self.replace_text_in_range(None, "", window, cx)
+  }
+
+  fn newline(&mut self, _: &Newline, _: &mut Window, cx: &mut Context<Self>) {
+    if self.single_line {
+      cx.propagate();
+      return;
+    }
+
+    let mut start = self.selected_range.start;
+    let line_start = self.line_start(start);
+    let before = &self.text(line_start..start);
+    let indent = leading_whitespace(before).to_string();
+    let end = self.selected_range.end;
+    let after = &self.text(end..self.line_end(end));
+    let (trimmed_before, trimmed_after) = whitespace_around_break(before, after);
+    start -= trimmed_before;
+    let end = end + trimmed_after;
+
+    let newline = self.line_ending.as_str();
+    let mut text = format!("{newline}{indent}");
+    if self.language.opens_block(before) {
+      text.push_str(&cx.global::<Settings>().indent_unit());
+
+      // Typing Enter inside an empty pair moves the closer to its own line.
+      let open = before.trim_end().chars().last();
+      let close = self.char_after(end);
+      if let (Some(open), Some(close)) = (open, close)
+        && self.language.closing_bracket(open) == Some(close)
+      {
+        let cursor = start + text.len();
+        text.push_str(&format!("{newline}{indent}"));
+        self.edit(start..end, &text, cx);
+        self.move_to(cursor, cx);
+        return;
+      }
+    }
+    self.edit(start..end, &text, cx);
+  }
+
+  fn indent(&mut self, _: &Indent, window: &mut Window, cx: &mut Context<Self>) {
+    self.end_snippet_outside_stops();
+    if let Some(session) = &self.snippet {
+      self.select_tab_stop(session.active + 1, cx);
+      return;
+    }
+    if self.expand_snippet_prefix(cx) {
+      return;
+    }
+    let unit = cx.global::<Settings>().indent_unit();
+    if self.selected_range.is_empty() {
+      self.replace_text_in_range(None, &unit, window, cx);
+    } else {
+      self.edit_selected_lines(
+        |line| {
+          if line.is_empty() {
+            line.to_string()
+          } else {
+            format!("{unit}{line}")
+          }
+        },
+        cx,
+      );
+    }
+  }
+
+  fn outdent(&mut self, _: &Outdent, _: &mut Window, cx: &mut Context<Self>) {
+    self.end_snippet_outside_stops();
+    if let Some(session) = &self.snippet {
+      self.select_tab_stop(session.active.saturating_sub(1), cx);
+      return;
+    }
+    let tab_size = cx.global::<Settings>().tab_size;
+    self.edit_selected_lines(
+      |line| {
+        let removed = if line.starts_with('\t') {
+          1
+        } else {
+          line.len() - line.trim_start_matches(' ').len()
+        };
+        line[removed.min(tab_size)..].to_string()
+      },
+      cx,
+    );
+  }
+
+  fn jump_to_matching_bracket(
+    &mut self,
+    _: &JumpToMatchingBracket,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let cursor = self.cursor_offset();
+    self.refresh_bracket_pairs();
+    let Some((open, close)) = self.bracket_pairs.pair_for_cursor(cursor) else {
+      return;
+    };
+    if cursor <= open + 1 {
+      self.move_to(close, cx);
+    } else {
+      self.move_to(open, cx);
+    }
+  }
+
+  /// Selects the contents of the innermost enclosing pair, then the pair
+  /// itself, then the contents of the next pair out on repeated use.
+  fn select_inside_brackets(
+    &mut self,
+    _: &SelectInsideBrackets,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.large_file {
+      return;
+    }
+    self.refresh_bracket_pairs();
+    let Some((open, close)) = self.bracket_pairs.enclosing_pair(self.selected_range.start) else {
+      return;
+    };
+    self.selected_range = if self.selected_range == (open + 1..close) {
+      open..close + 1
+    } else {
+      open + 1..close
+    };
+    self.selection_reversed = false;
+    cx.notify();
+  }
+
+  fn undo(&mut self, _: &Undo, _: &mut Window, cx: &mut Context<Self>) {
+    let entries = self.history.undo();
+    let Some(first) = entries.last() else {
+      return;
+    };
+    for entry in &entries {
+      self.splice(entry.new_range(), &entry.old_text);
+    }
+    self.selected_range = first.selection.clone();
+    self.selection_reversed = first.selection_reversed;
+    self.snippet = None;
+    self.marked_range = None;
+    self.mark = None;
+    self.scroll_to_cursor();
+    cx.notify();
+  }
+
+  fn redo(&mut self, _: &Redo, _: &mut Window, cx: &mut Context<Self>) {
+    let entries = self.history.redo();
+    let Some(first) = entries.first() else {
+      return;
+    };
+    // The cursor goes after the step's first edit, wherever the edits
+    // joined to it moved that.
+    let mut end = first.new_range().end;
+    for (ix, entry) in entries.iter().enumerate() {
+      self.splice(entry.old_range(), &entry.new_text);
+      if ix > 0 && entry.old_range().end <= end {
+        end = end - entry.old_text.len() + entry.new_text.len();
+      }
+    }
+    self.selected_range = end..end;
+    self.selection_reversed = false;
+    self.snippet = None;
+    self.marked_range = None;
+    self.mark = None;
+    self.scroll_to_cursor();
+    cx.notify();
+  }
+
+  fn move_line_up(&mut self, _: &MoveLineUp, _: &mut Window, cx: &mut Context<Self>) {
+    let lines = self.selected_lines_range();
+    if lines.start == 0 {
+      return;
+    }
+    let above = self.line_start(lines.start - 1)..self.line_end(lines.start - 1);
+    let text = format!(
+      "{}{}{}",
+      &self.text(lines.clone()),
+      &self.text(above.end..lines.start),
+      &self.text(above.clone())
+    );
+    let shift = lines.start - above.start;
+    let selection = self.selected_range.start - shift..self.selected_range.end - shift;
+    self.edit_and_select(above.start..lines.end, &text, selection, cx);
+  }
+
+  fn move_line_down(&mut self, _: &MoveLineDown, _: &mut Window, cx: &mut Context<Self>) {
+    let lines = self.selected_lines_range();
+    if lines.end == self.content.len_bytes() {
+      return;
+    }
+    let below_start = self.next_line_start(lines.end);
+    let below = below_start..self.line_end(below_start);
+    let text = format!(
+      "{}{}{}",
+      &self.text(below.clone()),
+      &self.text(lines.end..below.start),
+      &self.text(lines.clone())
+    );
+    let shift = below.end - lines.end;
+    let selection = self.selected_range.start + shift..self.selected_range.end + shift;
+    self.edit_and_select(lines.start..below.end, &text, selection, cx);
+  }
+
+  fn duplicate_line(&mut self, _: &DuplicateLine, _: &mut Window, cx: &mut Context<Self>) {
+    let lines = self.selected_lines_range();
+    let newline = self.line_ending.as_str();
+    let text = format!("{0}{newline}{0}", &self.text(lines.clone()));
+    let shift = lines.len() + newline.len();
+    let selection = self.selected_range.start + shift..self.selected_range.end + shift;
+    self.edit_and_select(lines, &text, selection, cx);
+  }
+
+  fn delete_line(&mut self, _: &DeleteLine, _: &mut Window, cx: &mut Context<Self>) {
+    let lines = self.selected_lines_range();
+    let range = if lines.end < self.content.len_bytes() {
+      lines.start..self.next_line_start(lines.end)
+    } else if lines.start > 0 {
+      self.line_end(lines.start - 1)..lines.end
+    } else {
+      lines
+    };
+    self.edit(range, "", cx);
+  }
+
+  fn join_lines(&mut self, _: &JoinLines, _: &mut Window, cx: &mut Context<Self>) {
+    let mut lines = self.selected_lines_range();
+    if !self.text(lines.clone()).contains('\n') {
+      if lines.end == self.content.len_bytes() {
+        return;
+      }
+      lines.end = self.line_end(self.next_line_start(lines.end));
+    }
+
+    let mut joined = String::new();
+    let mut cursor = 0;
+    for (ix, line) in self.text(lines.clone()).split('\n').enumerate() {
+      if ix == 0 {
+        joined.push_str(line);
+        continue;
+      }
+      let line = line.trim_start();
+      joined.truncate(joined.trim_end().len());
+      cursor = joined.len();
+      if !line.is_empty() && !joined.is_empty() {
+        joined.push(' ');
+      }
+      joined.push_str(line);
+    }
+    self.edit(lines.clone(), &joined, cx);
+    self.move_to(lines.start + cursor, cx);
+  }
+
+  fn insert_line_above(&mut self, _: &InsertLineAbove, _: &mut Window, cx: &mut Context<Self>) {
+    let line_start = self.line_start(self.cursor_offset());
+    let indent = leading_whitespace(&self.text(line_start..self.line_end(line_start))).to_string();
+    let newline = self.line_ending.as_str();
+    self.edit(line_start..line_start, &format!("{indent}{newline}"), cx);
+    self.move_to(line_start + indent.len(), cx);
+  }
+
+  fn insert_line_below(&mut self, _: &InsertLineBelow, _: &mut Window, cx: &mut Context<Self>) {
+    let cursor = self.cursor_offset();
+    let indent =
+      leading_whitespace(&self.text(self.line_start(cursor)..self.line_end(cursor))).to_string();
+    let line_end = self.line_end(cursor);
+    let newline = self.line_ending.as_str();
+    self.edit(line_end..line_end, &format!("{newline}{indent}"), cx);
+  }
+
+  fn sort_lines(&mut self, _: &SortLines, _: &mut Window, cx: &mut Context<Self>) {
+    self.rewrite_selected_lines(
+      |mut lines| {
+        lines.sort();
+        lines
+      },
+      cx,
+    );
+  }
+
+  fn reverse_lines(&mut self, _: &ReverseLines, _: &mut Window, cx: &mut Context<Self>) {
+    self.rewrite_selected_lines(
+      |mut lines| {
+        lines.reverse();
+        lines
+      },
+      cx,
+    );
+  }
+
+  fn unique_lines(&mut self, _: &UniqueLines, _: &mut Window, cx: &mut Context<Self>) {
+    self.rewrite_selected_lines(
+      |lines| {
+        let mut seen = HashSet::new();
+        lines
+          .into_iter()
+          .filter(|line| seen.insert(*line))
+          .collect()
+      },
+      cx,
+    );
+  }
+
+  /// Comments out the selected lines at their minimum indentation, or
+  /// uncomments them when every non-blank line is already commented.
+  fn toggle_comment(&mut self, _: &ToggleComment, window: &mut Window, cx: &mut Context<Self>) {
+    let Some(token) = self.language.line_comment else {
+      self.toggle_block_comment(&ToggleBlockComment, window, cx);
+      return;
+    };
+
+    let lines = self.selected_lines_range();
+    let Some(comments) = LineComments::new(&self.text(lines), token) else {
+      return;
+    };
+    self.edit_selected_lines(|line| comments.toggle(line), cx);
+  }
+
+  /// Wraps the selection in the language's block comment delimiters, or
+  /// removes them when the selection is already wrapped.
+  fn toggle_block_comment(
+    &mut self,
+    _: &ToggleBlockComment,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let Some((open, close)) = self.language.block_comment else {
+      return;
+    };
+    let offset = self.selected_range.start;
+    let selected = self.text(self.selected_range.clone());
+    let (range, text, selection) = comments::toggle_block_comment(&selected, open, close);
+    let start = offset + range.start;
+    self.edit_and_select(
+      start..offset + range.end,
+      &text,
+      start + selection.start..start + selection.end,
+      cx,
+    );
+  }
+
+  fn convert_to_upper_case(
+    &mut self,
+    _: &ConvertToUpperCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Upper, cx);
+  }
+
+  fn convert_to_lower_case(
+    &mut self,
+    _: &ConvertToLowerCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Lower, cx);
+  }
+
+  fn convert_to_title_case(
+    &mut self,
+    _: &ConvertToTitleCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Title, cx);
+  }
+
+  fn convert_to_snake_case(
+    &mut self,
+    _: &ConvertToSnakeCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Snake, cx);
+  }
+
+  fn convert_to_camel_case(
+    &mut self,
+    _: &ConvertToCamelCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Camel, cx);
+  }
+
+  fn convert_to_kebab_case(
+    &mut self,
+    _: &ConvertToKebabCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Kebab, cx);
+  }
+
+  fn convert_to_pascal_case(
+    &mut self,
+    _: &ConvertToPascalCase,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_case(Case::Pascal, cx);
+  }
+
+  fn convert_indentation_to_spaces(
+    &mut self,
+    _: &ConvertIndentationToSpaces,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let tab_size = cx.global::<Settings>().tab_size;
+    self.edit_lines(
+      0..self.content.len_bytes(),
+      true,
+      |line| {
+        let indent = leading_whitespace(line);
+        let columns = indent_columns(indent, tab_size);
+        format!("{}{}", " ".repeat(columns), &line[indent.len()..])
+      },
+      cx,
+    );
+  }
+
+  fn convert_indentation_to_tabs(
+    &mut self,
+    _: &ConvertIndentationToTabs,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let tab_size = cx.global::<Settings>().tab_size;
+    self.edit_lines(
+      0..self.content.len_bytes(),
+      true,
+      |line| {
+        let indent = leading_whitespace(line);
+        let columns = indent_columns(indent, tab_size);
+        format!(
+          "{}{}{}",
+          "\t".repeat(columns / tab_size),
+          " ".repeat(columns % tab_size),
+          &line[indent.len()..]
+        )
+      },
+      cx,
+    );
+  }
+
+  fn trim_trailing_whitespace(
+    &mut self,
+    _: &TrimTrailingWhitespace,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.edit_lines(
+      0..self.content.len_bytes(),
+      false,
+      |line| line.trim_end().to_string(),
+      cx,
+    );
+  }
+
+  fn convert_line_endings_to_lf(
+    &mut self,
+    _: &ConvertLineEndingsToLf,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_line_endings(LineEnding::Lf, cx);
+  }
+
+  fn convert_line_endings_to_crlf(
+    &mut self,
+    _: &ConvertLineEndingsToCrlf,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.convert_line_endings(LineEnding::Crlf, cx);
+  }
+
+  fn kill_line(&mut self, _: &KillLine, window: &mut Window, cx: &mut Context<Self>) {
+    let cursor = self.cursor_offset();
+    let line_end = self.line_end(cursor);
+    let end = if line_end == cursor {
+      self.next_boundary(cursor)
+    } else {
+      line_end
+    };
+    if end == cursor {
+      return;
+    }
+
+    let text = self.text(cursor..end).to_string();
+    self.mark = None;
+    self.selected_range = cursor..end;
+    self.selection_reversed = false;
+    self.replace_text_in_range(None, "", window, cx);
+    self.kill_ring.kill(&text, cursor);
+    if let Some(killed) = self.kill_ring.current() {
+      cx.write_to_clipboard(ClipboardItem::new_string(killed.to_string()));
+    }
+  }
+
+  fn kill_region(&mut self, _: &KillRegion, window: &mut Window, cx: &mut Context<Self>) {
+    if !self.selected_range.is_empty() {
+      self.copy_selection(cx);
+      self
+        .kill_ring
+        .push(self.text(self.selected_range.clone()).to_string());
+      self.replace_text_in_range(None, "", window, cx)
+    }
+  }
+
+  fn kill_ring_save(&mut self, _: &KillRingSave, _: &mut Window, cx: &mut Context<Self>) {
+    if !self.selected_range.is_empty() {
+      self.copy_selection(cx);
+      self
+        .kill_ring
+        .push(self.text(self.selected_range.clone()).to_string());
+    }
+    self.mark = None;
+    self.move_to(self.cursor_offset(), cx);
+  }
+
+  fn yank(&mut self, _: &Yank, window: &mut Window, cx: &mut Context<Self>) {
+    // Text copied in other applications takes precedence over older kills.
+    if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text())
+      && self.kill_ring.current() != Some(text.as_str())
+    {
+      self.kill_ring.push(text);
+    }
+    let Some(text) = self.kill_ring.start_yank().map(str::to_string) else {
+      return;
+    };
+
+    self.mark = None;
+    let start = self.selected_range.start;
+    self.replace_text_in_range(None, &text, window, cx);
+    self.kill_ring.last_yank = Some(start..start + text.len());
+  }
+
+  fn yank_pop(&mut self, _: &YankPop, window: &mut Window, cx: &mut Context<Self>) {
+    let Some(range) = self.kill_ring.last_yank.clone() else {
+      return;
+    };
+    let Some(text) = self.kill_ring.rotate().map(str::to_string) else {
+      return;
+    };
+
+    self.selected_range = range.clone();
+    self.replace_text_in_range(None, &text, window, cx);
+    self.kill_ring.last_yank = Some(range.start..range.start + text.len());
+  }
+
+  fn set_mark(&mut self, _: &SetMark, _: &mut Window, cx: &mut Context<Self>) {
+    let cursor = self.cursor_offset();
+    self.mark = Some(cursor);
+    self.move_to(cursor, cx);
+  }
+
+  fn exchange_point_and_mark(
+    &mut self,
+    _: &ExchangePointAndMark,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if let Some(mark) = self.mark {
+      self.mark = Some(self.cursor_offset());
+      self.move_to(mark, cx);
+    }
+  }
+
+  fn cancel(&mut self, _: &Cancel, _: &mut Window, cx: &mut Context<Self>) {
+    if self.snippet.take().is_some() {
+      cx.notify();
+      return;
+    }
+    // With nothing to cancel here, let an enclosing dialog close instead.
+    if self.mark.is_none() && self.selected_range.is_empty() {
+      cx.propagate();
+      return;
+    }
+    self.mark = None;
+    self.move_to(self.cursor_offset(), cx);
+  }
+
+  fn on_mouse_down(
+    &mut self,
+    event: &MouseDownEvent,
+    _window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if let (Some(bounds), Some(layout)) = (self.last_bounds, self.last_layout.as_ref())
+      && event.position.x < bounds.left()
+    {
+      let row = ((event.position.y - bounds.top()) / layout.line_height).floor();
+      if row >= 0. && (row as usize) < self.display_map().row_count() {
+        let line = self.display_map().line_for_row(row as usize);
+        self.toggle_fold_at_line(line, cx);
+      }
+      return;
+    }
+    self.is_selecting = true;
+    self.mark = None;
+
+    match event.click_count {
+      1 => {
+        if event.modifiers.shift {
+          self.select_to(self.index_for_mouse_position(event.position), cx);
+        } else {
+          self.move_to(self.index_for_mouse_position(event.position), cx)
+        }
+      }
+      2 => {
+        let click_index = self.index_for_mouse_position(event.position);
+        let start = self.previous_word_boundary(click_index);
+        let end = self.next_word_boundary(click_index);
+        self.selected_range = start..end;
+        self.selection_reversed = false;
+        cx.notify();
+      }
+      3 => {
+        let click_index = self.index_for_mouse_position(event.position);
+        let line_end = self.line_end(click_index);
+        self.selected_range = self.line_start(click_index)..self.next_boundary(line_end);
+        self.selection_reversed = false;
+        cx.notify();
+      }
+      _ => {}
+    }
+  }
+
+  fn on_mouse_up(&mut self, _: &MouseUpEvent, _window: &mut Window, _: &mut Context<Self>) {
+    self.is_selecting = false;
+    self.minimap_drag = None;
+  }
+
+  fn on_mouse_move(&mut self, event: &MouseMoveEvent, _: &mut Window, cx: &mut Context<Self>) {
+    if self.minimap_drag.is_some() {
+      self.drag_minimap(event.position.y, cx);
+    } else if self.is_selecting {
+      self.select_to(self.index_for_mouse_position(event.position), cx);
+    }
+  }
+
+  /// Starts dragging the minimap's slider, first centering it on the click
+  /// when it falls outside the slider.
+  fn on_minimap_mouse_down(
+    &mut self,
+    event: &MouseDownEvent,
+    _window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    cx.stop_propagation();
+    let Some(minimap) = self.last_minimap else {
+      return;
+    };
+    self.minimap_drag = Some(if minimap.slider.contains(&event.position) {
+      event.position.y - minimap.slider.top()
+    } else {
+      minimap.slider.size.height / 2.
+    });
+    self.drag_minimap(event.position.y, cx);
+  }
+
+  /// Scrolls so the minimap's slider follows the mouse at `y`.
+  fn drag_minimap(&mut self, y: Pixels, cx: &mut Context<Self>) {
+    let (Some(minimap), Some(grab)) = (self.last_minimap, self.minimap_drag) else {
+      return;
+    };
+    if minimap.track <= px(0.) {
+      return;
+    }
+    let fraction = ((y - grab - minimap.bounds.top()) / minimap.track).clamp(0., 1.);
+    let mut offset = self.scroll_handle.offset();
+    offset.y = -self.scroll_handle.max_offset().height * fraction;
+    self.scroll_handle.set_offset(offset);
+    cx.notify();
+  }
+
+  fn show_character_palette(
+    &mut self,
+    _: &ShowCharacterPalette,
+    window: &mut Window,
+    _: &mut Context<Self>,
+  ) {
+    window.show_character_palette();
+  }
+
+  fn paste(&mut self, _: &Paste, window: &mut Window, cx: &mut Context<Self>) {
+    if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
+      self.paste_text(&text, window, cx);
+    }
+  }
+
+  /// Replaces the selection with `text`, fitting its line breaks to the
+  /// editor.
+  pub fn paste_text(&mut self, text: &str, window: &mut Window, cx: &mut Context<Self>) {
+    let text = if self.single_line {
+      LineEnding::Lf.normalize(text).replace('\n', " ")
+    } else {
+      self.line_ending.normalize(text)
+    };
+    self.replace_text_in_range(None, &text, window, cx);
+  }
+
+  /// Writes the selected text to the system clipboard and records it in the
+  /// clipboard history.
+  fn copy_selection(&self, cx: &mut Context<Self>) {
+    let text = self.text(self.selected_range.clone()).to_string();
+    cx.write_to_clipboard(ClipboardItem::new_string(text.clone()));
+    if cx.has_global::<ClipboardHistory>() {
+      cx.global_mut::<ClipboardHistory>()
+        .push(ClipboardEntry::new(text));
+    }
+  }
+
+  fn copy(&mut self, _: &Copy, _: &mut Window, cx: &mut Context<Self>) {
+    if !self.selected_range.is_empty() {
+      self.copy_selection(cx);
+    }
+  }
+  fn cut(&mut self, _: &Cut, window: &mut Window, cx: &mut Context<Self>) {
+    if !self.selected_range.is_empty() {
+      self.copy_selection(cx);
+      self.replace_text_in_range(None, "", window, cx)
+    }
+  }
+
+  pub fn move_to(&mut self, offset: usize, cx: &mut Context<Self>) {
+    let offset = self.skip_folds(offset, offset > self.cursor_offset());
+    // While the mark is active every movement extends the selection from it.
+    if let Some(mark) = self.mark {
+      self.selected_range = mark.min(offset)..mark.max(offset);
+      self.selection_reversed = offset < mark;
+    } else {
+      self.selected_range = offset..offset;
+    }
+    self.end_snippet_outside_stops();
+    self.kill_ring.last_kill_offset = None;
+    self.kill_ring.last_yank = None;
+    self.scroll_to_cursor();
+    cx.notify()
+  }
+
+  pub fn line_count(&self) -> usize {
+    self.content.len_lines()
+  }
+
+  /// The range of a zero-based line, without its line break.
+  pub fn line_range(&self, line: usize) -> Range<usize> {
+    let start = self.content.line_to_byte(line);
+    start..self.line_end(start)
+  }
+
+  /// The text in the byte `range` of the content, borrowed when it lies in
+  /// one of the rope's chunks.
+  pub fn text(&self, range: Range<usize>) -> Cow<'_, str> {
+    self.content.byte_slice(range).into()
+  }
+
+  /// The character just before `offset`, if any.
+  pub fn char_before(&self, offset: usize) -> Option<char> {
+    let ix = self.content.byte_to_char(offset);
+    (ix > 0).then(|| self.content.char(ix - 1))
+  }
+
+  /// The character starting at `offset`, if any.
+  pub fn char_after(&self, offset: usize) -> Option<char> {
+    self.content.get_char(self.content.byte_to_char(offset))
+  }
+
+  /// The zero-based line and grapheme column of `offset`.
+  pub fn line_column_for_offset(&self, offset: usize) -> (usize, usize) {
+    (
+      self.content.byte_to_line(offset),
+      self.column_for_offset(offset),
+    )
+  }
+
+  /// The offset of a zero-based line and grapheme column, clamped to the
+  /// content.
+  pub fn offset_for_line_column(&self, line: usize, column: usize) -> usize {
+    let line_start = self
+      .content
+      .line_to_byte(line.min(self.content.len_lines() - 1));
+    self.offset_for_column(line_start, column)
+  }
+
+  /// Which lines are displayed on which rows, given the folds.
+  pub fn display_map(&self) -> DisplayMap {
+    let line_for_offset = |offset: usize| self.line_column_for_offset(offset).0;
+    let hidden = self
+      .folds
+      .iter()
+      .map(|fold| line_for_offset(fold.start) + 1..line_for_offset(fold.end) + 1)
+      .collect();
+    DisplayMap::new(hidden, self.line_count())
+  }
+
+  /// Whether the lines under `line` are folded away.
+  pub fn is_folded(&self, line: usize) -> bool {
+    let end = self.line_range(line).end;
+    self.folds.iter().any(|fold| fold.start == end)
+  }
+
+  /// Recomputes the foldable regions after the content changed. Large files
+  /// can't be folded, as that takes scanning their whole content.
+  fn refresh_fold_regions(&mut self, cx: &App) {
+    if self.fold_regions_version == Some(self.version) {
+      return;
+    }
+    self.fold_regions_version = Some(self.version);
+    self.fold_regions = if self.large_file || self.single_line {
+      Vec::new()
+    } else {
+      self.refresh_bracket_pairs();
+      let tab_size = cx.global::<Settings>().tab_size;
+      folding::fold_regions(&self.content, &self.bracket_pairs.pairs, tab_size)
+    };
+  }
+
+  /// Switches the language, which the cached regions and pairs depend on.
+  pub fn set_language(&mut self, language: &'static Language) {
+    self.language = language;
+    self.fold_regions_version = None;
+    self.bracket_pairs_version = None;
+  }
+
+  /// Recomputes the matched bracket pairs after the content changed.
+  fn refresh_bracket_pairs(&mut self) {
+    if self.bracket_pairs_version == Some(self.version) {
+      return;
+    }
+    self.bracket_pairs_version = Some(self.version);
+    self.bracket_pairs = BracketPairs::scan(&self.content, self.language);
+  }
+
+  fn fold(&mut self, region: FoldRegion) {
+    let fold = self.line_range(region.start_line).end..self.line_range(region.end_line).end;
+    if !self.folds.contains(&fold) {
+      let ix = self.folds.partition_point(|other| other.start < fold.start);
+      self.folds.insert(ix, fold);
+    }
+  }
+
+  /// Moves a cursor or selection left inside folded text out of it.
+  fn reveal_selection(&mut self) {
+    let start = self.skip_folds(self.selected_range.start, false);
+    let end = self.skip_folds(self.selected_range.end, false);
+    self.selected_range = start..end;
+  }
+
+  /// Folds or unfolds the region starting on `line`.
+  pub fn toggle_fold_at_line(&mut self, line: usize, cx: &mut Context<Self>) {
+    if self.is_folded(line) {
+      let end = self.line_range(line).end;
+      self.folds.retain(|fold| fold.start != end);
+    } else if let Some(&region) = self
+      .fold_regions
+      .iter()
+      .find(|region| region.start_line == line)
+    {
+      self.fold(region);
+      self.reveal_selection();
+    }
+    cx.notify();
+  }
+
+  /// Unfolds the cursor's line if it is folded, otherwise folds the
+  /// innermost region around the cursor.
+  fn toggle_fold(&mut self, _: &ToggleFold, _: &mut Window, cx: &mut Context<Self>) {
+    let (line, _) = self.line_column_for_offset(self.cursor_offset());
+    let region = self
+      .fold_regions
+      .iter()
+      .filter(|region| (region.start_line..=region.end_line).contains(&line))
+      .max_by_key(|region| region.start_line);
+    let line = match region {
+      Some(region) if !self.is_folded(line) => region.start_line,
+      _ => line,
+    };
+    self.toggle_fold_at_line(line, cx);
+    self.scroll_to_cursor();
+  }
+
+  fn fold_all(&mut self, _: &FoldAll, _: &mut Window, cx: &mut Context<Self>) {
+    for region in self.fold_regions.clone() {
+      self.fold(region);
+    }
+    self.reveal_selection();
+    self.scroll_to_cursor();
+    cx.notify();
+  }
+
+  fn unfold_all(&mut self, _: &UnfoldAll, _: &mut Window, cx: &mut Context<Self>) {
+    self.folds.clear();
+    self.scroll_to_cursor();
+    cx.notify();
+  }
+
+  /// Folds the regions nested `level` deep, leaving the others as they are.
+  fn fold_at_level(&mut self, level: usize, cx: &mut Context<Self>) {
+    let regions = self.fold_regions.clone();
+    for (region, depth) in regions.iter().zip(folding::region_depths(&regions)) {
+      if depth == level {
+        self.fold(*region);
+      }
+    }
+    self.reveal_selection();
+    self.scroll_to_cursor();
+    cx.notify();
+  }
+
+  /// Moves `offset` out of any folded text it is in: forward to the line
+  /// after the fold, or back to the end of the fold's first line.
+  fn skip_folds(&self, mut offset: usize, forward: bool) -> usize {
+    while let Some(fold) = self
+      .folds
+      .iter()
+      .filter(|fold| fold.start < offset && offset <= fold.end)
+      .max_by_key(|fold| fold.end - fold.start)
+    {
+      let after = self.next_line_start(fold.end);
+      offset = if forward && after > fold.end {
+        after
+      } else {
+        fold.start
+      };
+    }
+    offset
+  }
+
+  /// Scrolls the least amount that brings the cursor's line into view.
+  fn scroll_to_cursor(&self) {
+    let viewport = self.scroll_handle.bounds().size.height;
+    if viewport <= px(0.) {
+      return;
+    }
+    let (line, _) = self.line_column_for_offset(self.cursor_offset());
+    let top = LINE_HEIGHT * self.display_map().row_for_line(line) as f32;
+    let mut offset = self.scroll_handle.offset();
+    if top < -offset.y {
+      offset.y = -top;
+    } else if top + LINE_HEIGHT > viewport - offset.y {
+      offset.y = viewport - top - LINE_HEIGHT;
+    } else {
+      return;
+    }
+    self.scroll_handle.set_offset(offset);
+  }
+
+  /// Inserts the snippet `body` in place of `range` and selects its first
+  /// tab stop.
+  pub fn insert_snippet(&mut self, body: &str, range: Range<usize>, cx: &mut Context<Self>) {
+    let (line, _) = self.line_column_for_offset(range.start);
+    let current_line = self.text(self.line_range(line)).to_string();
+    let indent = current_line
+      [..current_line.len() - current_line.trim_start_matches([' ', '\t']).len()]
+      .to_string();
+    let cursor = self.cursor_offset();
+    let current_word = self
+      .text(self.previous_word_boundary(cursor)..self.next_word_boundary(cursor))
+      .into_owned();
+    let selected_text = self.text(self.selected_range.clone()).to_string();
+    let clipboard = cx.read_from_clipboard().and_then(|item| item.text());
+    let path = self.path.clone();
+    let file_name = |path: Option<&Path>| Some(path?.to_string_lossy().into_owned());
+    let indent_unit = cx.global::<Settings>().indent_unit();
+    let expanded = snippets::expand(
+      body,
+      &ExpandOptions {
+        line_ending: self.line_ending.as_str(),
+        indent: &indent,
+        indent_unit: &indent_unit,
+      },
+      |name| match name {
+        "TM_SELECTED_TEXT" => Some(selected_text.clone()),
+        "TM_CURRENT_LINE" => Some(current_line.clone()),
+        "TM_CURRENT_WORD" => Some(current_word.clone()),
+        "TM_LINE_INDEX" => Some(line.to_string()),
+        "TM_LINE_NUMBER" => Some((line + 1).to_string()),
+        "TM_FILENAME" => file_name(path.as_deref()?.file_name().map(Path::new)),
+        "TM_FILENAME_BASE" => file_name(path.as_deref()?.file_stem().map(Path::new)),
+        "TM_DIRECTORY" => file_name(path.as_deref()?.parent()),
+        "TM_FILEPATH" => file_name(path.as_deref()),
+        "CLIPBOARD" => clipboard.clone(),
+        _ => None,
+      },
+    );
+
+    self.snippet = None;
+    self.edit(range.clone(), &expanded.text, cx);
+    let mut stops = expanded.stops;
+    for stop in &mut stops {
+      for stop_range in &mut stop.ranges {
+        *stop_range = stop_range.start + range.start..stop_range.end + range.start;
+      }
+    }
+    self.snippet = Some(SnippetSession {
+      stops,
+      active: 0,
+      choice: 0,
+    });
+    self.select_tab_stop(0, cx);
+  }
+
+  /// Expands the snippet whose prefix ends at the cursor, if any. The
+  /// longest prefix wins, and the user's snippets over built-in ones.
+  fn expand_snippet_prefix(&mut self, cx: &mut Context<Self>) -> bool {
+    if self.single_line || !self.selected_range.is_empty() || !cx.has_global::<Snippets>() {
+      return false;
+    }
+    let cursor = self.cursor_offset();
+    let before = &self.text(self.line_start(cursor)..cursor);
+    let definition = cx
+      .global::<Snippets>()
+      .for_language(self.language)
+      .into_iter()
+      .rev()
+      .filter(|definition| {
+        let prefix = definition.prefix.as_str();
+        !prefix.is_empty()
+          && before.ends_with(prefix)
+          && before[..before.len() - prefix.len()]
+            .chars()
+            .next_back()
+            .is_none_or(|ch| !ch.is_alphanumeric() && ch != '_')
+      })
+      .max_by_key(|definition| definition.prefix.len());
+    let Some(definition) = definition else {
+      return false;
+    };
+    self.insert_snippet(
+      &definition.body,
+      cursor - definition.prefix.len()..cursor,
+      cx,
+    );
+    true
+  }
+
+  /// Selects the placeholder of the snippet's tab stop `ix`, ending the
+  /// snippet at its last stop.
+  fn select_tab_stop(&mut self, ix: usize, cx: &mut Context<Self>) {
+    let Some(session) = self.snippet.as_mut() else {
+      return;
+    };
+    session.active = ix;
+    session.choice = 0;
+    self.selected_range = session.stops[ix].ranges[0].clone();
+    if ix + 1 == session.stops.len() {
+      self.snippet = None;
+    }
+    self.selection_reversed = false;
+    self.mark = None;
+    self.reveal_selection();
+    self.scroll_to_cursor();
+    cx.notify();
   }
 
-  fn cmd_right(&mut self, _: &CmdRight, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(self.content.len(), cx);
+  /// Ends the snippet once the selection has left all of its tab stops.
+  fn end_snippet_outside_stops(&mut self) {
+    if let Some(session) = &self.snippet
+      && !session.contains(&self.selected_range)
+    {
+      self.snippet = None;
+    }
   }
 
-  fn select_left(&mut self, _: &SelectLeft, _: &mut Window, cx: &mut Context<Self>) {
-    self.select_to(self.previous_boundary(self.cursor_offset()), cx);
+  /// The choices offered at the active tab stop, with the one shown, while
+  /// its placeholder is selected.
+  pub fn snippet_choices(&self) -> Option<(&[String], usize)> {
+    let session = self.snippet.as_ref()?;
+    let stop = &session.stops[session.active];
+    (stop.choices.len() > 1 && self.selected_range == stop.ranges[0])
+      .then_some((&stop.choices[..], session.choice))
   }
 
-  fn select_word_left(&mut self, _: &SelectWordLeft, _: &mut Window, cx: &mut Context<Self>) {
-    self.select_to(self.previous_word_boundary(self.cursor_offset()), cx);
+  /// Replaces the active tab stop's placeholder with the choice `delta`
+  /// away from the one shown.
+  fn cycle_snippet_choice(&mut self, delta: isize, cx: &mut Context<Self>) -> bool {
+    let Some((choices, current)) = self.snippet_choices() else {
+      return false;
+    };
+    let choice = (current as isize + delta).rem_euclid(choices.len() as isize) as usize;
+    let text = choices[choice].clone();
+    let Some(session) = self.snippet.as_mut() else {
+      return false;
+    };
+    session.choice = choice;
+    let active = session.active;
+    self.edit(self.selected_range.clone(), &text, cx);
+    if let Some(session) = &self.snippet {
+      self.selected_range = session.stops[active].ranges[0].clone();
+    }
+    true
   }
 
-  fn select_start(&mut self, _: &SelectStart, _: &mut Window, cx: &mut Context<Self>) {
-    self.select_to(0, cx);
+  /// Copies the active tab stop's placeholder to its mirrors, undone
+  /// together with the edit that changed it.
+  fn sync_snippet_mirrors(&mut self) {
+    let Some(session) = &self.snippet else {
+      return;
+    };
+    let active = session.active;
+    let mirror_count = session.stops[active].ranges.len() - 1;
+    for mirror in 1..=mirror_count {
+      let Some(session) = &self.snippet else {
+        return;
+      };
+      let text = self
+        .text(session.stops[active].ranges[0].clone())
+        .to_string();
+      let range = session.stops[active].ranges[mirror].clone();
+      if self.text(range.clone()) == text {
+        continue;
+      }
+      self.history.record_joined(
+        range.clone(),
+        &Cow::from(self.content.byte_slice(range.clone())),
+        &text,
+      );
+      self.splice(range.clone(), &text);
+      if range.end <= self.selected_range.start {
+        let shift = |offset: usize| offset - range.len() + text.len();
+        self.selected_range = shift(self.selected_range.start)..shift(self.selected_range.end);
+      }
+    }
   }
 
-  fn select_right(&mut self, _: &SelectRight, _: &mut Window, cx: &mut Context<Self>) {
-    self.select_to(self.next_boundary(self.cursor_offset()), cx);
+  /// Notes the state the element just painted, restarting the blink with the
+  /// cursor shown when the editor gains focus or the selection or content
+  /// changes, so it stays solid while typing.
+  pub fn cursor_painted(&mut self, focused: bool, cx: &mut Context<Self>) {
+    let state = (self.selected_range.clone(), self.version);
+    if focused && (!self.painted_focused || self.painted_state != state) {
+      self.cursor_visible = true;
+      self.blink_epoch += 1;
+      self.schedule_blink(self.blink_epoch, cx);
+    }
+    self.painted_state = state;
+    self.painted_focused = focused;
   }
 
-  fn select_word_right(&mut self, _: &SelectWordRight, _: &mut Window, cx: &mut Context<Self>) {
-    self.select_to(self.next_word_boundary(self.cursor_offset()), cx);
+  fn schedule_blink(&mut self, epoch: usize, cx: &mut Context<Self>) {
+    if !cx.global::<Settings>().cursor_blink {
+      return;
+    }
+    self.blink_task = cx.spawn(async move |this, cx| {
+      cx.background_executor().timer(CURSOR_BLINK_INTERVAL).await;
+      this
+        .update(cx, |this: &mut Self, cx| {
+          // Blinking stops while unfocused, until the next paint restarts it.
+          if this.blink_epoch == epoch && this.painted_focused {
+            this.cursor_visible = !this.cursor_visible;
+            this.schedule_blink(epoch, cx);
+            cx.notify();
+          }
+        })
+        .ok();
+    });
   }
 
-  fn select_end(&mut self, _: &SelectEnd, _: &mut Window, cx: &mut Context<Self>) {
-    self.select_to(self.content.len(), cx);
+  /// The bracket pair to highlight around the cursor, if any.
+  pub fn highlighted_brackets(&self) -> Option<(usize, usize)> {
+    if !self.selected_range.is_empty() || self.large_file {
+      return None;
+    }
+    self.bracket_pairs.pair_for_cursor(self.cursor_offset())
   }
 
-  fn select_all(&mut self, _: &SelectAll, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(0, cx);
-    self.select_to(self.content.len(), cx)
+  pub fn cursor_offset(&self) -> usize {
+    if self.selection_reversed {
+      self.selected_range.start
+    } else {
+      self.selected_range.end
+    }
   }
 
-  fn home(&mut self, _: &Home, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(0, cx);
-  }
+  fn index_for_mouse_position(&self, position: Point<Pixels>) -> usize {
+    if self.content.len_bytes() == 0 {
+      return 0;
+    }
 
-  fn end(&mut self, _: &End, _: &mut Window, cx: &mut Context<Self>) {
-    self.move_to(self.content.len(), cx);
+    let (Some(bounds), Some(layout)) = (self.last_bounds.as_ref(), self.last_layout.as_ref())
+    else {
+      return 0;
+    };
+    if position.y < bounds.top() {
+      return 0;
+    }
+    if position.y > bounds.bottom() {
+      return self.content.len_bytes();
+    }
+    layout
+      .closest_offset_for_position(position - bounds.origin)
+      .unwrap_or(self.cursor_offset())
   }
 
-  fn backspace(&mut self, _: &Backspace, window: &mut Window, cx: &mut Context<Self>) {
-    if self.selected_range.is_empty() {
-      self.select_to(self.previous_boundary(self.cursor_offset()), cx)
+  /// Records replacing `range` with `new_text` in the history. Each update
+  /// of the text an input method is composing, and the text it finally
+  /// commits, amend the step that started the composition.
+  fn record_edit(&mut self, range: Range<usize>, new_text: &str) {
+    if self.marked_range.as_ref() == Some(&range)
+      && self.history.amend_last(range.clone(), new_text)
+    {
+      return;
     }
-    self.replace_text_in_range(None, "", window, cx)
+    self.history.record(
+      range.clone(),
+      &Cow::from(self.content.byte_slice(range)),
+      new_text,
+      self.selected_range.clone(),
+      self.selection_reversed,
+    );
   }
 
-  fn delete(&mut self, _: &Delete, window: &mut Window, cx: &mut Context<Self>) {
-    if self.selected_range.is_empty() {
-      self.select_to(self.next_boundary(self.cursor_offset()), cx)
+  /// Replaces `range` with `new_text`, bypassing the adjustments applied to
+  /// typed text in `replace_text_in_range`.
+  fn edit(&mut self, range: Range<usize>, new_text: &str, cx: &mut Context<Self>) {
+    self.record_edit(range.clone(), new_text);
+    self.splice(range.clone(), new_text);
+    self.selected_range = range.start + new_text.len()..range.start + new_text.len();
+    self.selection_reversed = false;
+    self.sync_snippet_mirrors();
+    self.marked_range.take();
+    self.mark = None;
+    self.kill_ring.last_yank = None;
+    if !new_text.is_empty() {
+      self.kill_ring.last_kill_offset = None;
     }
-    self.replace_text_in_range(None, "", window, cx)
+    self.scroll_to_cursor();
+    cx.notify();
   }
 
-  fn on_mouse_down(
+  /// Replaces the whole content, then selects `selection`.
+  pub fn set_text(&mut self, text: &str, selection: Range<usize>, cx: &mut Context<Self>) {
+    self.edit_and_select(0..self.content.len_bytes(), text, selection, cx);
+  }
+
+  /// Applies `edit` as a single undo step, then selects `selection`.
+  fn edit_and_select(
     &mut self,
-    event: &MouseDownEvent,
-    _window: &mut Window,
+    range: Range<usize>,
+    new_text: &str,
+    selection: Range<usize>,
     cx: &mut Context<Self>,
   ) {
-    self.is_selecting = true;
-
-    match event.click_count {
-      1 => {
-        if event.modifiers.shift {
-          self.select_to(self.index_for_mouse_position(event.position), cx);
-        } else {
-          self.move_to(self.index_for_mouse_position(event.position), cx)
-        }
-      }
-      2 => {
-        let click_index = self.index_for_mouse_position(event.position);
-        let start = self.previous_word_boundary(click_index);
-        let end = self.next_word_boundary(click_index);
-        self.selected_range = start..end;
-        self.selection_reversed = false;
-        cx.notify();
-      }
-      3 => {
-        self.selected_range = 0..self.content.len();
-        self.selection_reversed = false;
-        cx.notify();
-      }
-      _ => {}
-    }
+    let reversed = self.selection_reversed;
+    self.edit(range, new_text, cx);
+    self.selected_range = selection;
+    self.selection_reversed = reversed;
   }
 
-  fn on_mouse_up(&mut self, _: &MouseUpEvent, _window: &mut Window, _: &mut Context<Self>) {
-    self.is_selecting = false;
-  }
+  fn splice(&mut self, range: Range<usize>, new_text: &str) {
+    let start = self.content.byte_to_char(range.start);
+    let end = self.content.byte_to_char(range.end);
+    self.content.remove(start..end);
+    self.content.insert(start, new_text);
+    self.version += 1;
 
-  fn on_mouse_move(&mut self, event: &MouseMoveEvent, _: &mut Window, cx: &mut Context<Self>) {
-    if self.is_selecting {
-      self.select_to(self.index_for_mouse_position(event.position), cx);
+    self
+      .folds
+      .retain_mut(|fold| folding::adjust_fold(fold, &range, new_text.len()));
+    self
+      .auto_closed
+      .retain_mut(|closer| adjust_auto_closed(closer, &range, new_text.len()));
+
+    if let Some(session) = self.snippet.as_mut() {
+      for (ix, stop) in session.stops.iter_mut().enumerate() {
+        let sticky = ix == session.active;
+        for stop_range in &mut stop.ranges {
+          snippets::adjust_range(stop_range, &range, new_text.len(), sticky);
+        }
+      }
     }
   }
 
-  fn show_character_palette(
+  /// Replaces the selected lines with the output of `f` and selects them.
+  fn rewrite_selected_lines(
     &mut self,
-    _: &ShowCharacterPalette,
-    window: &mut Window,
-    _: &mut Context<Self>,
+    f: impl FnOnce(Vec<&str>) -> Vec<&str>,
+    cx: &mut Context<Self>,
   ) {
-    window.show_character_palette();
+    let range = self.selected_lines_range();
+    let selected = self.text(range.clone()).into_owned();
+    let lines = lines_with_breaks(&selected).map(|(line, _)| line).collect();
+    let text = f(lines).join(self.line_ending.as_str());
+    self.edit(range.clone(), &text, cx);
+    self.selected_range = range.start..range.start + text.len();
   }
 
-  fn paste(&mut self, _: &Paste, window: &mut Window, cx: &mut Context<Self>) {
-    if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
-      self.replace_text_in_range(None, &text.replace("\n", " "), window, cx);
+  /// The range of every line touched by the selection, without a trailing
+  /// line the selection only reaches the start of.
+  fn selected_lines_range(&self) -> Range<usize> {
+    let Range { start, mut end } = self.selected_range;
+    if end > start && end == self.line_start(end) {
+      end -= 1;
     }
+    self.line_start(start)..self.line_end(end)
   }
 
-  fn copy(&mut self, _: &Copy, _: &mut Window, cx: &mut Context<Self>) {
-    if !self.selected_range.is_empty() {
-      cx.write_to_clipboard(ClipboardItem::new_string(
-        self.content[self.selected_range.clone()].to_string(),
-      ));
-    }
-  }
-  fn cut(&mut self, _: &Cut, window: &mut Window, cx: &mut Context<Self>) {
-    if !self.selected_range.is_empty() {
-      cx.write_to_clipboard(ClipboardItem::new_string(
-        self.content[self.selected_range.clone()].to_string(),
-      ));
-      self.replace_text_in_range(None, "", window, cx)
+  /// Applies `case` to the selection, or to the word under the cursor.
+  fn convert_case(&mut self, case: Case, cx: &mut Context<Self>) {
+    let range = if self.selected_range.is_empty() {
+      self.word_range(self.cursor_offset())
+    } else {
+      self.selected_range.clone()
+    };
+    if range.is_empty() {
+      return;
     }
+
+    let text = case::convert(&self.text(range.clone()), case);
+    let selection = if self.selected_range.is_empty() {
+      let cursor = self.cursor_offset().min(range.start + text.len());
+      cursor..cursor
+    } else {
+      range.start..range.start + text.len()
+    };
+    self.edit_and_select(range, &text, selection, cx);
   }
 
-  fn move_to(&mut self, offset: usize, cx: &mut Context<Self>) {
-    self.selected_range = offset..offset;
-    cx.notify()
+  fn edit_selected_lines(&mut self, f: impl FnMut(&str) -> String, cx: &mut Context<Self>) {
+    self.edit_lines(self.selected_lines_range(), true, f, cx);
   }
 
-  pub fn cursor_offset(&self) -> usize {
-    if self.selection_reversed {
-      self.selected_range.start
-    } else {
-      self.selected_range.end
+  /// Rewrites every line in `range` with `f` as one undo step. Each end of the
+  /// selection keeps its distance from the end of its line when
+  /// `anchor_to_line_end` is set, and from the start otherwise.
+  fn edit_lines(
+    &mut self,
+    range: Range<usize>,
+    anchor_to_line_end: bool,
+    mut f: impl FnMut(&str) -> String,
+    cx: &mut Context<Self>,
+  ) {
+    let new_lines = lines_with_breaks(&self.text(range.clone()))
+      .map(|(line, line_break)| (f(line), line_break))
+      .collect::<Vec<_>>();
+    let new_text = new_lines
+      .iter()
+      .map(|(line, line_break)| format!("{line}{line_break}"))
+      .collect::<String>();
+    if new_text == self.text(range.clone()) {
+      return;
     }
+    let anchor = |offset: usize| {
+      if offset > range.end {
+        return Err(offset + new_text.len() - range.len());
+      }
+      let row = self.text(range.start..offset).matches('\n').count();
+      if anchor_to_line_end {
+        Ok((row, self.line_end(offset) - offset))
+      } else {
+        Ok((row, offset - self.line_start(offset)))
+      }
+    };
+    let (start, end) = (
+      anchor(self.selected_range.start),
+      anchor(self.selected_range.end),
+    );
+    let reversed = self.selection_reversed;
+
+    self.edit(range.clone(), &new_text, cx);
+    let resolve = |anchor: Result<(usize, usize), usize>| match anchor {
+      Ok((row, distance)) => {
+        let line_start = range.start
+          + new_lines[..row]
+            .iter()
+            .map(|(line, line_break)| line.len() + line_break.len())
+            .sum::<usize>();
+        let line_len = new_lines[row].0.len();
+        if anchor_to_line_end {
+          line_start + line_len - distance.min(line_len)
+        } else {
+          line_start + distance.min(line_len)
+        }
+      }
+      Err(offset) => offset,
+    };
+    self.selected_range = resolve(start)..resolve(end);
+    self.selection_reversed = reversed;
   }
 
-  fn index_for_mouse_position(&self, position: Point<Pixels>) -> usize {
-    if self.content.is_empty() {
-      return 0;
+  /// Handles typing a single character of an auto-close pair: skips over a
+  /// closer it inserted, surrounds a selection, or inserts the matching
+  /// closer.
+  /// Returns whether the edit was applied.
+  fn auto_close(&mut self, range: &Range<usize>, new_text: &str, cx: &mut Context<Self>) -> bool {
+    let mut chars = new_text.chars();
+    let Some(ch) = chars.next() else {
+      return false;
+    };
+    if chars.next().is_some() {
+      return false;
     }
+    let before = self.char_before(range.start);
+    let after = self.char_after(range.end);
 
-    let (Some(bounds), Some(line)) = (self.last_bounds.as_ref(), self.last_layout.as_ref()) else {
-      return 0;
+    if range.is_empty() && after == Some(ch) && self.is_auto_closed(range.end, ch) {
+      self.auto_closed.retain(|closer| closer.start != range.end);
+      self.move_to(range.end + ch.len_utf8(), cx);
+      return true;
+    }
+
+    let Some(close) = self.language.auto_close_pair(ch) else {
+      return false;
     };
-    if position.y < bounds.top() {
-      return 0;
+    if !range.is_empty() {
+      let selected = self.text(range.clone()).to_string();
+      self.edit(range.clone(), &format!("{ch}{selected}{close}"), cx);
+      self.selected_range = range.start + ch.len_utf8()..range.end + ch.len_utf8();
+      return true;
     }
-    if position.y > bounds.bottom() {
-      return self.content.len();
+
+    if !self.language.should_auto_close(ch, before, after) {
+      return false;
+    }
+    self.edit(range.clone(), &format!("{ch}{close}"), cx);
+    let closer = range.start + ch.len_utf8();
+    self.auto_closed.push(closer..closer + close.len_utf8());
+    self.move_to(closer, cx);
+    true
+  }
+
+  /// Whether the `close` at `offset` was inserted by auto-close.
+  fn is_auto_closed(&self, offset: usize, close: char) -> bool {
+    self
+      .auto_closed
+      .contains(&(offset..offset + close.len_utf8()))
+  }
+
+  /// Adjusts a closing bracket typed on a blank line to the indentation of the
+  /// line holding its opening bracket.
+  fn auto_dedent(
+    &mut self,
+    range: &Range<usize>,
+    new_text: &str,
+  ) -> Option<(Range<usize>, String)> {
+    let mut chars = new_text.chars();
+    let close = chars.next()?;
+    if chars.next().is_some() {
+      return None;
     }
-    line.closest_index_for_x(position.x - bounds.left())
+    if self.large_file || self.language.opening_bracket(close).is_none() {
+      return None;
+    }
+    self.refresh_bracket_pairs();
+    dedent_closer(
+      &self.content,
+      &self.bracket_pairs,
+      self.language,
+      range,
+      close,
+    )
   }
 
   fn select_to(&mut self, offset: usize, cx: &mut Context<Self>) {
+    let offset = self.skip_folds(offset, offset > self.cursor_offset());
     if self.selection_reversed {
       self.selected_range.start = offset
     } else {
@@ -247,75 +2139,179 @@ impl TextInput {
       self.selection_reversed = !self.selection_reversed;
       self.selected_range = self.selected_range.end..self.selected_range.start;
     }
+    self.end_snippet_outside_stops();
+    self.scroll_to_cursor();
     cx.notify()
   }
 
   fn offset_from_utf16(&self, offset: usize) -> usize {
-    let mut utf8_offset = 0;
-    let mut utf16_count = 0;
+    let offset = offset.min(self.content.len_utf16_cu());
+    self
+      .content
+      .char_to_byte(self.content.utf16_cu_to_char(offset))
+  }
 
-    for ch in self.content.chars() {
-      if utf16_count >= offset {
-        break;
+  fn offset_to_utf16(&self, offset: usize) -> usize {
+    self
+      .content
+      .char_to_utf16_cu(self.content.byte_to_char(offset))
+  }
+
+  fn range_to_utf16(&self, range: &Range<usize>) -> Range<usize> {
+    self.offset_to_utf16(range.start)..self.offset_to_utf16(range.end)
+  }
+
+  fn range_from_utf16(&self, range_utf16: &Range<usize>) -> Range<usize> {
+    self.offset_from_utf16(range_utf16.start)..self.offset_from_utf16(range_utf16.end)
+  }
+
+  /// Grapheme boundaries never split `\r\n`, so the cursor steps over a CRLF
+  /// break as one unit. The rope's chunks are fed to the grapheme cursor as
+  /// it asks for them.
+  fn previous_boundary(&self, offset: usize) -> usize {
+    let (mut chunk, mut chunk_start, _, _) = self.content.chunk_at_byte(offset);
+    let mut cursor = GraphemeCursor::new(offset, self.content.len_bytes(), true);
+    loop {
+      match cursor.prev_boundary(chunk, chunk_start) {
+        Ok(boundary) => return boundary.unwrap_or(0),
+        Err(GraphemeIncomplete::PrevChunk) => {
+          (chunk, chunk_start, _, _) = self.content.chunk_at_byte(chunk_start - 1);
+        }
+        Err(GraphemeIncomplete::PreContext(end)) => {
+          let context = self.content.chunk_at_byte(end - 1).0;
+          cursor.provide_context(context, end - context.len());
+        }
+        Err(_) => {
+          return self
+            .content
+            .char_to_byte(self.content.byte_to_char(offset) - 1);
+        }
       }
-      utf16_count += ch.len_utf16();
-      utf8_offset += ch.len_utf8();
     }
+  }
 
-    utf8_offset
+  fn next_boundary(&self, offset: usize) -> usize {
+    let len = self.content.len_bytes();
+    let (mut chunk, mut chunk_start, _, _) = self.content.chunk_at_byte(offset);
+    let mut cursor = GraphemeCursor::new(offset, len, true);
+    loop {
+      match cursor.next_boundary(chunk, chunk_start) {
+        Ok(boundary) => return boundary.unwrap_or(len),
+        Err(GraphemeIncomplete::NextChunk) => {
+          chunk_start += chunk.len();
+          chunk = self.content.chunk_at_byte(chunk_start).0;
+        }
+        Err(GraphemeIncomplete::PreContext(end)) => {
+          let context = self.content.chunk_at_byte(end - 1).0;
+          cursor.provide_context(context, end - context.len());
+        }
+        Err(_) => {
+          return self
+            .content
+            .char_to_byte(self.content.byte_to_char(offset) + 1);
+        }
+      }
+    }
   }
 
-  fn offset_to_utf16(&self, offset: usize) -> usize {
-    let mut utf16_offset = 0;
-    let mut utf8_count = 0;
+  fn line_start(&self, offset: usize) -> usize {
+    self.content.line_to_byte(self.content.byte_to_line(offset))
+  }
 
-    for ch in self.content.chars() {
-      if utf8_count >= offset {
-        break;
-      }
-      utf8_count += ch.len_utf8();
-      utf16_offset += ch.len_utf16();
+  /// The end of the line containing `offset`, before its `\n` or `\r\n`.
+  fn line_end(&self, offset: usize) -> usize {
+    let line = self.content.byte_to_line(offset);
+    if line + 1 == self.content.len_lines() {
+      return self.content.len_bytes();
+    }
+    let end = self.content.line_to_byte(line + 1) - 1;
+    if end > 0 && self.content.byte(end - 1) == b'\r' {
+      end - 1
+    } else {
+      end
     }
+  }
 
-    utf16_offset
+  /// The start of the line after the one ending at `line_end`.
+  fn next_line_start(&self, line_end: usize) -> usize {
+    let line = self.content.byte_to_line(line_end);
+    if line + 1 == self.content.len_lines() {
+      self.content.len_bytes()
+    } else {
+      self.content.line_to_byte(line + 1)
+    }
   }
 
-  fn range_to_utf16(&self, range: &Range<usize>) -> Range<usize> {
-    self.offset_to_utf16(range.start)..self.offset_to_utf16(range.end)
+  /// The offset in the same column on the row above, skipping folded lines.
+  fn offset_above(&self, offset: usize) -> usize {
+    let display_map = self.display_map();
+    let row = display_map.row_for_line(self.line_column_for_offset(offset).0);
+    if row == 0 {
+      return 0;
+    }
+    let column = self.column_for_offset(offset);
+    self.offset_for_line_column(display_map.line_for_row(row - 1), column)
   }
 
-  fn range_from_utf16(&self, range_utf16: &Range<usize>) -> Range<usize> {
-    self.offset_from_utf16(range_utf16.start)..self.offset_from_utf16(range_utf16.end)
+  /// The offset in the same column on the row below, skipping folded lines.
+  fn offset_below(&self, offset: usize) -> usize {
+    let display_map = self.display_map();
+    let row = display_map.row_for_line(self.line_column_for_offset(offset).0);
+    if row + 1 == display_map.row_count() {
+      return self.line_end(offset);
+    }
+    let column = self.column_for_offset(offset);
+    self.offset_for_line_column(display_map.line_for_row(row + 1), column)
   }
 
-  fn previous_boundary(&self, offset: usize) -> usize {
+  fn column_for_offset(&self, offset: usize) -> usize {
     self
-      .content
-      .grapheme_indices(true)
-      .rev()
-      .find_map(|(idx, _)| (idx < offset).then_some(idx))
-      .unwrap_or(0)
+      .text(self.line_start(offset)..offset)
+      .graphemes(true)
+      .count()
   }
 
-  fn next_boundary(&self, offset: usize) -> usize {
+  fn offset_for_column(&self, line_start: usize, column: usize) -> usize {
+    let line_end = self.line_end(line_start);
     self
-      .content
+      .text(line_start..line_end)
       .grapheme_indices(true)
-      .find_map(|(idx, _)| (idx > offset).then_some(idx))
-      .unwrap_or(self.content.len())
+      .nth(column)
+      .map_or(line_end, |(idx, _)| line_start + idx)
+  }
+
+  /// The identifier around `offset`, made of alphanumerics and underscores.
+  fn word_range(&self, offset: usize) -> Range<usize> {
+    let is_word = |ch: char| ch.is_alphanumeric() || ch == '_';
+    let ix = self.content.byte_to_char(offset);
+    let start = ix
+      - self
+        .content
+        .chars_at(ix)
+        .reversed()
+        .take_while(|&ch| is_word(ch))
+        .count();
+    let end = ix
+      + self
+        .content
+        .chars_at(ix)
+        .take_while(|&ch| is_word(ch))
+        .count();
+    self.content.char_to_byte(start)..self.content.char_to_byte(end)
   }
 
   fn next_word_boundary(&self, offset: usize) -> usize {
-    let bytes = self.content.as_bytes();
+    let bytes = self.content.bytes_at(offset);
     let mut idx = offset;
+    let mut in_word = false;
 
-    // Skip any non-word characters
-    while idx < bytes.len() && !bytes[idx].is_ascii_alphanumeric() {
-      idx += 1;
-    }
-
-    // Skip word characters
-    while idx < bytes.len() && bytes[idx].is_ascii_alphanumeric() {
+    // Skip any non-word characters, then the word characters after them
+    for byte in bytes {
+      if byte.is_ascii_alphanumeric() {
+        in_word = true;
+      } else if in_word {
+        break;
+      }
       idx += 1;
     }
 
@@ -323,16 +2319,17 @@ impl TextInput {
   }
 
   fn previous_word_boundary(&self, offset: usize) -> usize {
-    let bytes = self.content.as_bytes();
+    let mut bytes = self.content.bytes_at(offset);
     let mut idx = offset;
+    let mut in_word = false;
 
-    // Move backwards skipping any non-word characters
-    while idx > 0 && !bytes[idx - 1].is_ascii_alphanumeric() {
-      idx -= 1;
-    }
-
-    // Move backwards skipping word characters
-    while idx > 0 && bytes[idx - 1].is_ascii_alphanumeric() {
+    // Move backwards skipping any non-word characters, then word characters
+    while let Some(byte) = bytes.prev() {
+      if byte.is_ascii_alphanumeric() {
+        in_word = true;
+      } else if in_word {
+        break;
+      }
       idx -= 1;
     }
 
@@ -350,7 +2347,7 @@ impl EntityInputHandler for TextInput {
   ) -> Option<String> {
     let range = self.range_from_utf16(&range_utf16);
     actual_range.replace(self.range_to_utf16(&range));
-    Some(self.content[range].to_string())
+    Some(self.text(range).to_string())
   }
 
   fn selected_text_range(
@@ -393,11 +2390,20 @@ impl EntityInputHandler for TextInput {
       .or(self.marked_range.clone())
       .unwrap_or(self.selected_range.clone());
 
-    self.content =
-      (self.content[0..range.start].to_owned() + new_text + &self.content[range.end..]).into();
-    self.selected_range = range.start + new_text.len()..range.start + new_text.len();
-    self.marked_range.take();
-    cx.notify();
+    if self.marked_range.is_none()
+      && cx.global::<Settings>().auto_close(self.language)
+      && self.auto_close(&range, new_text, cx)
+    {
+      return;
+    }
+
+    if self.marked_range.is_none()
+      && let Some((range, new_text)) = self.auto_dedent(&range, new_text)
+    {
+      self.edit(range, &new_text, cx);
+    } else {
+      self.edit(range, new_text, cx);
+    }
   }
 
   fn replace_and_mark_text_in_range(
@@ -414,8 +2420,8 @@ impl EntityInputHandler for TextInput {
       .or(self.marked_range.clone())
       .unwrap_or(self.selected_range.clone());
 
-    self.content =
-      (self.content[0..range.start].to_owned() + new_text + &self.content[range.end..]).into();
+    self.record_edit(range.clone(), new_text);
+    self.splice(range.clone(), new_text);
     if !new_text.is_empty() {
       self.marked_range = Some(range.start..range.start + new_text.len());
     } else {
@@ -439,15 +2445,14 @@ impl EntityInputHandler for TextInput {
   ) -> Option<Bounds<Pixels>> {
     let last_layout = self.last_layout.as_ref()?;
     let range = self.range_from_utf16(&range_utf16);
+    let start = last_layout.position_for_offset(range.start)?;
+    let end_x = match last_layout.position_for_offset(range.end) {
+      Some(end) if end.y == start.y => end.x,
+      _ => last_layout.lines[last_layout.line_for_offset(range.start)?].width,
+    };
     Some(Bounds::from_corners(
-      point(
-        bounds.left() + last_layout.x_for_index(range.start),
-        bounds.top(),
-      ),
-      point(
-        bounds.left() + last_layout.x_for_index(range.end),
-        bounds.bottom(),
-      ),
+      bounds.origin + start,
+      bounds.origin + point(end_x, start.y + last_layout.line_height),
     ))
   }
 
@@ -460,14 +2465,29 @@ impl EntityInputHandler for TextInput {
     let line_point = self.last_bounds?.localize(&point)?;
     let last_layout = self.last_layout.as_ref()?;
 
-    assert_eq!(last_layout.text, self.content);
-    let utf8_index = last_layout.index_for_x(point.x - line_point.x)?;
+    let utf8_index = last_layout.offset_for_position(line_point)?;
     Some(self.offset_to_utf16(utf8_index))
   }
 }
 
 impl Render for TextInput {
   fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    self.refresh_fold_regions(cx);
+    if !self.large_file && !self.single_line {
+      self.refresh_bracket_pairs();
+    }
+    if let Some(progress) = self.loading {
+      return div()
+        .track_focus(&self.focus_handle(cx))
+        .size_full()
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(white())
+        .child(format!("Loading... {:.0}%", progress * 100.))
+        .into_any_element();
+    }
+
     div()
       .flex()
       .key_context("TextInput")
@@ -494,20 +2514,91 @@ impl Render for TextInput {
       .on_action(cx.listener(Self::paste))
       .on_action(cx.listener(Self::cut))
       .on_action(cx.listener(Self::copy))
+      .on_action(cx.listener(Self::up))
+      .on_action(cx.listener(Self::down))
+      .on_action(cx.listener(Self::kill_line))
+      .on_action(cx.listener(Self::kill_region))
+      .on_action(cx.listener(Self::kill_ring_save))
+      .on_action(cx.listener(Self::yank))
+      .on_action(cx.listener(Self::yank_pop))
+      .on_action(cx.listener(Self::set_mark))
+      .on_action(cx.listener(Self::exchange_point_and_mark))
+      .on_action(cx.listener(Self::cancel))
+      .on_action(cx.listener(Self::select_up))
+      .on_action(cx.listener(Self::select_down))
+      .on_action(cx.listener(Self::newline))
+      .on_action(cx.listener(Self::indent))
+      .on_action(cx.listener(Self::outdent))
+      .on_action(cx.listener(Self::jump_to_matching_bracket))
+      .on_action(cx.listener(Self::select_inside_brackets))
+      .on_action(cx.listener(Self::undo))
+      .on_action(cx.listener(Self::redo))
+      .on_action(cx.listener(Self::move_line_up))
+      .on_action(cx.listener(Self::move_line_down))
+      .on_action(cx.listener(Self::duplicate_line))
+      .on_action(cx.listener(Self::delete_line))
+      .on_action(cx.listener(Self::join_lines))
+      .on_action(cx.listener(Self::insert_line_above))
+      .on_action(cx.listener(Self::insert_line_below))
+      .on_action(cx.listener(Self::sort_lines))
+      .on_action(cx.listener(Self::reverse_lines))
+      .on_action(cx.listener(Self::unique_lines))
+      .on_action(cx.listener(Self::toggle_comment))
+      .on_action(cx.listener(Self::toggle_block_comment))
+      .on_action(cx.listener(Self::convert_to_upper_case))
+      .on_action(cx.listener(Self::convert_to_lower_case))
+      .on_action(cx.listener(Self::convert_to_title_case))
+      .on_action(cx.listener(Self::convert_to_snake_case))
+      .on_action(cx.listener(Self::convert_to_camel_case))
+      .on_action(cx.listener(Self::convert_to_kebab_case))
+      .on_action(cx.listener(Self::convert_to_pascal_case))
+      .on_action(cx.listener(Self::convert_indentation_to_spaces))
+      .on_action(cx.listener(Self::convert_indentation_to_tabs))
+      .on_action(cx.listener(Self::trim_trailing_whitespace))
+      .on_action(cx.listener(Self::convert_line_endings_to_lf))
+      .on_action(cx.listener(Self::convert_line_endings_to_crlf))
+      .on_action(cx.listener(Self::toggle_fold))
+      .on_action(cx.listener(Self::fold_all))
+      .on_action(cx.listener(Self::unfold_all))
+      .on_action(cx.listener(|this, _: &FoldAtLevel1, _, cx| this.fold_at_level(1, cx)))
+      .on_action(cx.listener(|this, _: &FoldAtLevel2, _, cx| this.fold_at_level(2, cx)))
+      .on_action(cx.listener(|this, _: &FoldAtLevel3, _, cx| this.fold_at_level(3, cx)))
+      .on_action(cx.listener(|this, _: &FoldAtLevel4, _, cx| this.fold_at_level(4, cx)))
+      .on_action(cx.listener(|this, _: &FoldAtLevel5, _, cx| this.fold_at_level(5, cx)))
       .on_mouse_down(MouseButton::Left, cx.listener(Self::on_mouse_down))
       .on_mouse_up(MouseButton::Left, cx.listener(Self::on_mouse_up))
       .on_mouse_up_out(MouseButton::Left, cx.listener(Self::on_mouse_up))
       .on_mouse_move(cx.listener(Self::on_mouse_move))
       .bg(rgb(0xeeeeee))
-      .line_height(px(30.))
+      .line_height(LINE_HEIGHT)
+      .size_full()
       .child(
         div()
-          .h(px(30. + 4. * 2.))
-          .w_full()
+          .id("text-input-scroll")
+          .flex_1()
+          .min_w_0()
+          .h_full()
+          .overflow_y_scroll()
+          .track_scroll(&self.scroll_handle)
           .p(px(4.))
           .bg(white())
           .child(TextElement { input: cx.entity() }),
       )
+      .when(!self.single_line, |this| {
+        this.child(
+          div()
+            .flex_none()
+            .w(MINIMAP_WIDTH)
+            .h_full()
+            .bg(white())
+            .border_l_1()
+            .border_color(rgb(0xdddddd))
+            .cursor(CursorStyle::Arrow)
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_minimap_mouse_down))
+            .child(MinimapElement { input: cx.entity() }),
+        )
+      })
+      .into_any_element()
   }
 }
 
@@ -516,3 +2607,129 @@ impl Focusable for TextInput {
     self.focus_handle.clone()
   }
 }
+
+/// The display width of `indent` in columns.
+fn indent_columns(indent: &str, tab_size: usize) -> usize {
+  indent.chars().fold(0, |column, ch| {
+    if ch == '\t' {
+      column + tab_size - column % tab_size
+    } else {
+      column + 1
+    }
+  })
+}
+
+pub fn leading_whitespace(text: &str) -> &str {
+  &text[..text.len() - text.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Shifts an auto-inserted `closer` to account for `edited` being replaced
+/// by `new_len` bytes. Returns false once the edit touches the closer
+/// itself, which makes it an ordinary character.
+fn adjust_auto_closed(closer: &mut Range<usize>, edited: &Range<usize>, new_len: usize) -> bool {
+  if edited.start < closer.end && edited.end > closer.start {
+    return false;
+  }
+  if edited.end <= closer.start {
+    closer.start = closer.start - edited.len() + new_len;
+    closer.end = closer.end - edited.len() + new_len;
+  }
+  true
+}
+
+/// The edit moving a `close` bracket typed at `range` on a blank line to the
+/// indentation of the line holding its opening bracket.
+fn dedent_closer(
+  text: &Rope,
+  brackets: &BracketPairs,
+  language: &Language,
+  range: &Range<usize>,
+  close: char,
+) -> Option<(Range<usize>, String)> {
+  let open = language.opening_bracket(close)?;
+  let line_start = text.line_to_byte(text.byte_to_line(range.start));
+  if !text
+    .byte_slice(line_start..range.start)
+    .chars()
+    .all(char::is_whitespace)
+  {
+    return None;
+  }
+
+  let open_offset = brackets.enclosing_open(line_start)?;
+  if text.get_char(text.byte_to_char(open_offset)) != Some(open) {
+    return None;
+  }
+  let open_line_start = text.line_to_byte(text.byte_to_line(open_offset));
+  let before_open = Cow::from(text.byte_slice(open_line_start..open_offset));
+  let indent = leading_whitespace(&before_open);
+  Some((line_start..range.end, format!("{indent}{close}")))
+}
+
+/// How many bytes of whitespace before and after a line break typed between
+/// `before` and `after` it replaces, as they would only linger as trailing
+/// whitespace or extra indentation. Within a line's indentation, the line
+/// moves down whole and keeps its indentation.
+fn whitespace_around_break(before: &str, after: &str) -> (usize, usize) {
+  if before.trim().is_empty() {
+    (before.len(), 0)
+  } else {
+    (0, after.len() - after.trim_start().len())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn auto_closed_brackets_shift_with_edits_before_them() {
+    let adjust = |edited: Range<usize>, new_len: usize| {
+      let mut closer = 5..6;
+      adjust_auto_closed(&mut closer, &edited, new_len).then_some(closer)
+    };
+    assert_eq!(adjust(5..5, 1), Some(6..7));
+    assert_eq!(adjust(2..4, 0), Some(3..4));
+    assert_eq!(adjust(1..2, 3), Some(7..8));
+    // Typing right after the closer or further on leaves it be.
+    assert_eq!(adjust(6..6, 1), Some(5..6));
+    assert_eq!(adjust(7..9, 0), Some(5..6));
+    // Editing the closer itself makes it an ordinary character.
+    assert_eq!(adjust(5..6, 0), None);
+    assert_eq!(adjust(4..6, 1), None);
+  }
+
+  #[test]
+  fn closers_dedent_to_their_opener() {
+    let rust = Language::for_path(Path::new("main.rs")).unwrap();
+    let dedent = |text: &str, close: char| {
+      let text = Rope::from_str(text);
+      let brackets = BracketPairs::scan(&text, rust);
+      let end = text.len_bytes();
+      dedent_closer(&text, &brackets, rust, &(end..end), close)
+    };
+    assert_eq!(
+      dedent("  fn f() {\n      ", '}'),
+      Some((11..17, "  }".to_string()))
+    );
+    // Closed pairs before the cursor are passed over.
+    assert_eq!(
+      dedent("\tif x {\n\t\tf(a)\n\t\t", '}'),
+      Some((15..17, "\t}".to_string()))
+    );
+    // Not after text on the line, nor for the wrong kind of bracket.
+    assert_eq!(dedent("fn f() {\n    x", '}'), None);
+    assert_eq!(dedent("fn f() {\n    ", ')'), None);
+    assert_eq!(dedent("\n    ", '}'), None);
+  }
+
+  #[test]
+  fn whitespace_around_breaks() {
+    // Within the text, the spaces at the break go.
+    assert_eq!(whitespace_around_break("  foo ", "  bar"), (0, 2));
+    // At column 0 and inside the indentation, the line keeps its indentation.
+    assert_eq!(whitespace_around_break("", "    foo"), (0, 0));
+    assert_eq!(whitespace_around_break("  ", "  foo"), (2, 0));
+    assert_eq!(whitespace_around_break("\t", "foo"), (1, 0));
+  }
+}
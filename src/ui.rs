@@ -0,0 +1,26 @@
+use std::fmt::Display;
+
+use gpui::{App, Div, PromptLevel, SharedString, Window, div, prelude::*, px, rgb};
+
+/// A clickable label; attach the click handler with `on_mouse_down`.
+pub fn button(label: impl Into<SharedString>) -> Div {
+  div()
+    .px(px(8.))
+    .bg(rgb(0xffffff))
+    .border_1()
+    .border_color(rgb(0x999999))
+    .hover(|style| style.bg(rgb(0xe4e4e4)))
+    .child(label.into())
+}
+
+/// Reports a failed operation, with `error` as the detail.
+pub fn show_error(message: &str, error: &dyn Display, window: &mut Window, cx: &mut App) {
+  let answer = window.prompt(
+    PromptLevel::Critical,
+    message,
+    Some(&error.to_string()),
+    &["OK"],
+    cx,
+  );
+  cx.background_spawn(answer).detach();
+}
@@ -0,0 +1,864 @@
+use std::{collections::VecDeque, mem};
+
+use unicode_width::UnicodeWidthChar;
+
+/// Lines kept above the screen once they scroll off it.
+const SCROLLBACK_LIMIT: usize = 10_000;
+/// The longest CSI parameter string and OSC string kept. The rest of a
+/// longer sequence is read but dropped.
+const MAX_PARAMS_LEN: usize = 256;
+const MAX_OSC_LEN: usize = 4096;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Color {
+  #[default]
+  Default,
+  /// One of the 256 xterm colors.
+  Indexed(u8),
+  Rgb(u8, u8, u8),
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Attrs {
+  pub fg: Color,
+  pub bg: Color,
+  pub bold: bool,
+  pub italic: bool,
+  pub underline: bool,
+  pub inverse: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+  pub ch: char,
+  pub attrs: Attrs,
+  /// Whether this is the blank second column of a wide character.
+  pub spacer: bool,
+}
+
+impl Default for Cell {
+  fn default() -> Self {
+    Self {
+      ch: ' ',
+      attrs: Attrs::default(),
+      spacer: false,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Default)]
+struct SavedCursor {
+  row: usize,
+  col: usize,
+  attrs: Attrs,
+}
+
+enum State {
+  Ground,
+  Escape,
+  /// An escape sequence taking one more byte, like a charset selection.
+  EscapeArgument,
+  Csi {
+    private: Option<u8>,
+    params: String,
+  },
+  Osc(Vec<u8>),
+  /// An escape inside an OSC string, which `\` terminates.
+  OscEscape(Vec<u8>),
+}
+
+/// The screen of a VT100/xterm compatible terminal, updated by feeding it
+/// the output of the program running in it.
+pub struct Screen {
+  pub rows: usize,
+  pub cols: usize,
+  lines: Vec<Vec<Cell>>,
+  /// Lines scrolled off the top of the main screen, oldest first.
+  pub scrollback: VecDeque<Vec<Cell>>,
+  /// The main screen's lines and cursor while the alternate screen is shown.
+  main_screen: Option<(Vec<Vec<Cell>>, SavedCursor)>,
+  pub cursor_row: usize,
+  pub cursor_col: usize,
+  /// Set after printing in the last column; the next character wraps.
+  wrap_pending: bool,
+  attrs: Attrs,
+  saved_cursor: SavedCursor,
+  scroll_top: usize,
+  scroll_bottom: usize,
+  autowrap: bool,
+  pub cursor_visible: bool,
+  pub app_cursor_keys: bool,
+  pub bracketed_paste: bool,
+  pub title: Option<String>,
+  state: State,
+  utf8: Vec<u8>,
+  /// Replies to queries, to write back to the program.
+  responses: Vec<u8>,
+}
+
+impl Screen {
+  pub fn new(rows: usize, cols: usize) -> Self {
+    let (rows, cols) = (rows.max(1), cols.max(1));
+    Self {
+      rows,
+      cols,
+      lines: vec![vec![Cell::default(); cols]; rows],
+      scrollback: VecDeque::new(),
+      main_screen: None,
+      cursor_row: 0,
+      cursor_col: 0,
+      wrap_pending: false,
+      attrs: Attrs::default(),
+      saved_cursor: SavedCursor::default(),
+      scroll_top: 0,
+      scroll_bottom: rows - 1,
+      autowrap: true,
+      cursor_visible: true,
+      app_cursor_keys: false,
+      bracketed_paste: false,
+      title: None,
+      state: State::Ground,
+      utf8: Vec::new(),
+      responses: Vec::new(),
+    }
+  }
+
+  /// A line by its index counting from the oldest line of scrollback.
+  pub fn line(&self, index: usize) -> Option<&[Cell]> {
+    match index.checked_sub(self.scrollback.len()) {
+      Some(row) => self.lines.get(row).map(Vec::as_slice),
+      None => self.scrollback.get(index).map(Vec::as_slice),
+    }
+  }
+
+  pub fn is_alternate_screen(&self) -> bool {
+    self.main_screen.is_some()
+  }
+
+  /// Resizes the screen. Lines that no longer fit above the cursor go to the
+  /// scrollback.
+  pub fn resize(&mut self, rows: usize, cols: usize) {
+    let (rows, cols) = (rows.max(1), cols.max(1));
+    for line in &mut self.lines {
+      line.resize(cols, Cell::default());
+    }
+    if let Some((lines, _)) = &mut self.main_screen {
+      for line in lines.iter_mut() {
+        line.resize(cols, Cell::default());
+      }
+      lines.resize(rows, vec![Cell::default(); cols]);
+    }
+    while self.lines.len() > rows {
+      if self.cursor_row > 0 && self.cursor_row >= rows {
+        let line = self.lines.remove(0);
+        self.push_scrollback(line);
+        self.cursor_row -= 1;
+      } else {
+        self.lines.pop();
+      }
+    }
+    self.lines.resize(rows, vec![Cell::default(); cols]);
+    self.rows = rows;
+    self.cols = cols;
+    self.scroll_top = 0;
+    self.scroll_bottom = rows - 1;
+    self.cursor_row = self.cursor_row.min(rows - 1);
+    self.cursor_col = self.cursor_col.min(cols - 1);
+    self.wrap_pending = false;
+  }
+
+  /// Interprets `bytes` of program output, returning any replies to write
+  /// back to the program.
+  pub fn advance(&mut self, bytes: &[u8]) -> Vec<u8> {
+    for &byte in bytes {
+      self.advance_byte(byte);
+    }
+    mem::take(&mut self.responses)
+  }
+
+  fn advance_byte(&mut self, byte: u8) {
+    match mem::replace(&mut self.state, State::Ground) {
+      State::Ground => self.ground(byte),
+      State::Escape => self.escape(byte),
+      State::EscapeArgument => {}
+      State::Csi {
+        private,
+        mut params,
+      } => match byte {
+        b'0'..=b'9' | b';' | b':' => {
+          if params.len() < MAX_PARAMS_LEN {
+            params.push(byte as char);
+          }
+          self.state = State::Csi { private, params };
+        }
+        b'<'..=b'?' if params.is_empty() && private.is_none() => {
+          self.state = State::Csi {
+            private: Some(byte),
+            params,
+          };
+        }
+        // Intermediate bytes aren't used by any sequence handled here.
+        b' '..=b'/' => self.state = State::Csi { private, params },
+        0x40..=0x7e => self.csi(private, &params, byte),
+        0x1b => self.state = State::Escape,
+        _ => self.control(byte),
+      },
+      State::Osc(mut string) => match byte {
+        0x07 => self.osc(&string),
+        0x1b => self.state = State::OscEscape(string),
+        _ => {
+          if string.len() < MAX_OSC_LEN {
+            string.push(byte);
+          }
+          self.state = State::Osc(string);
+        }
+      },
+      State::OscEscape(string) => {
+        self.osc(&string);
+        if byte != b'\\' {
+          self.advance_byte(byte);
+        }
+      }
+    }
+  }
+
+  fn ground(&mut self, byte: u8) {
+    if byte < 0x80 {
+      self.utf8.clear();
+      match byte {
+        0x1b => self.state = State::Escape,
+        0x20..=0x7e => self.print(byte as char),
+        _ => self.control(byte),
+      }
+      return;
+    }
+    if byte & 0xc0 != 0x80 {
+      self.utf8.clear();
+    }
+    self.utf8.push(byte);
+    let expected = match self.utf8[0] {
+      0xc0..=0xdf => 2,
+      0xe0..=0xef => 3,
+      0xf0..=0xf7 => 4,
+      _ => 1,
+    };
+    if self.utf8.len() >= expected {
+      let ch = std::str::from_utf8(&self.utf8)
+        .ok()
+        .and_then(|text| text.chars().next())
+        .unwrap_or(char::REPLACEMENT_CHARACTER);
+      self.utf8.clear();
+      self.print(ch);
+    }
+  }
+
+  fn control(&mut self, byte: u8) {
+    match byte {
+      0x08 => {
+        self.cursor_col = self.cursor_col.saturating_sub(1);
+        self.wrap_pending = false;
+      }
+      0x09 => {
+        self.cursor_col = ((self.cursor_col / 8 + 1) * 8).min(self.cols - 1);
+        self.wrap_pending = false;
+      }
+      0x0a..=0x0c => self.linefeed(),
+      0x0d => {
+        self.cursor_col = 0;
+        self.wrap_pending = false;
+      }
+      _ => {}
+    }
+  }
+
+  fn escape(&mut self, byte: u8) {
+    match byte {
+      b'[' => {
+        self.state = State::Csi {
+          private: None,
+          params: String::new(),
+        }
+      }
+      b']' => self.state = State::Osc(Vec::new()),
+      b'(' | b')' | b'*' | b'+' | b'#' | b'%' => self.state = State::EscapeArgument,
+      b'7' => self.save_cursor(),
+      b'8' => self.restore_cursor(),
+      b'D' => self.linefeed(),
+      b'E' => {
+        self.linefeed();
+        self.cursor_col = 0;
+      }
+      b'M' => self.reverse_index(),
+      b'c' => *self = Self::new(self.rows, self.cols),
+      _ => {}
+    }
+  }
+
+  fn osc(&mut self, string: &[u8]) {
+    let string = String::from_utf8_lossy(string);
+    if let Some((kind, title)) = string.split_once(';')
+      && (kind == "0" || kind == "2")
+    {
+      self.title = Some(title.to_string());
+    }
+  }
+
+  fn print(&mut self, ch: char) {
+    // Cells hold a single character, so zero width ones like combining
+    // marks are dropped.
+    let width = match ch.width() {
+      Some(0) => return,
+      width => width.unwrap_or(1).min(self.cols),
+    };
+    if self.wrap_pending && self.autowrap {
+      self.cursor_col = 0;
+      self.linefeed();
+    }
+    self.wrap_pending = false;
+    // A wide character that doesn't fit in the rest of the line goes on the
+    // next one.
+    if self.cursor_col + width > self.cols {
+      if self.autowrap {
+        self.cursor_col = 0;
+        self.linefeed();
+      } else {
+        self.cursor_col = self.cols - width;
+      }
+    }
+    let (row, col) = (self.cursor_row, self.cursor_col);
+    self.split_wide_chars(row, col..col + width);
+    let cell = Cell {
+      ch,
+      attrs: self.attrs,
+      spacer: false,
+    };
+    self.lines[row][col] = cell;
+    if width == 2 {
+      self.lines[row][col + 1] = Cell {
+        ch: ' ',
+        spacer: true,
+        ..cell
+      };
+    }
+    if col + width < self.cols {
+      self.cursor_col = col + width;
+    } else {
+      self.cursor_col = self.cols - 1;
+      self.wrap_pending = true;
+    }
+  }
+
+  /// Blanks the halves of wide characters outside `cols` whose other half is
+  /// inside it, before those cells are overwritten.
+  fn split_wide_chars(&mut self, row: usize, cols: std::ops::Range<usize>) {
+    let blank = self.blank_cell();
+    let line = &mut self.lines[row];
+    if line.get(cols.start).is_some_and(|cell| cell.spacer) && cols.start > 0 {
+      line[cols.start - 1] = blank;
+    }
+    if line.get(cols.end).is_some_and(|cell| cell.spacer) {
+      line[cols.end] = blank;
+    }
+  }
+
+  fn linefeed(&mut self) {
+    self.wrap_pending = false;
+    if self.cursor_row == self.scroll_bottom {
+      self.scroll_up(1);
+    } else if self.cursor_row + 1 < self.rows {
+      self.cursor_row += 1;
+    }
+  }
+
+  fn reverse_index(&mut self) {
+    self.wrap_pending = false;
+    if self.cursor_row == self.scroll_top {
+      self.scroll_down(1);
+    } else {
+      self.cursor_row = self.cursor_row.saturating_sub(1);
+    }
+  }
+
+  fn blank_cell(&self) -> Cell {
+    Cell {
+      ch: ' ',
+      attrs: Attrs {
+        bg: self.attrs.bg,
+        ..Attrs::default()
+      },
+      spacer: false,
+    }
+  }
+
+  fn blank_line(&self) -> Vec<Cell> {
+    vec![self.blank_cell(); self.cols]
+  }
+
+  fn push_scrollback(&mut self, line: Vec<Cell>) {
+    if self.scrollback.len() == SCROLLBACK_LIMIT {
+      self.scrollback.pop_front();
+    }
+    self.scrollback.push_back(line);
+  }
+
+  /// Scrolls the scroll region up, keeping lines leaving the top of the main
+  /// screen as scrollback.
+  fn scroll_up(&mut self, count: usize) {
+    for _ in 0..count.min(self.scroll_bottom + 1 - self.scroll_top) {
+      let line = self.lines.remove(self.scroll_top);
+      if self.scroll_top == 0 && self.main_screen.is_none() {
+        self.push_scrollback(line);
+      }
+      self.lines.insert(self.scroll_bottom, self.blank_line());
+    }
+  }
+
+  fn scroll_down(&mut self, count: usize) {
+    for _ in 0..count.min(self.scroll_bottom + 1 - self.scroll_top) {
+      self.lines.remove(self.scroll_bottom);
+      self.lines.insert(self.scroll_top, self.blank_line());
+    }
+  }
+
+  fn save_cursor(&mut self) {
+    self.saved_cursor = SavedCursor {
+      row: self.cursor_row,
+      col: self.cursor_col,
+      attrs: self.attrs,
+    };
+  }
+
+  fn restore_cursor(&mut self) {
+    let saved = self.saved_cursor;
+    self.cursor_row = saved.row.min(self.rows - 1);
+    self.cursor_col = saved.col.min(self.cols - 1);
+    self.attrs = saved.attrs;
+    self.wrap_pending = false;
+  }
+
+  fn move_cursor(&mut self, row: usize, col: usize) {
+    self.cursor_row = row.min(self.rows - 1);
+    self.cursor_col = col.min(self.cols - 1);
+    self.wrap_pending = false;
+  }
+
+  fn erase(&mut self, row: usize, cols: std::ops::Range<usize>) {
+    let blank = self.blank_cell();
+    let end = cols.end.min(self.cols);
+    let start = cols.start.min(end);
+    self.split_wide_chars(row, start..end);
+    self.lines[row][start..end].fill(blank);
+  }
+
+  fn set_alternate_screen(&mut self, alternate: bool) {
+    if alternate && self.main_screen.is_none() {
+      self.save_cursor();
+      let lines = mem::replace(
+        &mut self.lines,
+        vec![vec![Cell::default(); self.cols]; self.rows],
+      );
+      self.main_screen = Some((lines, self.saved_cursor));
+    } else if !alternate && let Some((lines, cursor)) = self.main_screen.take() {
+      self.lines = lines;
+      self.saved_cursor = cursor;
+      self.restore_cursor();
+    }
+  }
+
+  fn csi(&mut self, private: Option<u8>, params: &str, command: u8) {
+    let params = params
+      .split(';')
+      .map(|param| {
+        param
+          .split(':')
+          .next()
+          .unwrap_or("")
+          .parse::<usize>()
+          .unwrap_or(0)
+      })
+      .collect::<Vec<_>>();
+    let param = |ix: usize| params.get(ix).copied().unwrap_or(0);
+    // Counts and positions treat a missing or zero parameter as one.
+    let count = |ix: usize| param(ix).max(1);
+    let (row, col) = (self.cursor_row, self.cursor_col);
+
+    if private == Some(b'?') {
+      let enable = match command {
+        b'h' => true,
+        b'l' => false,
+        _ => return,
+      };
+      for &mode in &params {
+        match mode {
+          1 => self.app_cursor_keys = enable,
+          7 => self.autowrap = enable,
+          25 => self.cursor_visible = enable,
+          47 | 1047 | 1049 => self.set_alternate_screen(enable),
+          2004 => self.bracketed_paste = enable,
+          _ => {}
+        }
+      }
+      return;
+    }
+    if private.is_some() {
+      return;
+    }
+
+    match command {
+      b'A' => self.move_cursor(row.saturating_sub(count(0)), col),
+      b'B' | b'e' => self.move_cursor(row.saturating_add(count(0)), col),
+      b'C' | b'a' => self.move_cursor(row, col.saturating_add(count(0))),
+      b'D' => self.move_cursor(row, col.saturating_sub(count(0))),
+      b'E' => self.move_cursor(row.saturating_add(count(0)), 0),
+      b'F' => self.move_cursor(row.saturating_sub(count(0)), 0),
+      b'G' | b'`' => self.move_cursor(row, count(0) - 1),
+      b'd' => self.move_cursor(count(0) - 1, col),
+      b'H' | b'f' => self.move_cursor(count(0) - 1, count(1) - 1),
+      b'J' => {
+        let rows = match param(0) {
+          0 => {
+            self.erase(row, col..self.cols);
+            row + 1..self.rows
+          }
+          1 => {
+            self.erase(row, 0..col + 1);
+            0..row
+          }
+          2 => 0..self.rows,
+          3 => {
+            self.scrollback.clear();
+            0..0
+          }
+          _ => 0..0,
+        };
+        for row in rows {
+          self.erase(row, 0..self.cols);
+        }
+      }
+      b'K' => match param(0) {
+        0 => self.erase(row, col..self.cols),
+        1 => self.erase(row, 0..col + 1),
+        2 => self.erase(row, 0..self.cols),
+        _ => {}
+      },
+      b'L' | b'M' if (self.scroll_top..=self.scroll_bottom).contains(&row) => {
+        let top = mem::replace(&mut self.scroll_top, row);
+        if command == b'L' {
+          self.scroll_down(count(0));
+        } else {
+          self.scroll_up_in_place(count(0));
+        }
+        self.scroll_top = top;
+        self.cursor_col = 0;
+      }
+      b'@' => {
+        let blank = self.blank_cell();
+        let line = &mut self.lines[row];
+        for _ in 0..count(0).min(self.cols - col) {
+          line.pop();
+          line.insert(col, blank);
+        }
+      }
+      b'P' => {
+        let blank = self.blank_cell();
+        let line = &mut self.lines[row];
+        for _ in 0..count(0).min(self.cols - col) {
+          line.remove(col);
+          line.push(blank);
+        }
+      }
+      b'X' => self.erase(row, col..col.saturating_add(count(0))),
+      b'S' => self.scroll_up_in_place(count(0)),
+      b'T' => self.scroll_down(count(0)),
+      b'm' => self.sgr(&params),
+      b'r' => {
+        let top = count(0) - 1;
+        let bottom = match param(1) {
+          0 => self.rows,
+          bottom => bottom.min(self.rows),
+        } - 1;
+        if top < bottom {
+          self.scroll_top = top;
+          self.scroll_bottom = bottom;
+          self.move_cursor(0, 0);
+        }
+      }
+      b's' => self.save_cursor(),
+      b'u' => self.restore_cursor(),
+      b'n' => match param(0) {
+        5 => self.responses.extend_from_slice(b"\x1b[0n"),
+        6 => self
+          .responses
+          .extend_from_slice(format!("\x1b[{};{}R", row + 1, col + 1).as_bytes()),
+        _ => {}
+      },
+      b'c' => self.responses.extend_from_slice(b"\x1b[?1;2c"),
+      _ => {}
+    }
+  }
+
+  /// Scrolls the scroll region up without keeping scrollback, for deleting
+  /// lines.
+  fn scroll_up_in_place(&mut self, count: usize) {
+    for _ in 0..count.min(self.scroll_bottom + 1 - self.scroll_top) {
+      self.lines.remove(self.scroll_top);
+      self.lines.insert(self.scroll_bottom, self.blank_line());
+    }
+  }
+
+  /// Select Graphic Rendition: colors and text styles.
+  fn sgr(&mut self, params: &[usize]) {
+    let mut params = params.iter().copied();
+    while let Some(param) = params.next() {
+      match param {
+        0 => self.attrs = Attrs::default(),
+        1 => self.attrs.bold = true,
+        3 => self.attrs.italic = true,
+        4 => self.attrs.underline = true,
+        7 => self.attrs.inverse = true,
+        22 => self.attrs.bold = false,
+        23 => self.attrs.italic = false,
+        24 => self.attrs.underline = false,
+        27 => self.attrs.inverse = false,
+        30..=37 => self.attrs.fg = Color::Indexed(param as u8 - 30),
+        38 => self.attrs.fg = extended_color(&mut params).unwrap_or_default(),
+        39 => self.attrs.fg = Color::Default,
+        40..=47 => self.attrs.bg = Color::Indexed(param as u8 - 40),
+        48 => self.attrs.bg = extended_color(&mut params).unwrap_or_default(),
+        49 => self.attrs.bg = Color::Default,
+        90..=97 => self.attrs.fg = Color::Indexed(param as u8 - 90 + 8),
+        100..=107 => self.attrs.bg = Color::Indexed(param as u8 - 100 + 8),
+        _ => {}
+      }
+    }
+  }
+}
+
+/// Reads the `5;n` or `2;r;g;b` following an extended color parameter.
+fn extended_color(params: &mut impl Iterator<Item = usize>) -> Option<Color> {
+  match params.next()? {
+    5 => Some(Color::Indexed(params.next()?.min(255) as u8)),
+    2 => Some(Color::Rgb(
+      params.next()?.min(255) as u8,
+      params.next()?.min(255) as u8,
+      params.next()?.min(255) as u8,
+    )),
+    _ => None,
+  }
+}
+
+/// The RGB value of an xterm color: the 16 ANSI colors, a 6×6×6 cube and a
+/// grayscale ramp.
+pub fn indexed_rgb(index: u8) -> u32 {
+  const ANSI: [u32; 16] = [
+    0x000000, 0xcd3131, 0x00bc00, 0x949800, 0x0451a5, 0xbc05bc, 0x0598bc, 0x555555, 0x666666,
+    0xcd3131, 0x14ce14, 0xb5ba00, 0x0451a5, 0xbc05bc, 0x0598bc, 0xa5a5a5,
+  ];
+  match index {
+    0..=15 => ANSI[index as usize],
+    16..=231 => {
+      let index = index as u32 - 16;
+      let level = |value: u32| if value == 0 { 0 } else { value * 40 + 55 };
+      level(index / 36) << 16 | level(index / 6 % 6) << 8 | level(index % 6)
+    }
+    _ => {
+      let level = (index as u32 - 232) * 10 + 8;
+      level << 16 | level << 8 | level
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn screen(rows: usize, cols: usize, output: &str) -> Screen {
+    let mut screen = Screen::new(rows, cols);
+    screen.advance(output.as_bytes());
+    screen
+  }
+
+  fn row_text(screen: &Screen, row: usize) -> String {
+    screen.lines[row]
+      .iter()
+      .filter(|cell| !cell.spacer)
+      .map(|cell| cell.ch)
+      .collect::<String>()
+      .trim_end()
+      .to_string()
+  }
+
+  #[test]
+  fn cursor_motion() {
+    let mut screen = screen(5, 10, "\x1b[3;4Hx");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (2, 4));
+    assert_eq!(row_text(&screen, 2), "   x");
+
+    screen.advance(b"\x1b[2A\x1b[3D");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (0, 1));
+    screen.advance(b"\x1b[B\x1b[2C");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (1, 3));
+    screen.advance(b"\x1b[5G\x1b[4d");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (3, 4));
+
+    // Moves stop at the edges, however large the count.
+    screen.advance(b"\x1b[99999999999999999999B\x1b[18446744073709551615C");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (4, 9));
+    screen.advance(b"\x1b[18446744073709551615E\x1b[18446744073709551615X");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (4, 0));
+  }
+
+  #[test]
+  fn cursor_position_report() {
+    let mut screen = screen(5, 10, "\x1b[2;3H");
+    assert_eq!(screen.advance(b"\x1b[6n"), b"\x1b[2;3R");
+  }
+
+  #[test]
+  fn wrapping_and_scrolling() {
+    let screen = screen(2, 3, "abcdefg");
+    assert_eq!(screen.scrollback.len(), 1);
+    assert_eq!(
+      screen.scrollback[0]
+        .iter()
+        .map(|cell| cell.ch)
+        .collect::<String>(),
+      "abc"
+    );
+    assert_eq!(row_text(&screen, 0), "def");
+    assert_eq!(row_text(&screen, 1), "g");
+  }
+
+  #[test]
+  fn scroll_region() {
+    let mut screen = screen(4, 5, "top\r\na\r\nb\r\nbot");
+    screen.advance(b"\x1b[2;3r\x1b[3;1H\nc");
+    assert_eq!(row_text(&screen, 0), "top");
+    assert_eq!(row_text(&screen, 1), "b");
+    assert_eq!(row_text(&screen, 2), "c");
+    assert_eq!(row_text(&screen, 3), "bot");
+    // Lines scrolled out of a region below the top aren't history.
+    assert!(screen.scrollback.is_empty());
+
+    screen.advance(b"\x1b[2;1H\x1bMd");
+    assert_eq!(row_text(&screen, 1), "d");
+    assert_eq!(row_text(&screen, 2), "b");
+    assert_eq!(row_text(&screen, 3), "bot");
+  }
+
+  #[test]
+  fn alternate_screen() {
+    let mut screen = screen(3, 5, "main\r\nx");
+    screen.advance(b"\x1b[?1049h");
+    assert!(screen.is_alternate_screen());
+    assert_eq!(row_text(&screen, 0), "");
+    screen.advance(b"\x1b[Halt\r\n\r\n\n\n");
+    assert!(screen.scrollback.is_empty());
+
+    screen.advance(b"\x1b[?1049l");
+    assert!(!screen.is_alternate_screen());
+    assert_eq!(row_text(&screen, 0), "main");
+    assert_eq!(row_text(&screen, 1), "x");
+    assert_eq!((screen.cursor_row, screen.cursor_col), (1, 1));
+  }
+
+  #[test]
+  fn graphic_rendition() {
+    let screen = screen(
+      1,
+      10,
+      "\x1b[1;31ma\x1b[38;5;200;48;2;1;2;3mb\x1b[0mc\x1b[7;94md",
+    );
+    let attrs = |col: usize| screen.lines[0][col].attrs;
+    assert_eq!(
+      attrs(0),
+      Attrs {
+        fg: Color::Indexed(1),
+        bold: true,
+        ..Attrs::default()
+      }
+    );
+    assert_eq!(
+      attrs(1),
+      Attrs {
+        fg: Color::Indexed(200),
+        bg: Color::Rgb(1, 2, 3),
+        bold: true,
+        ..Attrs::default()
+      }
+    );
+    assert_eq!(attrs(2), Attrs::default());
+    assert_eq!(
+      attrs(3),
+      Attrs {
+        fg: Color::Indexed(12),
+        inverse: true,
+        ..Attrs::default()
+      }
+    );
+  }
+
+  #[test]
+  fn utf8_split_across_reads() {
+    let mut screen = Screen::new(1, 10);
+    let bytes = "é€😀".as_bytes();
+    for byte in bytes {
+      screen.advance(&[*byte]);
+    }
+    assert_eq!(row_text(&screen, 0), "é€😀");
+    screen.advance(&[0xe2, 0x82]);
+    screen.advance(b"a");
+    assert_eq!(row_text(&screen, 0), "é€😀a");
+  }
+
+  #[test]
+  fn wide_characters() {
+    let mut screen = screen(2, 5, "a中b");
+    assert_eq!(row_text(&screen, 0), "a中b");
+    assert!(screen.lines[0][2].spacer);
+    assert_eq!(screen.cursor_col, 4);
+
+    // One that doesn't fit at the end of the line wraps whole.
+    screen.advance("文".as_bytes());
+    assert_eq!(row_text(&screen, 0), "a中b");
+    assert_eq!(row_text(&screen, 1), "文");
+    assert_eq!(screen.cursor_col, 2);
+
+    // Overwriting half of one blanks the other half.
+    screen.advance(b"\x1b[1;3Hx");
+    assert_eq!(row_text(&screen, 0), "a xb");
+    assert!(!screen.lines[0][2].spacer);
+  }
+
+  #[test]
+  fn zero_width_characters_are_dropped() {
+    let screen = screen(1, 5, "e\u{301}x");
+    assert_eq!(row_text(&screen, 0), "ex");
+  }
+
+  #[test]
+  fn long_sequences_are_bounded() {
+    let mut screen = Screen::new(2, 10);
+    let params = ";".repeat(100_000);
+    screen.advance(format!("\x1b[{params}").as_bytes());
+    assert!(matches!(&screen.state, State::Csi { params, .. } if params.len() == MAX_PARAMS_LEN));
+    screen.advance(b"mx");
+    assert_eq!(row_text(&screen, 0), "x");
+
+    let title = "t".repeat(100_000);
+    screen.advance(format!("\x1b]2;{title}").as_bytes());
+    assert!(matches!(&screen.state, State::Osc(string) if string.len() == MAX_OSC_LEN));
+    screen.advance(b"\x07y");
+    assert_eq!(
+      screen.title.as_ref().map(String::len),
+      Some(MAX_OSC_LEN - 2)
+    );
+    assert_eq!(row_text(&screen, 0), "xy");
+  }
+
+  #[test]
+  fn title_from_osc() {
+    let screen = screen(1, 5, "\x1b]0;héllo\x1b\\");
+    assert_eq!(screen.title.as_deref(), Some("héllo"));
+  }
+}
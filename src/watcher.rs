@@ -0,0 +1,58 @@
+use std::{
+  fs::{self, Metadata},
+  io,
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+
+/// How often open files are compared with the disk.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What identifies a version of a file on disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+  modified: Option<SystemTime>,
+  len: u64,
+  #[cfg(unix)]
+  inode: u64,
+}
+
+impl FileStamp {
+  pub fn new(metadata: &Metadata) -> Self {
+    Self {
+      modified: metadata.modified().ok(),
+      len: metadata.len(),
+      #[cfg(unix)]
+      inode: std::os::unix::fs::MetadataExt::ino(metadata),
+    }
+  }
+
+  pub fn read(path: &Path) -> io::Result<Self> {
+    fs::metadata(path).map(|metadata| Self::new(&metadata))
+  }
+}
+
+/// Looks for the file `stamp` was taken from under a new name in the
+/// directory `path` was in. Files are matched by inode, so renames can only be
+/// followed on Unix.
+pub fn find_renamed(path: &Path, stamp: &FileStamp) -> Option<PathBuf> {
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::read_dir(path.parent()?)
+      .ok()?
+      .flatten()
+      .find(|entry| {
+        entry
+          .metadata()
+          .is_ok_and(|metadata| metadata.is_file() && metadata.ino() == stamp.inode)
+      })
+      .map(|entry| entry.path())
+  }
+  #[cfg(not(unix))]
+  {
+    let _ = (path, stamp);
+    None
+  }
+}
@@ -1,35 +1,1455 @@
+use std::path::{Path, PathBuf};
+
 use gpui::{
-  App, Context, Entity, FocusHandle, Focusable, KeyBinding, Window, div, prelude::*, rgb,
+  AnyView, App, Context, DismissEvent, Entity, EntityInputHandler, FocusHandle, Focusable,
+  KeyBinding, Keystroke, KeystrokeEvent, ManagedView, PathPromptOptions, PromptLevel, SharedString,
+  Subscription, Task, Window, WindowBounds, actions, div, prelude::*, px, rgb,
 };
 
-use crate::text_input::{
-  AltLeft, AltRight, Backspace, CmdLeft, CmdRight, Copy, Cut, Delete, End, Home, Left, Paste,
-  Right, SelectAll, SelectEnd, SelectLeft, SelectRight, SelectStart, SelectWordLeft,
-  SelectWordRight, ShowCharacterPalette, TextInput,
+use crate::{
+  clipboard_history::{ClipboardHistory, PasteFromHistory},
+  diagnostics::Problem,
+  diff_view::DiffView,
+  encoding::Encoding,
+  go_to_line::{self, GoToLine},
+  language::Language,
+  line_ending::LineEnding,
+  macros::{
+    self, MacroStep, ReplayMacro, ReplayMacroTimes, RunMacro, RunSavedMacro, SaveMacro,
+    ToggleMacroRecording,
+  },
+  pane::{Pane, PaneEvent},
+  picker::Picker,
+  project_panel::{
+    self, DeleteEntry, DuplicateEntry, NewDirectory, NewEntry, ProjectPanel, ProjectPanelEvent,
+    RenameEntry, RevealActiveFile, ToggleProjectPanel,
+  },
+  prompt::Prompt,
+  recovery::{self, Recovery, Writes},
+  session::{PaneState, Session, TabState},
+  settings::{KeymapPreset, Settings},
+  snippets::{InsertSnippet, Snippets},
+  status_bar::{SelectEncoding, SelectIndentation, SelectLanguage, SelectLineEnding, StatusBar},
+  task_panel::{RerunLastTask, RunTask, TaskPanel, TaskPanelEvent},
+  tasks::{self, TaskDefinition},
+  terminal_panel::{NewTerminal, TerminalPanel, TerminalPanelEvent, ToggleTerminalPanel},
+  text_input::{
+    AltLeft, AltRight, Backspace, Cancel, CmdLeft, CmdRight, ConvertIndentationToSpaces,
+    ConvertIndentationToTabs, ConvertLineEndingsToCrlf, ConvertLineEndingsToLf, ConvertToCamelCase,
+    ConvertToKebabCase, ConvertToLowerCase, ConvertToPascalCase, ConvertToSnakeCase,
+    ConvertToTitleCase, ConvertToUpperCase, Copy, Cut, Delete, DeleteLine, Down, DuplicateLine,
+    End, ExchangePointAndMark, FoldAll, FoldAtLevel1, FoldAtLevel2, FoldAtLevel3, FoldAtLevel4,
+    FoldAtLevel5, Home, Indent, InsertLineAbove, InsertLineBelow, JoinLines, JumpToMatchingBracket,
+    KillLine, KillRegion, KillRingSave, Left, MoveLineDown, MoveLineUp, Newline, Outdent, Paste,
+    Quit, Redo, ReverseLines, Right, SelectAll, SelectDown, SelectEnd, SelectInsideBrackets,
+    SelectLeft, SelectRight, SelectStart, SelectUp, SelectWordLeft, SelectWordRight, SetMark,
+    ShowCharacterPalette, SortLines, TextInput, ToggleBlockComment, ToggleComment, ToggleFold,
+    TrimTrailingWhitespace, Undo, UnfoldAll, UniqueLines, Up, Yank, YankPop,
+  },
+  ui::show_error,
+  watcher,
 };
 
+actions!(
+  zeta,
+  [
+    Open,
+    Save,
+    SaveAs,
+    NewFile,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    SplitRight,
+    FocusNextPane,
+    FocusPreviousPane
+  ]
+);
+
+/// Indentation choices offered by the status bar, as `(hard_tabs, tab_size)`.
+const INDENTATION_CHOICES: [(bool, usize); 6] = [
+  (false, 2),
+  (false, 4),
+  (false, 8),
+  (true, 2),
+  (true, 4),
+  (true, 8),
+];
+
+/// A dialog shown over the editor until it emits `DismissEvent`.
+pub struct Modal {
+  view: AnyView,
+  focus_handle: FocusHandle,
+}
+
 pub struct Zeta {
-  pub text_input: Entity<TextInput>,
+  /// The side by side splits, left to right.
+  pub panes: Vec<Entity<Pane>>,
+  pub active_pane: usize,
+  pub status_bar: Entity<StatusBar>,
   pub focus_handle: FocusHandle,
+  pub modal: Option<Modal>,
+  pub project_panel: Entity<ProjectPanel>,
+  pub project_panel_open: bool,
+  pub terminal_panel: Entity<TerminalPanel>,
+  pub terminal_panel_open: bool,
+  pub task_panel: Entity<TaskPanel>,
+  pub task_panel_open: bool,
+  /// The steps recorded so far while recording a macro.
+  macro_recording: Option<Vec<MacroStep>>,
+  /// The last macro recorded, for replaying.
+  last_macro: Option<Vec<MacroStep>>,
+  /// The directory the session is kept for.
+  workspace: PathBuf,
+  window_bounds: WindowBounds,
+  recovery: Recovery,
+  _subscriptions: Vec<Subscription>,
+  _watch_task: Task<()>,
+  _snapshot_task: Task<()>,
 }
 
 impl Zeta {
-  pub fn new(cx: &mut Context<Self>) -> Self {
-    let text_input = cx.new(|cx| TextInput {
-      focus_handle: cx.focus_handle(),
-      content: "".into(),
-      placeholder: "Type here...".into(),
-      selected_range: 0..0,
-      selection_reversed: false,
-      marked_range: None,
-      last_layout: None,
-      last_bounds: None,
-      is_selecting: false,
+  /// Restores `session`, along with the unsaved buffers left by the previous
+  /// run. Those belonging to a restored tab replace its file's content, the
+  /// others are added to the first pane.
+  pub fn new(
+    workspace: PathBuf,
+    session: Session,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) -> Self {
+    let mut recovery = Recovery::new();
+    let mut snapshots = recovery.load();
+    let mut panes = session
+      .panes
+      .into_iter()
+      .map(|pane| {
+        let active = pane.active.min(pane.tabs.len() - 1);
+        let items = pane
+          .tabs
+          .into_iter()
+          .map(|tab| {
+            let editor = Self::new_editor(cx);
+            match snapshots
+              .iter()
+              .position(|snapshot| snapshot.path.as_ref() == Some(&tab.path))
+            {
+              Some(ix) => {
+                let snapshot = snapshots.remove(ix);
+                editor.update(cx, |editor, cx| {
+                  editor.restore(snapshot, cx);
+                  editor.restore_position(tab.cursor, px(tab.scroll_y), cx);
+                });
+              }
+              None => Self::open_tab(editor.clone(), tab, window, cx),
+            }
+            editor
+          })
+          .collect::<Vec<_>>();
+        (items, active)
+      })
+      .collect::<Vec<_>>();
+    let restored = snapshots.into_iter().map(|snapshot| {
+      let editor = Self::new_editor(cx);
+      editor.update(cx, |editor, cx| editor.restore(snapshot, cx));
+      editor
+    });
+    match panes.first_mut() {
+      Some((items, _)) => items.extend(restored),
+      None => {
+        let mut items = restored.collect::<Vec<_>>();
+        if items.is_empty() {
+          items.push(Self::new_editor(cx));
+        }
+        panes.push((items, 0));
+      }
+    }
+    let panes = panes
+      .into_iter()
+      .map(|(items, active)| {
+        let pane = cx.new(|cx| Pane::new(items, active, cx));
+        cx.subscribe_in(&pane, window, Self::handle_pane_event)
+          .detach();
+        pane
+      })
+      .collect::<Vec<_>>();
+    let active_pane = session.active_pane.min(panes.len() - 1);
+    let active_editor = panes[active_pane].read(cx).active_item();
+    let status_bar = cx.new(|cx| StatusBar::new(active_editor, cx));
+    let project_panel = cx.new(|cx| ProjectPanel::new(workspace.clone(), cx));
+    cx.subscribe_in(&project_panel, window, Self::handle_project_panel_event)
+      .detach();
+    let terminal_panel = cx.new(|cx| TerminalPanel::new(workspace.clone(), cx));
+    cx.subscribe_in(&terminal_panel, window, Self::handle_terminal_panel_event)
+      .detach();
+    let task_panel = cx.new(|_| TaskPanel::new(workspace.clone()));
+    cx.subscribe_in(&task_panel, window, Self::handle_task_panel_event)
+      .detach();
+
+    let subscriptions = vec![
+      cx.observe_window_bounds(window, |this, window, _| {
+        this.window_bounds = window.window_bounds();
+      }),
+      // Snapshotting unsaved buffers on quit means quitting never asks to
+      // save; they are restored on the next launch.
+      cx.on_app_quit(|this, cx| {
+        this.prepare_snapshots(cx).write().ok();
+        this.session(cx).save(&this.workspace).ok();
+        async {}
+      }),
+      cx.observe_keystrokes(|this, event, _, cx| this.record_keystroke(event, cx)),
+    ];
+    let watch_task = cx.spawn(async move |this, cx| {
+      loop {
+        cx.background_executor().timer(watcher::POLL_INTERVAL).await;
+        let checked = this.update(cx, |this, cx| {
+          for editor in this.editors(cx).cloned().collect::<Vec<_>>() {
+            editor.update(cx, |editor, cx| editor.check_disk(cx));
+          }
+        });
+        if checked.is_err() {
+          break;
+        }
+      }
+    });
+
+    let snapshot_task = cx.spawn(async move |this, cx| {
+      loop {
+        cx.background_executor()
+          .timer(recovery::SNAPSHOT_INTERVAL)
+          .await;
+        let Ok((writes, session, workspace)) = this.update(cx, |this, cx| {
+          (
+            this.prepare_snapshots(cx),
+            this.session(cx),
+            this.workspace.clone(),
+          )
+        }) else {
+          break;
+        };
+        cx.background_spawn(async move {
+          writes.write()?;
+          session.save(&workspace)
+        })
+        .await
+        .ok();
+      }
     });
 
     Self {
-      text_input,
+      panes,
+      active_pane,
+      status_bar,
       focus_handle: cx.focus_handle(),
+      modal: None,
+      project_panel,
+      project_panel_open: session.project_panel_open,
+      terminal_panel,
+      terminal_panel_open: false,
+      task_panel,
+      task_panel_open: false,
+      workspace,
+      window_bounds: window.window_bounds(),
+      recovery,
+      macro_recording: None,
+      last_macro: None,
+      _subscriptions: subscriptions,
+      _watch_task: watch_task,
+      _snapshot_task: snapshot_task,
+    }
+  }
+
+  fn new_editor(cx: &mut App) -> Entity<TextInput> {
+    cx.new(|cx| TextInput::new("Type here...", cx))
+  }
+
+  pub fn active_editor(&self, cx: &App) -> Entity<TextInput> {
+    self.panes[self.active_pane].read(cx).active_item()
+  }
+
+  fn editors<'a>(&'a self, cx: &'a App) -> impl Iterator<Item = &'a Entity<TextInput>> {
+    self.panes.iter().flat_map(|pane| &pane.read(cx).items)
+  }
+
+  /// Focuses the active editor and shows its state in the status bar.
+  pub fn activate_editor(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+    let editor = self.active_editor(cx);
+    window.focus(&editor.read(cx).focus_handle);
+    self
+      .status_bar
+      .update(cx, |status_bar, cx| status_bar.set_editor(editor, cx));
+    cx.notify();
+  }
+
+  fn handle_pane_event(
+    &mut self,
+    pane: &Entity<Pane>,
+    event: &PaneEvent,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let Some(ix) = self.panes.iter().position(|p| p == pane) else {
+      return;
+    };
+    match event {
+      PaneEvent::Activated => {
+        if ix != self.active_pane {
+          self.active_pane = ix;
+          self.activate_editor(window, cx);
+        }
+      }
+      PaneEvent::ActiveItemChanged => {
+        if ix == self.active_pane {
+          self.activate_editor(window, cx);
+        }
+      }
+      PaneEvent::CloseItem(item_ix) => {
+        let editor = pane.read(cx).items[*item_ix].clone();
+        self.close_editor(editor, window, cx);
+      }
+      PaneEvent::ShowDiff => {
+        let editor = pane.read(cx).active_item();
+        self.show_diff(editor, window, cx);
+      }
+    }
+  }
+
+  fn handle_project_panel_event(
+    &mut self,
+    _: &Entity<ProjectPanel>,
+    event: &ProjectPanelEvent,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    match event {
+      ProjectPanelEvent::OpenFile(path) => {
+        self.open_path(path, None, window, cx).detach();
+        self.activate_editor(window, cx);
+      }
+      // Editors follow their files, and those inside renamed directories.
+      ProjectPanelEvent::Renamed { from, to } => {
+        for editor in self.editors(cx).cloned().collect::<Vec<_>>() {
+          editor.update(cx, |editor, cx| {
+            let Some(rest) = editor
+              .path
+              .as_ref()
+              .and_then(|path| path.strip_prefix(from).ok())
+            else {
+              return;
+            };
+            let path = if rest.as_os_str().is_empty() {
+              to.clone()
+            } else {
+              to.join(rest)
+            };
+            editor.set_path(path, cx);
+            cx.notify();
+          });
+        }
+      }
+    }
+  }
+
+  fn toggle_project_panel(
+    &mut self,
+    _: &ToggleProjectPanel,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.project_panel_open = !self.project_panel_open;
+    if !self.project_panel_open
+      && self
+        .project_panel
+        .focus_handle(cx)
+        .contains_focused(window, cx)
+    {
+      self.activate_editor(window, cx);
+    }
+    cx.notify();
+  }
+
+  /// Moves focus between the project panel and the active editor.
+  fn toggle_project_panel_focus(
+    &mut self,
+    _: &project_panel::ToggleFocus,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let focus_handle = self.project_panel.focus_handle(cx);
+    if focus_handle.contains_focused(window, cx) {
+      self.activate_editor(window, cx);
+    } else {
+      self.project_panel_open = true;
+      window.focus(&focus_handle);
+      cx.notify();
+    }
+  }
+
+  fn reveal_active_file(
+    &mut self,
+    _: &RevealActiveFile,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let Some(path) = self.active_editor(cx).read(cx).path.clone() else {
+      return;
+    };
+    self.project_panel_open = true;
+    self
+      .project_panel
+      .update(cx, |project_panel, cx| project_panel.reveal(&path, cx));
+    window.focus(&self.project_panel.focus_handle(cx));
+    cx.notify();
+  }
+
+  fn handle_terminal_panel_event(
+    &mut self,
+    _: &Entity<TerminalPanel>,
+    event: &TerminalPanelEvent,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    match event {
+      TerminalPanelEvent::Empty => {
+        self.terminal_panel_open = false;
+        self.activate_editor(window, cx);
+        cx.notify();
+      }
+    }
+  }
+
+  /// Opens the terminal panel and focuses it, starting a terminal if there
+  /// is none. Toggling from a focused terminal closes the panel.
+  fn toggle_terminal_panel(
+    &mut self,
+    _: &ToggleTerminalPanel,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.terminal_panel_open
+      && self
+        .terminal_panel
+        .focus_handle(cx)
+        .contains_focused(window, cx)
+    {
+      self.terminal_panel_open = false;
+      self.activate_editor(window, cx);
+    } else if self.terminal_panel.read(cx).is_empty() {
+      self.new_terminal(&NewTerminal, window, cx);
+    } else {
+      self.terminal_panel_open = true;
+      self
+        .terminal_panel
+        .read(cx)
+        .focus_active_terminal(window, cx);
+    }
+    cx.notify();
+  }
+
+  fn new_terminal(&mut self, _: &NewTerminal, window: &mut Window, cx: &mut Context<Self>) {
+    let added = self.terminal_panel.update(cx, |terminal_panel, cx| {
+      terminal_panel.add_terminal(window, cx)
+    });
+    match added {
+      Ok(()) => self.terminal_panel_open = true,
+      Err(error) => show_error("Couldn't start a terminal", &error, window, cx),
+    }
+    cx.notify();
+  }
+
+  fn handle_task_panel_event(
+    &mut self,
+    _: &Entity<TaskPanel>,
+    event: &TaskPanelEvent,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    match event {
+      TaskPanelEvent::ProblemsChanged => {
+        for editor in self.editors(cx).cloned().collect::<Vec<_>>() {
+          self.update_diagnostics(&editor, cx);
+        }
+      }
+      TaskPanelEvent::OpenProblem(problem) => self.open_problem(problem, window, cx),
+      TaskPanelEvent::Close => {
+        self.task_panel_open = false;
+        cx.notify();
+      }
+    }
+  }
+
+  /// Shows the problems the last task found in the file of `editor`.
+  fn update_diagnostics(&self, editor: &Entity<TextInput>, cx: &mut App) {
+    let Some(path) = editor.read(cx).path.clone() else {
+      return;
+    };
+    let diagnostics = self.task_panel.read(cx).diagnostics_for(&path);
+    editor.update(cx, |editor, cx| {
+      editor.diagnostics = diagnostics;
+      cx.notify();
+    });
+  }
+
+  /// Opens the file of `problem` with the cursor at its position.
+  fn open_problem(&mut self, problem: &Problem, window: &mut Window, cx: &mut Context<Self>) {
+    let open = self.open_path(&problem.path, None, window, cx);
+    let path = problem.path.clone();
+    let (line, column) = (problem.diagnostic.line, problem.diagnostic.column);
+    cx.spawn_in(window, async move |this, cx| {
+      open.await;
+      this
+        .update_in(cx, |this, window, cx| {
+          let editor = this.active_editor(cx);
+          if editor.read(cx).path.as_ref() != Some(&path) {
+            return;
+          }
+          editor.update(cx, |editor, cx| {
+            let offset = editor.offset_for_line_column(line, column);
+            editor.mark = None;
+            editor.move_to(offset, cx);
+          });
+          this.activate_editor(window, cx);
+        })
+        .ok();
+    })
+    .detach();
+  }
+
+  /// Offers the tasks of the project's tasks file for running.
+  fn run_task(&mut self, _: &RunTask, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let path = tasks::tasks_path(&self.workspace);
+    let definitions = match tasks::load(&self.workspace) {
+      Ok(definitions) if !definitions.is_empty() => definitions,
+      Ok(_) => {
+        let message = format!("No tasks are defined in {}", path.display());
+        show_error(
+          &message,
+          &"Add lines like `build = cargo build`.",
+          window,
+          cx,
+        );
+        return;
+      }
+      Err(error) => {
+        let message = format!("Couldn't read {}", path.display());
+        show_error(&message, &error, window, cx);
+        return;
+      }
+    };
+    let items = definitions
+      .iter()
+      .map(|task| SharedString::from(format!("{}: {}", task.label, task.command)))
+      .collect();
+    let selected = self
+      .task_panel
+      .read(cx)
+      .last_task
+      .as_ref()
+      .and_then(|last| definitions.iter().position(|task| task == last))
+      .unwrap_or(0);
+
+    let zeta = cx.entity();
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Run Task",
+        items,
+        selected,
+        move |ix, _, cx| {
+          let task = definitions[ix].clone();
+          zeta.update(cx, |zeta, cx| zeta.start_task(task, cx));
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  fn rerun_last_task(&mut self, _: &RerunLastTask, window: &mut Window, cx: &mut Context<Self>) {
+    match self.task_panel.read(cx).last_task.clone() {
+      Some(task) => self.start_task(task, cx),
+      None => self.run_task(&RunTask, window, cx),
+    }
+  }
+
+  fn start_task(&mut self, task: TaskDefinition, cx: &mut Context<Self>) {
+    self.task_panel_open = true;
+    self
+      .task_panel
+      .update(cx, |task_panel, cx| task_panel.run(task, cx));
+    cx.notify();
+  }
+
+  /// Offers the snippets of the active editor's language for inserting in
+  /// place of its selection.
+  fn insert_snippet(&mut self, _: &InsertSnippet, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let editor = self.active_editor(cx);
+    let definitions = cx
+      .global::<Snippets>()
+      .for_language(editor.read(cx).language);
+    let items = definitions
+      .iter()
+      .map(|definition| {
+        SharedString::from(format!("{}: {}", definition.prefix, definition.description))
+      })
+      .collect();
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Insert Snippet",
+        items,
+        0,
+        move |ix, _, cx| {
+          editor.update(cx, |editor, cx| {
+            let range = editor.selected_range.clone();
+            editor.insert_snippet(&definitions[ix].body, range, cx);
+          });
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  fn paste_from_history(
+    &mut self,
+    _: &PasteFromHistory,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let entries = cx
+      .global::<ClipboardHistory>()
+      .entries()
+      .iter()
+      .cloned()
+      .collect::<Vec<_>>();
+    if entries.is_empty() {
+      return;
+    }
+    let items = entries
+      .iter()
+      .map(|entry| SharedString::from(entry.preview()))
+      .collect();
+    let editor = self.active_editor(cx);
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Paste from History",
+        items,
+        0,
+        move |ix, window, cx| {
+          editor.update(cx, |editor, cx| {
+            editor.paste_text(&entries[ix].text, window, cx)
+          });
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  fn toggle_macro_recording(
+    &mut self,
+    _: &ToggleMacroRecording,
+    _: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    match self.macro_recording.take() {
+      Some(steps) => self.last_macro = Some(steps),
+      None => self.macro_recording = Some(Vec::new()),
+    }
+    let recording = self.macro_recording.is_some();
+    self.status_bar.update(cx, |status_bar, cx| {
+      status_bar.recording_macro = recording;
+      cx.notify();
+    });
+  }
+
+  /// Records the editor action or text a keystroke produced while a macro
+  /// is being recorded. Keystrokes in dialogs aren't recorded. Only
+  /// keystrokes are seen, so text committed by an input method and actions
+  /// run from menus or pickers are missing from the macro.
+  fn record_keystroke(&mut self, event: &KeystrokeEvent, cx: &mut Context<Self>) {
+    if self.modal.is_some()
+      || !event
+        .context_stack
+        .iter()
+        .any(|context| context.contains("TextInput"))
+    {
+      return;
+    }
+    let Some(steps) = self.macro_recording.as_mut() else {
+      return;
+    };
+    let step = match &event.action {
+      Some(action) if action.name().starts_with("text_input::") => {
+        MacroStep::Action(action.name().into())
+      }
+      Some(_) => return,
+      None => {
+        let modifiers = &event.keystroke.modifiers;
+        if modifiers.control || modifiers.platform || modifiers.function {
+          return;
+        }
+        let Some(text) = event.keystroke.key_char.clone() else {
+          return;
+        };
+        // Consecutive characters are kept as one step.
+        if let Some(MacroStep::Text(last)) = steps.last_mut() {
+          last.push_str(&text);
+          return;
+        }
+        MacroStep::Text(text)
+      }
+    };
+    steps.push(step);
+    cx.notify();
+  }
+
+  fn replay_macro(&mut self, _: &ReplayMacro, window: &mut Window, cx: &mut Context<Self>) {
+    if let Some(steps) = self.last_macro.clone() {
+      self.play_macro(steps, 1, window, cx);
+    }
+  }
+
+  fn replay_macro_times(
+    &mut self,
+    _: &ReplayMacroTimes,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let Some(steps) = self.last_macro.clone() else {
+      return;
+    };
+    let zeta = cx.entity();
+    let prompt = cx.new(|cx| {
+      Prompt::new(
+        "Replay Macro",
+        "Number of times",
+        move |count, window, cx| match macros::parse_times(count) {
+          Ok(times) => zeta.update(cx, |zeta, cx| {
+            zeta.play_macro(steps.clone(), times, window, cx)
+          }),
+          Err(error) => show_error("Couldn't replay the macro", &error, window, cx),
+        },
+        cx,
+      )
+    });
+    self.open_modal(prompt, window, cx);
+  }
+
+  fn save_macro(&mut self, _: &SaveMacro, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let Some(steps) = self.last_macro.clone() else {
+      return;
+    };
+    let prompt = cx.new(|cx| {
+      Prompt::new(
+        "Save Macro",
+        "Name",
+        move |name, window, cx| {
+          if let Err(error) = macros::save(name, &steps) {
+            show_error("Couldn't save the macro", &error, window, cx);
+          }
+        },
+        cx,
+      )
+    });
+    self.open_modal(prompt, window, cx);
+  }
+
+  fn run_saved_macro(&mut self, _: &RunSavedMacro, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let names = macros::saved_names();
+    let items = names.iter().cloned().map(SharedString::from).collect();
+    let zeta = cx.entity();
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Run Macro",
+        items,
+        0,
+        move |ix, window, cx| {
+          zeta.update(cx, |zeta, cx| zeta.run_named_macro(&names[ix], window, cx));
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  fn run_macro(&mut self, action: &RunMacro, window: &mut Window, cx: &mut Context<Self>) {
+    self.run_named_macro(&action.name, window, cx);
+  }
+
+  fn run_named_macro(&mut self, name: &str, window: &mut Window, cx: &mut Context<Self>) {
+    match macros::load(name) {
+      Ok(steps) => self.play_macro(steps, 1, window, cx),
+      Err(error) => show_error(&format!("Couldn't load macro {name}"), &error, window, cx),
+    }
+  }
+
+  /// Plays `steps` in the active editor `times` times over. Editors have a
+  /// single selection, so the macro plays at its cursor. Actions are
+  /// dispatched once the current one finishes, so typed text is deferred as
+  /// well to keep the steps in order.
+  fn play_macro(
+    &mut self,
+    steps: Vec<MacroStep>,
+    times: usize,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let editor = self.active_editor(cx);
+    window.focus(&editor.read(cx).focus_handle);
+    for _ in 0..times {
+      for step in &steps {
+        match step {
+          MacroStep::Action(name) => {
+            if let Ok(action) = cx.build_action(name, None) {
+              window.dispatch_action(action, cx);
+            }
+          }
+          MacroStep::Text(text) => {
+            let editor = editor.clone();
+            let text = text.clone();
+            window.defer(cx, move |window, cx| {
+              editor.update(cx, |editor, cx| {
+                editor.replace_text_in_range(None, &text, window, cx)
+              });
+            });
+          }
+        }
+      }
+    }
+  }
+
+  /// Loads the file of `tab` into `editor`, then restores its cursor and
+  /// scroll position. Files that can no longer be read are closed.
+  fn open_tab(
+    editor: Entity<TextInput>,
+    tab: TabState,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let load = editor.update(cx, |editor, cx| editor.load(&tab.path, None, cx));
+    cx.spawn_in(window, async move |this, cx| {
+      let loaded = load.await;
+      this
+        .update_in(cx, |this, window, cx| match loaded {
+          Ok(()) => editor.update(cx, |editor, cx| {
+            editor.restore_position(tab.cursor, px(tab.scroll_y), cx)
+          }),
+          Err(_) => this.remove_editor(&editor, window, cx),
+        })
+        .ok();
+    })
+    .detach();
+  }
+
+  /// The open files of each pane, which are reopened next time the workspace
+  /// is. Untitled buffers are left to crash recovery.
+  fn session(&self, cx: &App) -> Session {
+    let mut active_pane = 0;
+    let mut panes = Vec::new();
+    for (ix, pane) in self.panes.iter().enumerate() {
+      let pane = pane.read(cx);
+      let mut active = 0;
+      let mut tabs = Vec::new();
+      for (item_ix, item) in pane.items.iter().enumerate() {
+        let editor = item.read(cx);
+        let Some(path) = editor.path.clone() else {
+          continue;
+        };
+        if item_ix == pane.active {
+          active = tabs.len();
+        }
+        tabs.push(TabState {
+          path,
+          cursor: editor.cursor_offset(),
+          scroll_y: editor.scroll_handle.offset().y.into(),
+        });
+      }
+      if tabs.is_empty() {
+        continue;
+      }
+      if ix == self.active_pane {
+        active_pane = panes.len();
+      }
+      panes.push(PaneState { tabs, active });
+    }
+    Session {
+      window: Some(self.window_bounds),
+      project_panel_open: self.project_panel_open,
+      panes,
+      active_pane,
+    }
+  }
+
+  fn prepare_snapshots(&mut self, cx: &mut Context<Self>) -> Writes {
+    let cx: &App = cx;
+    let buffers = self
+      .panes
+      .iter()
+      .flat_map(|pane| &pane.read(cx).items)
+      .map(|editor| editor.read(cx));
+    self.recovery.update(buffers)
+  }
+
+  fn add_pane(&mut self, pane: Entity<Pane>, window: &mut Window, cx: &mut Context<Self>) {
+    cx.subscribe_in(&pane, window, Self::handle_pane_event)
+      .detach();
+    self.active_pane = (self.active_pane + 1).min(self.panes.len());
+    self.panes.insert(self.active_pane, pane);
+    self.activate_editor(window, cx);
+  }
+
+  /// Opens the active file again in a new pane to the right.
+  fn split_right(&mut self, _: &SplitRight, window: &mut Window, cx: &mut Context<Self>) {
+    let current = self.active_editor(cx).read(cx);
+    let tab = current.path.clone().map(|path| TabState {
+      path,
+      cursor: current.cursor_offset(),
+      scroll_y: current.scroll_handle.offset().y.into(),
+    });
+    let editor = Self::new_editor(cx);
+    if let Some(tab) = tab {
+      Self::open_tab(editor.clone(), tab, window, cx);
+    }
+    let pane = cx.new(|cx| Pane::new(vec![editor], 0, cx));
+    self.add_pane(pane, window, cx);
+  }
+
+  fn focus_next_pane(&mut self, _: &FocusNextPane, window: &mut Window, cx: &mut Context<Self>) {
+    self.active_pane = (self.active_pane + 1) % self.panes.len();
+    self.activate_editor(window, cx);
+  }
+
+  fn focus_previous_pane(
+    &mut self,
+    _: &FocusPreviousPane,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    self.active_pane = (self.active_pane + self.panes.len() - 1) % self.panes.len();
+    self.activate_editor(window, cx);
+  }
+
+  fn new_file(&mut self, _: &NewFile, _: &mut Window, cx: &mut Context<Self>) {
+    let editor = Self::new_editor(cx);
+    self.panes[self.active_pane].update(cx, |pane, cx| pane.add_item(editor, cx));
+  }
+
+  fn next_tab(&mut self, _: &NextTab, _: &mut Window, cx: &mut Context<Self>) {
+    self.panes[self.active_pane].update(cx, |pane, cx| pane.activate_relative(1, cx));
+  }
+
+  fn previous_tab(&mut self, _: &PreviousTab, _: &mut Window, cx: &mut Context<Self>) {
+    self.panes[self.active_pane].update(cx, |pane, cx| pane.activate_relative(-1, cx));
+  }
+
+  fn close_tab(&mut self, _: &CloseTab, window: &mut Window, cx: &mut Context<Self>) {
+    let editor = self.active_editor(cx);
+    self.close_editor(editor, window, cx);
+  }
+
+  /// Closes `editor`, asking first if it has unsaved changes.
+  fn close_editor(
+    &mut self,
+    editor: Entity<TextInput>,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let discard = self.confirm_discard(&editor, window, cx);
+    cx.spawn_in(window, async move |this, cx| {
+      if discard.await {
+        this
+          .update_in(cx, |this, window, cx| {
+            this.remove_editor(&editor, window, cx)
+          })
+          .ok();
+      }
+    })
+    .detach();
+  }
+
+  /// Removes `editor` from its pane. A pane losing its last tab is closed,
+  /// unless it is the only one, which gets an untitled buffer instead.
+  fn remove_editor(
+    &mut self,
+    editor: &Entity<TextInput>,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let Some((pane_ix, item_ix)) = self.panes.iter().enumerate().find_map(|(pane_ix, pane)| {
+      let item_ix = pane.read(cx).items.iter().position(|item| item == editor)?;
+      Some((pane_ix, item_ix))
+    }) else {
+      return;
+    };
+    let pane = self.panes[pane_ix].clone();
+    if pane.read(cx).items.len() > 1 {
+      pane.update(cx, |pane, cx| pane.remove_item(item_ix, cx));
+    } else if self.panes.len() > 1 {
+      self.panes.remove(pane_ix);
+      if self.active_pane > pane_ix || self.active_pane == self.panes.len() {
+        self.active_pane -= 1;
+      }
+      self.activate_editor(window, cx);
+    } else {
+      let untitled = Self::new_editor(cx);
+      pane.update(cx, |pane, cx| {
+        pane.add_item(untitled, cx);
+        pane.remove_item(item_ix, cx);
+      });
+    }
+  }
+
+  fn show_diff(&mut self, editor: Entity<TextInput>, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let diff_view = cx.new(|cx| DiffView::new(editor, cx));
+    self.open_modal(diff_view, window, cx);
+  }
+
+  /// Shows `view` as the modal, returning focus to the editor once it is
+  /// dismissed.
+  fn open_modal<V: ManagedView>(
+    &mut self,
+    view: Entity<V>,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    cx.subscribe_in(&view, window, |this, _, _: &DismissEvent, window, cx| {
+      this.modal = None;
+      this.activate_editor(window, cx);
+      cx.notify();
+    })
+    .detach();
+    let focus_handle = view.focus_handle(cx);
+    window.focus(&focus_handle);
+    self.modal = Some(Modal {
+      view: view.into(),
+      focus_handle,
+    });
+    cx.notify();
+  }
+
+  /// Focuses the open modal instead of opening another one.
+  fn focus_modal(&self, window: &mut Window) -> bool {
+    match self.modal.as_ref() {
+      Some(modal) => {
+        window.focus(&modal.focus_handle);
+        true
+      }
+      None => false,
+    }
+  }
+
+  fn toggle_go_to_line(
+    &mut self,
+    _: &go_to_line::Toggle,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let editor = self.active_editor(cx);
+    let go_to_line = cx.new(|cx| GoToLine::new(editor, cx));
+    self.open_modal(go_to_line, window, cx);
+  }
+
+  fn select_language(&mut self, _: &SelectLanguage, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let current = self.active_editor(cx).read(cx).language;
+    let languages = Language::all().collect::<Vec<_>>();
+    let items = languages
+      .iter()
+      .map(|language| SharedString::from(language.name))
+      .collect();
+    let selected = languages
+      .iter()
+      .position(|language| std::ptr::eq(*language, current))
+      .unwrap_or(0);
+
+    let editor = self.active_editor(cx);
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Select Language",
+        items,
+        selected,
+        move |ix, _, cx| {
+          editor.update(cx, |editor, cx| {
+            editor.set_language(languages[ix]);
+            cx.notify();
+          })
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  fn select_line_ending(
+    &mut self,
+    _: &SelectLineEnding,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let current = self.active_editor(cx).read(cx).line_ending;
+    let items = LineEnding::ALL
+      .iter()
+      .map(|line_ending| SharedString::from(format!("Convert to {}", line_ending.label())))
+      .collect();
+    let selected = LineEnding::ALL
+      .iter()
+      .position(|&line_ending| line_ending == current)
+      .unwrap_or(0);
+
+    let editor = self.active_editor(cx);
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Select Line Ending",
+        items,
+        selected,
+        move |ix, _, cx| {
+          editor.update(cx, |editor, cx| {
+            editor.convert_line_endings(LineEnding::ALL[ix], cx)
+          })
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  /// Offers reopening the file or saving it in each encoding.
+  fn select_encoding(&mut self, _: &SelectEncoding, window: &mut Window, cx: &mut Context<Self>) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let editor = self.active_editor(cx);
+    let editor = editor.read(cx);
+    let reopen = if editor.path.is_some() {
+      &Encoding::ALL[..]
+    } else {
+      &[]
+    };
+    let choices = reopen
+      .iter()
+      .map(|&encoding| (true, encoding))
+      .chain(Encoding::ALL.iter().map(|&encoding| (false, encoding)))
+      .collect::<Vec<_>>();
+    let items = choices
+      .iter()
+      .map(|&(reopen, encoding)| {
+        let verb = if reopen { "Reopen" } else { "Save" };
+        SharedString::from(format!("{verb} with {}", encoding.label()))
+      })
+      .collect();
+    let selected = choices
+      .iter()
+      .position(|&(_, encoding)| encoding == editor.encoding)
+      .unwrap_or(0);
+
+    let this = cx.entity().downgrade();
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Select Encoding",
+        items,
+        selected,
+        move |ix, window, cx| {
+          let (reopen, encoding) = choices[ix];
+          this
+            .update(cx, |this, cx| {
+              if reopen {
+                this.reopen_with_encoding(encoding, window, cx)
+              } else {
+                this.save_with_encoding(encoding, window, cx)
+              }
+            })
+            .ok();
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  fn select_indentation(
+    &mut self,
+    _: &SelectIndentation,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    if self.focus_modal(window) {
+      return;
+    }
+    let settings = cx.global::<Settings>();
+    let current = (settings.hard_tabs, settings.tab_size);
+    let items = INDENTATION_CHOICES
+      .iter()
+      .map(|&(hard_tabs, tab_size)| {
+        let kind = if hard_tabs { "Tabs" } else { "Spaces" };
+        SharedString::from(format!("Indent Using {kind}: {tab_size}"))
+      })
+      .collect();
+    let selected = INDENTATION_CHOICES
+      .iter()
+      .position(|&choice| choice == current)
+      .unwrap_or(0);
+
+    let picker = cx.new(|cx| {
+      Picker::new(
+        "Select Indentation",
+        items,
+        selected,
+        |ix, _, cx| {
+          let (hard_tabs, tab_size) = INDENTATION_CHOICES[ix];
+          cx.update_global::<Settings, _>(|settings, _| {
+            settings.hard_tabs = hard_tabs;
+            settings.tab_size = tab_size;
+          });
+        },
+        cx,
+      )
+    });
+    self.open_modal(picker, window, cx);
+  }
+
+  /// Resolves to whether the editor's content may be replaced, asking first
+  /// when it has unsaved changes.
+  fn confirm_discard(
+    &self,
+    editor: &Entity<TextInput>,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) -> impl Future<Output = bool> + use<> {
+    let answer = editor.read(cx).is_dirty().then(|| {
+      window.prompt(
+        PromptLevel::Warning,
+        "Discard unsaved changes?",
+        None,
+        &["Discard", "Cancel"],
+        cx,
+      )
+    });
+    async move {
+      match answer {
+        Some(answer) => answer.await == Ok(0),
+        None => true,
+      }
+    }
+  }
+
+  fn open(&mut self, _: &Open, window: &mut Window, cx: &mut Context<Self>) {
+    let paths = cx.prompt_for_paths(PathPromptOptions {
+      files: true,
+      directories: false,
+      multiple: false,
+      prompt: None,
+    });
+    cx.spawn_in(window, async move |this, cx| {
+      let Ok(Ok(Some(paths))) = paths.await else {
+        return;
+      };
+      if let Some(path) = paths.first() {
+        this
+          .update_in(cx, |this, window, cx| {
+            this.open_path(path, None, window, cx).detach()
+          })
+          .ok();
+      }
+    })
+    .detach();
+  }
+
+  /// Opens `path` in a new tab of the active pane, detecting its encoding
+  /// unless one is given. A file already open there is switched to, and an
+  /// empty untitled buffer is replaced. The task completes once the file
+  /// has loaded.
+  pub fn open_path(
+    &mut self,
+    path: &Path,
+    encoding: Option<Encoding>,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) -> Task<()> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let pane = self.panes[self.active_pane].clone();
+    let open = pane
+      .read(cx)
+      .items
+      .iter()
+      .position(|item| item.read(cx).path.as_ref() == Some(&path));
+    if let Some(ix) = open {
+      pane.update(cx, |pane, cx| pane.activate(ix, cx));
+      return Task::ready(());
+    }
+    let active = pane.read(cx).active_item();
+    let replace = {
+      let active = active.read(cx);
+      active.path.is_none() && active.content.len_bytes() == 0
+    };
+    let editor = if replace {
+      active
+    } else {
+      let editor = Self::new_editor(cx);
+      pane.update(cx, |pane, cx| pane.add_item(editor.clone(), cx));
+      editor
+    };
+    self.load_into(editor, &path, encoding, true, window, cx)
+  }
+
+  /// Loads `path` into `editor`, reporting failures and closing the editor
+  /// then if `close_on_error` is set.
+  fn load_into(
+    &mut self,
+    editor: Entity<TextInput>,
+    path: &Path,
+    encoding: Option<Encoding>,
+    close_on_error: bool,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) -> Task<()> {
+    let load = editor.update(cx, |editor, cx| editor.load(path, encoding, cx));
+    let message = format!("Couldn't open {}", path.display());
+    cx.spawn_in(window, async move |this, cx| {
+      let loaded = load.await;
+      this
+        .update_in(cx, |this, window, cx| match loaded {
+          Ok(()) => this.update_diagnostics(&editor, cx),
+          Err(error) => {
+            if close_on_error {
+              this.remove_editor(&editor, window, cx);
+            }
+            show_error(&message, &error, window, cx);
+          }
+        })
+        .ok();
+    })
+  }
+
+  fn reopen_with_encoding(
+    &mut self,
+    encoding: Encoding,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let editor = self.active_editor(cx);
+    let Some(path) = editor.read(cx).path.clone() else {
+      return;
+    };
+    let discard = self.confirm_discard(&editor, window, cx);
+    cx.spawn_in(window, async move |this, cx| {
+      if discard.await {
+        this
+          .update_in(cx, |this, window, cx| {
+            this
+              .load_into(editor, &path, Some(encoding), false, window, cx)
+              .detach()
+          })
+          .ok();
+      }
+    })
+    .detach();
+  }
+
+  fn save(&mut self, _: &Save, window: &mut Window, cx: &mut Context<Self>) {
+    let encoding = self.active_editor(cx).read(cx).encoding;
+    self.save_with_encoding(encoding, window, cx);
+  }
+
+  fn save_with_encoding(
+    &mut self,
+    encoding: Encoding,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let editor = self.active_editor(cx);
+    match editor.read(cx).path.clone() {
+      Some(path) => self.save_to(&editor, &path, encoding, window, cx),
+      None => self.prompt_for_save_path(editor, encoding, window, cx),
+    }
+  }
+
+  fn save_as(&mut self, _: &SaveAs, window: &mut Window, cx: &mut Context<Self>) {
+    let editor = self.active_editor(cx);
+    let encoding = editor.read(cx).encoding;
+    self.prompt_for_save_path(editor, encoding, window, cx);
+  }
+
+  fn prompt_for_save_path(
+    &mut self,
+    editor: Entity<TextInput>,
+    encoding: Encoding,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let current = editor.read(cx).path.clone();
+    let directory = current
+      .as_deref()
+      .and_then(Path::parent)
+      .map(Path::to_path_buf)
+      .unwrap_or_else(|| self.workspace.clone());
+    let name = current
+      .as_deref()
+      .and_then(Path::file_name)
+      .map(|name| name.to_string_lossy().into_owned());
+    let path = cx.prompt_for_new_path(&directory, name.as_deref());
+    cx.spawn_in(window, async move |this, cx| {
+      let Ok(Ok(Some(path))) = path.await else {
+        return;
+      };
+      this
+        .update_in(cx, |this, window, cx| {
+          this.save_to(&editor, &path, encoding, window, cx)
+        })
+        .ok();
+    })
+    .detach();
+  }
+
+  fn save_to(
+    &mut self,
+    editor: &Entity<TextInput>,
+    path: &Path,
+    encoding: Encoding,
+    window: &mut Window,
+    cx: &mut Context<Self>,
+  ) {
+    let result = editor.update(cx, |editor, cx| editor.save(path, encoding, cx));
+    match result {
+      // The buffer is clean now, so its snapshot is removed.
+      Ok(()) => {
+        let writes = self.prepare_snapshots(cx);
+        cx.background_spawn(async move { writes.write() }).detach();
+      }
+      Err(error) => show_error(
+        &format!("Couldn't save {}", path.display()),
+        &error,
+        window,
+        cx,
+      ),
     }
   }
 
@@ -56,6 +1476,136 @@ impl Zeta {
       KeyBinding::new("home", Home, None),
       KeyBinding::new("end", End, None),
       KeyBinding::new("ctrl-cmd-space", ShowCharacterPalette, None),
+      KeyBinding::new("up", Up, None),
+      KeyBinding::new("down", Down, None),
+      KeyBinding::new("shift-up", SelectUp, None),
+      KeyBinding::new("shift-down", SelectDown, None),
+      KeyBinding::new("enter", Newline, None),
+      KeyBinding::new("tab", Indent, None),
+      KeyBinding::new("shift-tab", Outdent, None),
+      KeyBinding::new("ctrl-m", JumpToMatchingBracket, None),
+      KeyBinding::new("ctrl-shift-m", SelectInsideBrackets, None),
+      KeyBinding::new("cmd-z", Undo, None),
+      KeyBinding::new("cmd-shift-z", Redo, None),
+      KeyBinding::new("alt-up", MoveLineUp, None),
+      KeyBinding::new("alt-down", MoveLineDown, None),
+      KeyBinding::new("alt-shift-down", DuplicateLine, None),
+      KeyBinding::new("cmd-shift-k", DeleteLine, None),
+      KeyBinding::new("cmd-j", JoinLines, None),
+      KeyBinding::new("cmd-shift-enter", InsertLineAbove, None),
+      KeyBinding::new("cmd-enter", InsertLineBelow, None),
+      KeyBinding::new("cmd-k s", SortLines, None),
+      KeyBinding::new("cmd-k r", ReverseLines, None),
+      KeyBinding::new("cmd-k u", UniqueLines, None),
+      KeyBinding::new("cmd-/", ToggleComment, None),
+      KeyBinding::new("alt-shift-a", ToggleBlockComment, None),
+      KeyBinding::new("cmd-k cmd-u", ConvertToUpperCase, None),
+      KeyBinding::new("cmd-k cmd-l", ConvertToLowerCase, None),
+      KeyBinding::new("cmd-k cmd-t", ConvertToTitleCase, None),
+      KeyBinding::new("cmd-k c s", ConvertToSnakeCase, None),
+      KeyBinding::new("cmd-k c c", ConvertToCamelCase, None),
+      KeyBinding::new("cmd-k c k", ConvertToKebabCase, None),
+      KeyBinding::new("cmd-k c p", ConvertToPascalCase, None),
+      KeyBinding::new("cmd-k i s", ConvertIndentationToSpaces, None),
+      KeyBinding::new("cmd-k i t", ConvertIndentationToTabs, None),
+      KeyBinding::new("cmd-k cmd-x", TrimTrailingWhitespace, None),
+      KeyBinding::new("cmd-k l l", ConvertLineEndingsToLf, None),
+      KeyBinding::new("cmd-k l c", ConvertLineEndingsToCrlf, None),
+      KeyBinding::new("cmd-alt-[", ToggleFold, None),
+      KeyBinding::new("cmd-k cmd-0", FoldAll, None),
+      KeyBinding::new("cmd-k cmd-j", UnfoldAll, None),
+      KeyBinding::new("cmd-k cmd-1", FoldAtLevel1, None),
+      KeyBinding::new("cmd-k cmd-2", FoldAtLevel2, None),
+      KeyBinding::new("cmd-k cmd-3", FoldAtLevel3, None),
+      KeyBinding::new("cmd-k cmd-4", FoldAtLevel4, None),
+      KeyBinding::new("cmd-k cmd-5", FoldAtLevel5, None),
+      KeyBinding::new("cmd-o", Open, None),
+      KeyBinding::new("cmd-s", Save, None),
+      KeyBinding::new("cmd-shift-s", SaveAs, None),
+      KeyBinding::new("cmd-n", NewFile, None),
+      KeyBinding::new("cmd-w", CloseTab, None),
+      KeyBinding::new("ctrl-tab", NextTab, None),
+      KeyBinding::new("ctrl-shift-tab", PreviousTab, None),
+      KeyBinding::new("cmd-\\", SplitRight, None),
+      KeyBinding::new("cmd-k cmd-right", FocusNextPane, None),
+      KeyBinding::new("cmd-k cmd-left", FocusPreviousPane, None),
+      KeyBinding::new("cmd-b", ToggleProjectPanel, None),
+      KeyBinding::new("cmd-shift-e", project_panel::ToggleFocus, None),
+      KeyBinding::new("cmd-k cmd-r", RevealActiveFile, None),
+      KeyBinding::new("ctrl-`", ToggleTerminalPanel, None),
+      KeyBinding::new("ctrl-shift-`", NewTerminal, None),
+      KeyBinding::new("cmd-shift-b", RunTask, None),
+      KeyBinding::new("cmd-alt-r", RerunLastTask, None),
+      KeyBinding::new("cmd-k cmd-s", InsertSnippet, None),
+      KeyBinding::new("cmd-shift-v", PasteFromHistory, None),
+      KeyBinding::new("cmd-alt-m", ToggleMacroRecording, None),
+      KeyBinding::new("cmd-alt-p", ReplayMacro, None),
+      KeyBinding::new("cmd-alt-shift-p", ReplayMacroTimes, None),
+      KeyBinding::new("cmd-k cmd-m", SaveMacro, None),
+      KeyBinding::new("cmd-k cmd-e", RunSavedMacro, None),
+      KeyBinding::new("a", NewEntry, Some("ProjectPanel && !editing")),
+      KeyBinding::new("shift-a", NewDirectory, Some("ProjectPanel && !editing")),
+      KeyBinding::new("r", RenameEntry, Some("ProjectPanel && !editing")),
+      KeyBinding::new("f2", RenameEntry, Some("ProjectPanel && !editing")),
+      KeyBinding::new("d", DuplicateEntry, Some("ProjectPanel && !editing")),
+      KeyBinding::new(
+        "cmd-backspace",
+        DeleteEntry,
+        Some("ProjectPanel && !editing"),
+      ),
+      KeyBinding::new("escape", Cancel, None),
+      KeyBinding::new("ctrl-g", go_to_line::Toggle, None),
+    ]);
+
+    let macro_bindings = cx.global::<Settings>().macro_bindings.clone();
+    for (name, keystrokes) in macro_bindings {
+      if keystrokes
+        .split_whitespace()
+        .all(|keystroke| Keystroke::parse(keystroke).is_ok())
+      {
+        cx.bind_keys([KeyBinding::new(
+          &keystrokes,
+          RunMacro { name: name.into() },
+          None,
+        )]);
+      }
+    }
+
+    if cx.global::<Settings>().keymap == KeymapPreset::Emacs {
+      Self::register_emacs(cx);
+    }
+  }
+
+  fn register_emacs(cx: &mut App) {
+    cx.bind_keys([
+      KeyBinding::new("ctrl-a", Home, None),
+      KeyBinding::new("ctrl-e", End, None),
+      KeyBinding::new("ctrl-f", Right, None),
+      KeyBinding::new("ctrl-b", Left, None),
+      KeyBinding::new("ctrl-n", Down, None),
+      KeyBinding::new("ctrl-p", Up, None),
+      KeyBinding::new("alt-f", AltRight, None),
+      KeyBinding::new("alt-b", AltLeft, None),
+      KeyBinding::new("ctrl-d", Delete, None),
+      KeyBinding::new("ctrl-k", KillLine, None),
+      KeyBinding::new("ctrl-w", KillRegion, None),
+      KeyBinding::new("alt-w", KillRingSave, None),
+      KeyBinding::new("ctrl-y", Yank, None),
+      KeyBinding::new("alt-y", YankPop, None),
+      KeyBinding::new("ctrl-space", SetMark, None),
+      KeyBinding::new("ctrl-g", Cancel, None),
+      KeyBinding::new("ctrl-x ctrl-x", ExchangePointAndMark, None),
+      KeyBinding::new("ctrl-x h", SelectAll, None),
+      KeyBinding::new("ctrl-x ctrl-c", Quit, None),
+      KeyBinding::new("ctrl-x ctrl-f", Open, None),
+      KeyBinding::new("ctrl-x ctrl-s", Save, None),
+      KeyBinding::new("ctrl-x ctrl-w", SaveAs, None),
+      KeyBinding::new("ctrl-x k", CloseTab, None),
+      KeyBinding::new("ctrl-x 3", SplitRight, None),
+      KeyBinding::new("ctrl-x o", FocusNextPane, None),
+      KeyBinding::new("ctrl-/", Undo, None),
+      KeyBinding::new("ctrl-x u", Undo, None),
+      KeyBinding::new("alt-g g", go_to_line::Toggle, None),
     ]);
   }
 }
@@ -71,9 +1621,76 @@ impl Render for Zeta {
     div()
       .bg(rgb(0xaaaaaa))
       .track_focus(&self.focus_handle(cx))
+      .on_action(cx.listener(Self::toggle_go_to_line))
+      .on_action(cx.listener(Self::select_language))
+      .on_action(cx.listener(Self::select_indentation))
+      .on_action(cx.listener(Self::select_line_ending))
+      .on_action(cx.listener(Self::select_encoding))
+      .on_action(cx.listener(Self::open))
+      .on_action(cx.listener(Self::save))
+      .on_action(cx.listener(Self::save_as))
+      .on_action(cx.listener(Self::new_file))
+      .on_action(cx.listener(Self::close_tab))
+      .on_action(cx.listener(Self::next_tab))
+      .on_action(cx.listener(Self::previous_tab))
+      .on_action(cx.listener(Self::split_right))
+      .on_action(cx.listener(Self::focus_next_pane))
+      .on_action(cx.listener(Self::focus_previous_pane))
+      .on_action(cx.listener(Self::toggle_project_panel))
+      .on_action(cx.listener(Self::toggle_project_panel_focus))
+      .on_action(cx.listener(Self::reveal_active_file))
+      .on_action(cx.listener(Self::toggle_terminal_panel))
+      .on_action(cx.listener(Self::new_terminal))
+      .on_action(cx.listener(Self::run_task))
+      .on_action(cx.listener(Self::rerun_last_task))
+      .on_action(cx.listener(Self::insert_snippet))
+      .on_action(cx.listener(Self::paste_from_history))
+      .on_action(cx.listener(Self::toggle_macro_recording))
+      .on_action(cx.listener(Self::replay_macro))
+      .on_action(cx.listener(Self::replay_macro_times))
+      .on_action(cx.listener(Self::save_macro))
+      .on_action(cx.listener(Self::run_saved_macro))
+      .on_action(cx.listener(Self::run_macro))
+      .relative()
       .flex()
       .flex_col()
       .size_full()
-      .child(self.text_input.clone())
+      .child(
+        div()
+          .flex()
+          .flex_1()
+          .min_h_0()
+          .gap(px(1.))
+          .when(self.project_panel_open, |this| {
+            this.child(self.project_panel.clone())
+          })
+          .children(self.panes.iter().cloned()),
+      )
+      .when(self.task_panel_open, |this| {
+        this.child(
+          div()
+            .border_t_1()
+            .border_color(rgb(0xaaaaaa))
+            .child(self.task_panel.clone()),
+        )
+      })
+      .when(self.terminal_panel_open, |this| {
+        this.child(
+          div()
+            .border_t_1()
+            .border_color(rgb(0xaaaaaa))
+            .child(self.terminal_panel.clone()),
+        )
+      })
+      .child(self.status_bar.clone())
+      .children(self.modal.as_ref().map(|modal| {
+        div()
+          .absolute()
+          .top(px(40.))
+          .w_full()
+          .flex()
+          .justify_center()
+          .child(modal.view.clone())
+      }))
   }
 }